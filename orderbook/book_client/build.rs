@@ -0,0 +1,6 @@
+fn main()->Result<(),Box<dyn std::error::Error>>{
+  
+    // compiling protos using path on build time
+       tonic_build::compile_protos("proto/orderbook.proto")?;
+       Ok(())
+    }
\ No newline at end of file
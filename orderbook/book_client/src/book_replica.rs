@@ -0,0 +1,138 @@
+use std::{
+    collections::BTreeMap,
+    str::FromStr
+};
+use rust_decimal::Decimal;
+use thiserror::Error;
+use crate::orderbook::{BookDelta, Level, LevelChange, level_change::Action};
+
+/// Errors raised applying a delta, the replica can no longer be trusted and the stream must be subscribed again
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum DeltaError {
+    #[error("{symbol}: sequence gap, expected {expected} received {received}")]
+    SequenceGap { symbol: String, expected: u64, received: u64 },
+
+    #[error("{symbol}: changes received before the first snapshot")]
+    NoSnapshot { symbol: String },
+
+    #[error("{symbol}: {action} of a level not in the book, {exchange} at {price}")]
+    UnknownLevel { symbol: String, action: String, exchange: String, price: String },
+
+    #[error("{symbol}: invalid level change: {reason}")]
+    InvalidChange { symbol: String, reason: String },
+}
+
+/// Levels of one side by exact price then exchange
+type Ladder = BTreeMap<(Decimal, String), Level>;
+
+/// Book rebuilt from the messages of one BookDeltas stream
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BookReplica {
+    symbol: String,
+    /// Sequence of the last delta applied, none before the first snapshot
+    sequence: Option<u64>,
+    publish_time: u64,
+    bids: Ladder,
+    asks: Ladder,
+}
+
+impl BookReplica {
+    pub fn new() -> Self {
+        BookReplica::default()
+    }
+
+    /// Applies the next message of the stream.
+    ///
+    /// A snapshot numbered 1 starts a new stream, any other message must follow the last one applied.
+    pub fn apply(&mut self, delta: &BookDelta) -> Result<(), DeltaError> {
+        let new_stream = delta.snapshot && delta.sequence == 1;
+        match self.sequence {
+            Some(sequence) if !new_stream && delta.sequence != sequence + 1 => {
+                return Err(DeltaError::SequenceGap{
+                    symbol: delta.symbol.clone(),
+                    expected: sequence + 1,
+                    received: delta.sequence,
+                });
+            },
+            None if !delta.snapshot => return Err(DeltaError::NoSnapshot{ symbol: delta.symbol.clone() }),
+            _ => {}
+        }
+        if delta.snapshot {
+            self.bids.clear();
+            self.asks.clear();
+        }
+        for change in delta.bids.iter() {
+            apply_change(&mut self.bids, &delta.symbol, change)?;
+        }
+        for change in delta.asks.iter() {
+            apply_change(&mut self.asks, &delta.symbol, change)?;
+        }
+        self.symbol = delta.symbol.clone();
+        self.sequence = Some(delta.sequence);
+        self.publish_time = delta.publish_time;
+        Ok(())
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    pub fn sequence(&self) -> Option<u64> {
+        self.sequence
+    }
+
+    /// When the server published the last delta applied, in ms since the epoch
+    pub fn publish_time(&self) -> u64 {
+        self.publish_time
+    }
+
+    /// Bids, highest price first
+    pub fn bids(&self) -> impl Iterator<Item = &Level> {
+        self.bids.values().rev()
+    }
+
+    /// Asks, lowest price first
+    pub fn asks(&self) -> impl Iterator<Item = &Level> {
+        self.asks.values()
+    }
+
+    /// Best ask less best bid, none while a side is empty
+    pub fn spread(&self) -> Option<Decimal> {
+        let best_bid = self.bids.keys().next_back()?;
+        let best_ask = self.asks.keys().next()?;
+        Some(best_ask.0 - best_bid.0)
+    }
+}
+
+fn apply_change(ladder: &mut Ladder, symbol: &str, change: &LevelChange) -> Result<(), DeltaError> {
+    let invalid_change = |reason: String| DeltaError::InvalidChange{ symbol: symbol.to_string(), reason: reason };
+    let level = change.level.as_ref().ok_or_else(|| invalid_change("no level".to_string()))?;
+    let price = Decimal::from_str(&level.price_decimal)
+        .map_err(|err| invalid_change(format!("price {:?}: {}", level.price_decimal, err)))?;
+    let key = (price, level.exchange.clone());
+    let action = Action::from_i32(change.action)
+        .ok_or_else(|| invalid_change(format!("unknown action {}", change.action)))?;
+    let known = match action {
+        Action::Insert => {
+            ladder.insert(key, level.clone());
+            true
+        },
+        Action::Update => match ladder.get_mut(&key) {
+            Some(kept) => {
+                *kept = level.clone();
+                true
+            },
+            None => false
+        },
+        Action::Delete => ladder.remove(&key).is_some()
+    };
+    if !known {
+        return Err(DeltaError::UnknownLevel{
+            symbol: symbol.to_string(),
+            action: format!("{:?}", action),
+            exchange: level.exchange.clone(),
+            price: level.price_decimal.clone(),
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,20 @@
+#![deny(
+    //  missing_docs, // not compatible with big_array
+      trivial_casts,
+      trivial_numeric_casts,
+      unsafe_code,
+      unused_import_braces,
+      unused_qualifications,
+      warnings
+  )]
+// fields are named in full, `field: field`, across the crates
+#![allow(clippy::redundant_field_names)]
+
+//! Client side of the OrderbookAggregator service, keeps a book from the BookDeltas stream
+pub mod orderbook;
+pub mod book_replica;
+#[cfg(test)]
+mod tests;
+
+pub use book_replica::*;
+pub use orderbook::orderbook_aggregator_client::OrderbookAggregatorClient;
@@ -0,0 +1 @@
+tonic::include_proto!("orderbook");
\ No newline at end of file
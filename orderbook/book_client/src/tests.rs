@@ -0,0 +1,96 @@
+use std::str::FromStr;
+use rust_decimal::Decimal;
+use pretty_assertions::assert_eq;
+use crate::{
+    BookReplica,
+    DeltaError,
+    orderbook::{BookDelta, Level, LevelChange, level_change::Action}
+};
+
+fn change(action: Action, exchange: &str, price: &str, amount: &str) -> LevelChange {
+    LevelChange {
+        action: action as i32,
+        level: Some(Level {
+            exchange: exchange.to_string(),
+            price: price.parse::<f64>().unwrap(),
+            amount: amount.parse::<f64>().unwrap(),
+            raw_price: price.parse::<f64>().unwrap(),
+            price_decimal: price.to_string(),
+            amount_decimal: amount.to_string(),
+            raw_price_decimal: price.to_string(),
+        }),
+    }
+}
+
+fn delta(sequence: u64, snapshot: bool, bids: Vec<LevelChange>, asks: Vec<LevelChange>) -> BookDelta {
+    BookDelta {
+        symbol: "ETHBTC".to_string(),
+        sequence: sequence,
+        snapshot: snapshot,
+        bids: bids,
+        asks: asks,
+        publish_time: 1833980193,
+    }
+}
+
+fn prices<'a>(levels: impl Iterator<Item = &'a Level>) -> Vec<(String, String, String)> {
+    levels.map(|level| (level.exchange.clone(), level.price_decimal.clone(), level.amount_decimal.clone())).collect()
+}
+
+#[test]
+fn test_replica_applies_deltas() {
+
+    let mut replica = BookReplica::new();
+    replica.apply(&delta(1, true,
+        vec![change(Action::Insert, "Binance", "7", "1"), change(Action::Insert, "Bitstamp", "6.5", "2")],
+        vec![change(Action::Insert, "Binance", "8", "1")])).unwrap();
+    assert_eq!(replica.spread(), Some(Decimal::from_str("1").unwrap()));
+
+    replica.apply(&delta(2, false,
+        vec![change(Action::Delete, "Binance", "7", "1"), change(Action::Update, "Bitstamp", "6.5", "3")],
+        vec![change(Action::Insert, "Bitstamp", "7.5", "1")])).unwrap();
+
+    assert_eq!(replica.sequence(), Some(2));
+    assert_eq!(prices(replica.bids()), vec![("Bitstamp".to_string(), "6.5".to_string(), "3".to_string())]);
+    assert_eq!(prices(replica.asks()), vec![
+        ("Bitstamp".to_string(), "7.5".to_string(), "1".to_string()),
+        ("Binance".to_string(), "8".to_string(), "1".to_string()),
+    ]);
+    assert_eq!(replica.spread(), Some(Decimal::from_str("1").unwrap()));
+
+    // a refresh replaces the whole book
+    replica.apply(&delta(3, true, vec![change(Action::Insert, "Binance", "7.25", "1")], Vec::new())).unwrap();
+    assert_eq!(prices(replica.bids()), vec![("Binance".to_string(), "7.25".to_string(), "1".to_string())]);
+    assert_eq!(replica.asks().count(), 0);
+    assert_eq!(replica.spread(), None);
+}
+
+#[test]
+fn test_replica_checks_sequence() {
+
+    let mut replica = BookReplica::new();
+    assert_eq!(replica.apply(&delta(1, false, Vec::new(), Vec::new())),
+        Err(DeltaError::NoSnapshot { symbol: "ETHBTC".to_string() }));
+
+    replica.apply(&delta(1, true, Vec::new(), Vec::new())).unwrap();
+    assert_eq!(replica.apply(&delta(3, false, Vec::new(), Vec::new())),
+        Err(DeltaError::SequenceGap { symbol: "ETHBTC".to_string(), expected: 2, received: 3 }));
+
+    // a new subscription starts over
+    replica.apply(&delta(1, true, Vec::new(), Vec::new())).unwrap();
+    assert_eq!(replica.sequence(), Some(1));
+}
+
+#[test]
+fn test_replica_rejects_unknown_level() {
+
+    let mut replica = BookReplica::new();
+    replica.apply(&delta(1, true, Vec::new(), Vec::new())).unwrap();
+    let result = replica.apply(&delta(2, false, vec![change(Action::Update, "Binance", "7", "1")], Vec::new()));
+    assert_eq!(result, Err(DeltaError::UnknownLevel {
+        symbol: "ETHBTC".to_string(),
+        action: "Update".to_string(),
+        exchange: "Binance".to_string(),
+        price: "7".to_string(),
+    }));
+}
@@ -0,0 +1,61 @@
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use crate::*;
+
+/// Book metric added to the summaries
+#[derive(Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    Mid,
+    Microprice,
+    Imbalance,
+    Depth,
+    WeightedSpread,
+}
+
+/// Book metrics computed on each update, none when `metrics` is empty
+#[derive(Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AnalyticsConfig {
+    #[serde(default)]
+    pub metrics: Vec<Metric>,
+
+    /// Price levels of each side summed by the imbalance
+    #[serde(default = "default_imbalance_levels")]
+    pub imbalance_levels: usize,
+
+    /// Distances from the mid in basis points, the depth is summed within each
+    #[serde(default)]
+    pub depth_bps: Vec<Decimal>,
+
+    /// Base volume bought and sold by the weighted spread
+    #[serde(default = "default_weighted_spread_volume")]
+    pub weighted_spread_volume: Volume,
+}
+
+fn default_imbalance_levels() -> usize {
+    5
+}
+
+fn default_weighted_spread_volume() -> Volume {
+    Decimal::ONE
+}
+
+impl Default for AnalyticsConfig {
+    fn default() -> Self {
+        AnalyticsConfig{
+            metrics: Vec::new(),
+            imbalance_levels: default_imbalance_levels(),
+            depth_bps: Vec::new(),
+            weighted_spread_volume: default_weighted_spread_volume(),
+        }
+    }
+}
+
+impl AnalyticsConfig {
+    pub fn has(&self, metric: Metric) -> bool {
+        self.metrics.contains(&metric)
+    }
+}
@@ -0,0 +1,54 @@
+use std::collections::BTreeMap;
+use serde::Deserialize;
+
+use crate::*;
+
+/// Bearer tokens of the gRPC API, static or HMAC-SHA256 signed JWTs whose `sub` claim is the client name.
+/// The API is open when neither `tokens` nor `jwt_secret` is set
+#[derive(Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AuthConfig {
+    /// What each client may ask for, by client name, an authenticated client not listed is denied
+    #[serde(default)]
+    pub clients: BTreeMap<String, ClientGrant>,
+
+    /// Static token to the name of its client
+    #[serde(default)]
+    pub tokens: BTreeMap<String, String>,
+
+    #[serde(default)]
+    pub jwt_secret: Option<String>,
+
+    /// Token the webserver presents to the orderbook_server
+    #[serde(default)]
+    pub client_token: Option<String>,
+}
+
+impl AuthConfig {
+    pub fn is_enabled(&self) -> bool {
+        !self.tokens.is_empty() || self.jwt_secret.is_some()
+    }
+}
+
+/// Limits of one client, no limit on what is left empty
+#[derive(Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClientGrant {
+    #[serde(default)]
+    pub symbols: Vec<Symbol>,
+
+    /// Exchange names, as in the requests
+    #[serde(default)]
+    pub exchanges: Vec<String>,
+
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+
+    /// Streaming calls the client may have open at once
+    #[serde(default)]
+    pub max_streams: Option<usize>,
+
+    /// May call the Admin service
+    #[serde(default)]
+    pub admin: bool,
+}
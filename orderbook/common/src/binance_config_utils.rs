@@ -26,6 +26,8 @@ struct BinanceConfiguration {
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct BinanceConfig{
+    pub websocket_base_url: Url,
+    pub snapshot_base_url: Url,
     pub websocket_rate_ms: u32,
     pub snapshot_urls: HashMap<Symbol, Url>,
     pub websocket_urls: HashMap<Symbol, Url>,
@@ -33,6 +35,25 @@ pub struct BinanceConfig{
     pub symbols: Vec<String>
 }
 
+impl BinanceConfig {
+    /// Adds the urls of `symbol` in Binance upper case, the symbol it is streamed as
+    pub fn add_symbol(&mut self, symbol: &str) -> Symbol {
+        let symbol = symbol.to_uppercase();
+        let mut symbol_snapshot_url = self.snapshot_base_url.clone();
+        symbol_snapshot_url.set_query(Some(&format!("symbol={}&limit={}", symbol, self.snapshot_depth)));
+        self.snapshot_urls.insert(symbol.clone(), symbol_snapshot_url);
+
+        let mut symbol_websocket_url = self.websocket_base_url.clone();
+        symbol_websocket_url.set_path(&format!("/ws/{}@depth@{}ms", symbol.to_lowercase(), self.websocket_rate_ms));
+        self.websocket_urls.insert(symbol.clone(), symbol_websocket_url);
+
+        if !self.symbols.contains(&symbol) {
+            self.symbols.push(symbol.clone());
+        }
+        symbol
+    }
+}
+
 impl<'de> Deserialize<'de> for BinanceConfig {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -40,28 +61,18 @@ impl<'de> Deserialize<'de> for BinanceConfig {
     {
         let binance_config: BinanceConfiguration = Deserialize::deserialize(deserializer)?;
 
-        let mut snapshot_hashmap: HashMap<Symbol, Url> = HashMap::new();
-        let mut symbol_websocket_url_hashmap: HashMap<Symbol, Url> = HashMap::new();
-
-        for symbol in binance_config.symbols.iter(){
-            let symbol_lower_case = symbol.to_lowercase();
-            let mut symbol_snapshot_url = binance_config.snapshot_base_url.clone();
-            symbol_snapshot_url.set_query(Some(&format!("symbol={}&limit={}", symbol, binance_config.snapshot_depth)));
-            snapshot_hashmap.insert(symbol.clone(), symbol_snapshot_url);
-
-            let mut symbol_websocket_url = binance_config.websocket_base_url.clone();
-            symbol_websocket_url.set_path(&format!("/ws/{}@depth@{}ms", symbol_lower_case.clone(), &binance_config.websocket_rate_ms));
-            symbol_websocket_url_hashmap.insert(symbol.clone(), symbol_websocket_url); 
-        }
-
-        let config = BinanceConfig{
-            websocket_urls: symbol_websocket_url_hashmap,
+        let mut config = BinanceConfig{
+            websocket_base_url: binance_config.websocket_base_url,
+            snapshot_base_url: binance_config.snapshot_base_url,
+            websocket_urls: HashMap::new(),
             websocket_rate_ms: binance_config.websocket_rate_ms,
-            snapshot_urls: snapshot_hashmap,
+            snapshot_urls: HashMap::new(),
             snapshot_depth: binance_config.snapshot_depth,
-            symbols: binance_config.symbols
-
+            symbols: Vec::new()
         };
+        for symbol in binance_config.symbols.iter(){
+            config.add_symbol(symbol);
+        }
 
         Ok(config)
 
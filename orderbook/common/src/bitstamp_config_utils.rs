@@ -24,11 +24,35 @@ pub struct BitstampConfiguration {
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct BitstampConfig{
     pub websocket_url: Url,
+    pub snapshot_base_url: Url,
     pub websocket_payloads: HashMap<String, Message>,
     pub snapshot_urls: HashMap<String, Url>,
     pub symbols: Vec<String>
 }
 
+impl BitstampConfig {
+    /// Adds the snapshot url and the subscription of `symbol` in Bitstamp lower case, the symbol it is streamed as
+    pub fn add_symbol(&mut self, symbol: &str) -> Symbol {
+        let symbol = symbol.to_lowercase();
+        self.insert_symbol(&symbol);
+        symbol
+    }
+
+    fn insert_symbol(&mut self, symbol: &str) {
+        let path = self.snapshot_base_url.path();
+        let mut new_snapshot_url = self.snapshot_base_url.clone();
+        new_snapshot_url.set_path(&format!("{}/{}", path, symbol));
+        self.snapshot_urls.insert(symbol.to_string(), new_snapshot_url);
+
+        let payload_message =  format!("{{\"event\": \"bts:subscribe\", \"data\": {{ \"channel\": \"order_book_{}\" }} }}", symbol);
+        self.websocket_payloads.insert(symbol.to_string(), Message::Text(payload_message));
+
+        if !self.symbols.iter().any(|known| known == symbol) {
+            self.symbols.push(symbol.to_string());
+        }
+    }
+}
+
 impl<'de> Deserialize<'de> for BitstampConfig {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -36,29 +60,17 @@ impl<'de> Deserialize<'de> for BitstampConfig {
     {
         let bitstamp_config: BitstampConfiguration = Deserialize::deserialize(deserializer)?;
 
-        let mut snapshot_hashmap: HashMap<Symbol, Url> = HashMap::new();
-        let mut websocket_payloads: HashMap<Symbol, Message> = HashMap::new();
-
-        
-        let path = bitstamp_config.snapshot_base_url.path();
-        for symbol in bitstamp_config.symbols.iter(){
-            let mut new_snapshot_url = bitstamp_config.snapshot_base_url.clone();
-            new_snapshot_url.set_path(symbol);
-            new_snapshot_url.set_path(&format!("{}/{}", path, symbol));
-            snapshot_hashmap.insert(symbol.clone(), new_snapshot_url);
-
-            let payload_message =  format!("{{\"event\": \"bts:subscribe\", \"data\": {{ \"channel\": \"order_book_{}\" }} }}", symbol);
-            websocket_payloads.insert(symbol.clone(), Message::Text(payload_message)); 
-        }
- 
-
-        let config = BitstampConfig{
+        let mut config = BitstampConfig{
             websocket_url: bitstamp_config.websocket_base_url,
-            websocket_payloads: websocket_payloads,
-            snapshot_urls: snapshot_hashmap,
-            symbols: bitstamp_config.symbols
-
+            snapshot_base_url: bitstamp_config.snapshot_base_url,
+            websocket_payloads: HashMap::new(),
+            snapshot_urls: HashMap::new(),
+            symbols: Vec::new()
         };
+        // the config symbols are kept in their case
+        for symbol in bitstamp_config.symbols.iter(){
+            config.insert_symbol(symbol);
+        }
         Ok(config)
 
     }
@@ -0,0 +1,14 @@
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+/// Crossed market detection, a crossing is reported once it lasted `persist_ms`
+/// and its fee-adjusted edge reached `min_edge_bps`, whatever its edge when not set
+#[derive(Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CrossedMarketConfig {
+    #[serde(default)]
+    pub min_edge_bps: Option<Decimal>,
+
+    #[serde(default)]
+    pub persist_ms: u64,
+}
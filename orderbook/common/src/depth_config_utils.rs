@@ -0,0 +1,44 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+/// Price levels per side streamed by BookSummary, `default_depth` when the request sets none,
+/// a request above `max_depth` is refused
+#[derive(Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DepthConfig {
+    #[serde(default = "default_depth")]
+    pub default_depth: usize,
+
+    #[serde(default = "default_max_depth")]
+    pub max_depth: usize,
+}
+
+fn default_depth() -> usize {
+    20
+}
+
+fn default_max_depth() -> usize {
+    50
+}
+
+impl Default for DepthConfig {
+    fn default() -> Self {
+        DepthConfig{
+            default_depth: default_depth(),
+            max_depth: default_max_depth(),
+        }
+    }
+}
+
+impl DepthConfig {
+    /// Checked when the config is loaded, the requests without a depth would all be refused otherwise
+    pub fn check(&self) -> Result<()> {
+        if self.max_depth == 0 {
+            return Err(anyhow!("depth max_depth must be above 0"));
+        }
+        if self.default_depth > self.max_depth {
+            return Err(anyhow!("depth default_depth {} is above max_depth {}", self.default_depth, self.max_depth));
+        }
+        Ok(())
+    }
+}
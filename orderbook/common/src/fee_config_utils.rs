@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Deserializer};
+
+use crate::*;
+
+/// Maker and taker fees in basis points
+#[derive(Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FeeSchedule {
+    pub maker_bps: Decimal,
+    pub taker_bps: Decimal,
+}
+
+/// Fees of one exchange, `symbols` overrides the default schedule of some instruments
+#[derive(Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExchangeFees {
+    #[serde(default)]
+    pub maker_bps: Decimal,
+
+    #[serde(default)]
+    pub taker_bps: Decimal,
+
+    #[serde(default, deserialize_with = "to_canonical_keys")]
+    pub symbols: HashMap<Symbol, FeeSchedule>,
+}
+
+impl ExchangeFees {
+    /// Schedule of `symbol`, the exchange default unless overridden
+    pub fn schedule(&self, symbol: &str) -> FeeSchedule {
+        match self.symbols.get(&canonical_symbol(symbol)) {
+            Some(schedule) => schedule.clone(),
+            None => FeeSchedule {
+                maker_bps: self.maker_bps,
+                taker_bps: self.taker_bps,
+            }
+        }
+    }
+}
+
+/// Per-exchange fees, an exchange left out is charged nothing
+#[derive(Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FeesConfig {
+    #[serde(default)]
+    pub binance: ExchangeFees,
+
+    #[serde(default)]
+    pub bitstamp: ExchangeFees,
+}
+
+impl FeesConfig {
+    pub fn exchange_fees(&self, exchange: &Exchange) -> &ExchangeFees {
+        match exchange {
+            Exchange::Binance => &self.binance,
+            Exchange::Bitstamp => &self.bitstamp,
+        }
+    }
+
+    /// Taker fee in basis points charged by `exchange` on `symbol`
+    pub fn taker_bps(&self, exchange: &Exchange, symbol: &str) -> Decimal {
+        self.exchange_fees(exchange).schedule(symbol).taker_bps
+    }
+}
+
+fn to_canonical_keys<'de, D>(deserializer: D) -> Result<HashMap<Symbol, FeeSchedule>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let schedules: HashMap<Symbol, FeeSchedule> = Deserialize::deserialize(deserializer)?;
+    Ok(schedules.into_iter().map(|(symbol, schedule)| (canonical_symbol(&symbol), schedule)).collect())
+}
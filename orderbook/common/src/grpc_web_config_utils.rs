@@ -0,0 +1,12 @@
+use serde::Deserialize;
+
+/// gRPC-Web endpoint of the orderbook_server, served over HTTP/1.1 on `address` to the browsers
+/// of `allowed_origins`, any origin when empty
+#[derive(Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GrpcWebConfig {
+    pub address: String,
+
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+}
@@ -7,11 +7,22 @@
       unused_qualifications,
       warnings
   )]
+// fields are named in full, `field: field`, across the crates
+#![allow(clippy::redundant_field_names)]
  
 
 //! This crate provides common types for gateway_in crates and order_book_server
 pub mod binance_config_utils;
 pub mod bitstamp_config_utils;
+pub mod fee_config_utils;
+pub mod tls_config_utils;
+pub mod auth_config_utils;
+pub mod synthetic_config_utils;
+pub mod crossed_market_config_utils;
+pub mod analytics_config_utils;
+pub mod depth_config_utils;
+pub mod grpc_web_config_utils;
+pub mod metrics;
 #[cfg(test)]
 mod tests;
 
@@ -20,7 +31,8 @@ use std::{
     collections::BTreeMap,
     io::Read,
     fmt,
-    fs::File
+    fs::File,
+    time::{SystemTime, UNIX_EPOCH}
 };
 use url::Url;
 use anyhow::{Context, Result};
@@ -29,6 +41,14 @@ use serde::{Deserialize, Deserializer, de::Error};
 
 pub use binance_config_utils::*;
 pub use bitstamp_config_utils::*;
+pub use fee_config_utils::*;
+pub use tls_config_utils::*;
+pub use auth_config_utils::*;
+pub use synthetic_config_utils::*;
+pub use crossed_market_config_utils::*;
+pub use analytics_config_utils::*;
+pub use depth_config_utils::*;
+pub use grpc_web_config_utils::*;
 
 
 /// ErrCode
@@ -49,7 +69,7 @@ pub type LastUpdateIdTimestamp =u64 ;
 pub type Timestamp = u64;
 
 /// Exchange
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Exchange {
      Binance, Bitstamp
 }
@@ -113,17 +133,101 @@ pub struct SnapshotData {
 
 }
 
+/// State of an exchange feed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeedState {
+    Connecting, Subscribed, Synced, Resyncing, Stale, Disconnected
+}
+
+impl fmt::Display for FeedState {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+       match *self {
+            FeedState::Connecting => write!(f, "Connecting"),
+            FeedState::Subscribed => write!(f, "Subscribed"),
+            FeedState::Synced => write!(f, "Synced"),
+            FeedState::Resyncing => write!(f, "Resyncing"),
+            FeedState::Stale => write!(f, "Stale"),
+            FeedState::Disconnected => write!(f, "Disconnected"),
+       }
+    }
+}
+
+/// FeedStatus, published by the exchange pipelines on every state change
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FeedStatus {
+    pub exchange: Exchange,
+    pub symbol: Symbol,
+    pub state: FeedState,
+    pub reason: Option<String>,
+    /// Milliseconds since the unix epoch
+    pub timestamp: Timestamp
+}
+impl FeedStatus {
+    pub fn new(exchange: Exchange, symbol: Symbol, state: FeedState, reason: Option<String>) -> Self {
+        FeedStatus{
+            exchange: exchange,
+            symbol: symbol,
+            state: state,
+            reason: reason,
+            timestamp: now_millis()
+        }
+    }
+}
+
+/// Symbol used across exchanges for an instrument, exchanges quoting it in lower case included
+pub fn canonical_symbol(symbol: &str) -> Symbol {
+    symbol.to_uppercase()
+}
+
+/// Milliseconds since the unix epoch
+pub fn now_millis() -> Timestamp {
+    SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as Timestamp)
+        .unwrap_or(0)
+}
 
 #[derive(Deserialize)]
 #[derive(Clone, Debug)]
 pub struct ExchangesConfig {
     pub binance: BinanceConfig,
     pub bitstamp: BitstampConfig,
+    #[serde(default)]
+    pub fees: FeesConfig,
+    #[serde(default)]
+    pub synthetics: Vec<SyntheticConfig>,
+    #[serde(default)]
+    pub crossed_market: CrossedMarketConfig,
+    #[serde(default)]
+    pub analytics: AnalyticsConfig,
+    #[serde(default)]
+    pub depth: DepthConfig,
+    #[serde(default)]
+    pub tls: TlsConfig,
+    #[serde(default)]
+    pub auth: AuthConfig,
     pub grpc_server: String,
     pub web_server: String,
     pub client_websocket: String,
+    /// Address the orderbook_server serves its Prometheus metrics on, none when unset
+    #[serde(default)]
+    pub metrics_server: Option<String>,
+    #[serde(default)]
+    pub grpc_web: Option<GrpcWebConfig>,
 }
 
+impl ExchangesConfig {
+    /// Canonical symbols streamed from any exchange
+    pub fn symbols(&self) -> Vec<Symbol> {
+        let mut symbols: Vec<Symbol> = self.binance.symbols.iter()
+            .chain(self.bitstamp.symbols.iter())
+            .map(|symbol| canonical_symbol(symbol))
+            .collect();
+        symbols.sort();
+        symbols.dedup();
+        symbols
+    }
+}
 
 fn to_url<'de, D>(deserializer: D) -> Result<Url, D::Error>
 where
@@ -150,6 +254,7 @@ pub fn setup_config(path: &str) -> Result<ExchangesConfig> {
 
     let config: ExchangesConfig = serde_json::from_str(&buff)
         .context("JSON was not well-formatted config binance")?;
+    config.depth.check()?;
 
     Ok(config)
 }
\ No newline at end of file
@@ -0,0 +1,58 @@
+//! Prometheus metrics shared by the gateway, the orderbook_server and the webserver,
+//! registered once in the default registry and served as text on /metrics
+use lazy_static::lazy_static;
+use prometheus::{
+    Encoder, GaugeVec, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, TextEncoder,
+    register_gauge_vec, register_histogram_vec, register_int_counter_vec, register_int_gauge, register_int_gauge_vec
+};
+use crate::{Exchange, canonical_symbol};
+
+/// Content type of the text exposition format
+pub const CONTENT_TYPE: &str = prometheus::TEXT_FORMAT;
+
+lazy_static! {
+    /// Websocket messages received from the exchanges
+    pub static ref MESSAGES_RECEIVED: IntCounterVec = register_int_counter_vec!(
+        "orderbook_messages_received_total", "Websocket messages received from the exchanges", &["exchange", "symbol"]).unwrap();
+    /// Exchange messages and snapshots that could not be decoded
+    pub static ref DECODE_ERRORS: IntCounterVec = register_int_counter_vec!(
+        "orderbook_decode_errors_total", "Exchange messages and snapshots that could not be decoded", &["exchange", "symbol"]).unwrap();
+    /// Updates not following the previous one
+    pub static ref SEQUENCE_GAPS: IntCounterVec = register_int_counter_vec!(
+        "orderbook_sequence_gaps_total", "Depth updates not following the previous one", &["exchange", "symbol"]).unwrap();
+    /// Books rebuilt from a new snapshot
+    pub static ref RESYNCS: IntCounterVec = register_int_counter_vec!(
+        "orderbook_resyncs_total", "Books rebuilt from a new snapshot", &["exchange", "symbol"]).unwrap();
+    /// Time taken by the REST snapshot requests, failed ones included
+    pub static ref SNAPSHOT_FETCH_SECONDS: HistogramVec = register_histogram_vec!(
+        "orderbook_snapshot_fetch_seconds", "Time taken by the REST snapshot requests", &["exchange"]).unwrap();
+    /// Messages a broadcast receiver missed for being too slow, by channel
+    pub static ref BROADCAST_LAGGED: IntCounterVec = register_int_counter_vec!(
+        "orderbook_broadcast_lagged_total", "Messages a broadcast receiver missed for being too slow", &["channel"]).unwrap();
+    /// Seconds since the last update of each exchange book, set when scraped
+    pub static ref FEED_AGE_SECONDS: GaugeVec = register_gauge_vec!(
+        "orderbook_feed_age_seconds", "Seconds since the last update of each exchange book", &["exchange", "symbol"]).unwrap();
+    /// gRPC streams open, by client
+    pub static ref GRPC_SUBSCRIBERS: IntGaugeVec = register_int_gauge_vec!(
+        "orderbook_grpc_subscribers", "gRPC streams open", &["client"]).unwrap();
+    /// Summaries published, by symbol
+    pub static ref SUMMARIES_PUBLISHED: IntCounterVec = register_int_counter_vec!(
+        "orderbook_summaries_published_total", "Summaries published", &["symbol"]).unwrap();
+    /// Browser websocket sessions open on the webserver
+    pub static ref WEBSOCKET_SESSIONS: IntGauge = register_int_gauge!(
+        "webserver_websocket_sessions", "Browser websocket sessions open").unwrap();
+}
+
+/// Counts one more event of `counter` for the exchange and the canonical symbol
+pub fn inc(counter: &IntCounterVec, exchange: &Exchange, symbol: &str) {
+    counter.with_label_values(&[&exchange.to_string(), &canonical_symbol(symbol)]).inc();
+}
+
+/// Every metric registered, in the text exposition format
+pub fn encode() -> String {
+    let mut buffer = Vec::new();
+    if let Err(err) = TextEncoder::new().encode(&prometheus::gather(), &mut buffer) {
+        log::error!("Error encoding the metrics:\n{}", err);
+    }
+    String::from_utf8(buffer).unwrap_or_default()
+}
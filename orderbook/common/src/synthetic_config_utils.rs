@@ -0,0 +1,13 @@
+use serde::Deserialize;
+
+use crate::*;
+
+/// Virtual instrument implied by two legs quoted in the same currency,
+/// e.g. ETHBTC from the ETHUSD base leg and the BTCUSD quote leg
+#[derive(Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntheticConfig {
+    pub symbol: Symbol,
+    pub base_leg: Symbol,
+    pub quote_leg: Symbol,
+}
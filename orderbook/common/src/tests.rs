@@ -1,8 +1,17 @@
 use url::Url;
 use std::collections::HashMap;
 use tokio_tungstenite::tungstenite::protocol::Message;
+use std::str::FromStr;
+use rust_decimal::Decimal;
 use crate::{
     ExchangesConfig,
+    Exchange,
+    AnalyticsConfig,
+    Metric,
+    DepthConfig,
+    fee_config_utils::*,
+    tls_config_utils::*,
+    auth_config_utils::*,
     binance_config_utils::*,
     bitstamp_config_utils::*
 }; 
@@ -65,6 +74,8 @@ fn test_binance_config(){
     
 
     let expected =  BinanceConfig{
+        websocket_base_url: Url::parse("wss://stream.binance.com:9443/stream").unwrap(),
+        snapshot_base_url: snapshot_url,
         websocket_urls: websocket_hashmap,
         websocket_rate_ms: 100,
         snapshot_urls: snapshot_hashmap,
@@ -72,7 +83,7 @@ fn test_binance_config(){
         symbols: vec!["ETHBTC".to_string(), "LTCBTC".to_string(), "BNBBTC".to_string()]
     };
 
-    let result = serde_json::from_str::<ExchangesConfig>(&data).unwrap();
+    let result = serde_json::from_str::<ExchangesConfig>(data).unwrap();
 
     assert_eq!(expected, result.binance);
 }
@@ -125,11 +136,301 @@ fn test_bitstamp_config(){
 
     let expected =  BitstampConfig{
         websocket_url: websocket_url,
+        snapshot_base_url: snapshot_url.clone(),
         websocket_payloads: websocket_payloads,
         snapshot_urls: snapshot_hashmap,
         symbols: vec!["ETHBTC".to_string(), "LTCBTC".to_string(), "BNBBTC".to_string()]
     };
-    let result = serde_json::from_str::<ExchangesConfig>(&data).unwrap();
+    let result = serde_json::from_str::<ExchangesConfig>(data).unwrap();
  
     assert_eq!(expected, result.bitstamp);
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_add_symbol_in_exchange_case(){
+
+    let data = r#"{
+        "binance": {
+            "websocket_base_url": "wss://stream.binance.com:9443/stream",
+            "websocket_rate_ms": 100,
+            "symbols":["ETHBTC"],
+            "snapshot_depth": 10,
+            "snapshot_base_url":"https://api.binance.com/api/v3/depth"
+       },
+       "bitstamp": {
+           "websocket_base_url": "wss://ws.bitstamp.net",
+           "symbols":["ethbtc"],
+           "snapshot_base_url":"https://www.bitstamp.net/api/v2/order_book"
+       },
+       "grpc_server": "127.0.0.1:50051",
+       "web_server": "127.0.0.1:8080",
+       "client_websocket":"ws://127.0.0.1:8080/rates"
+    }"#;
+    let mut config = serde_json::from_str::<ExchangesConfig>(data).unwrap();
+
+    assert_eq!(config.binance.add_symbol("ltcBTC"), "LTCBTC");
+    assert_eq!(config.binance.snapshot_urls["LTCBTC"].as_str(), "https://api.binance.com/api/v3/depth?symbol=LTCBTC&limit=10");
+    assert_eq!(config.binance.websocket_urls["LTCBTC"].as_str(), "wss://stream.binance.com:9443/ws/ltcbtc@depth@100ms");
+    assert_eq!(config.binance.symbols, vec!["ETHBTC".to_string(), "LTCBTC".to_string()]);
+
+    assert_eq!(config.bitstamp.add_symbol("LTCBTC"), "ltcbtc");
+    assert_eq!(config.bitstamp.snapshot_urls["ltcbtc"].as_str(), "https://www.bitstamp.net/api/v2/order_book/ltcbtc");
+    assert_eq!(config.bitstamp.websocket_payloads["ltcbtc"],
+        Message::Text("{\"event\": \"bts:subscribe\", \"data\": { \"channel\": \"order_book_ltcbtc\" } }".to_string()));
+    // a symbol already configured is not listed twice
+    assert_eq!(config.bitstamp.add_symbol("ETHBTC"), "ethbtc");
+    assert_eq!(config.bitstamp.symbols, vec!["ethbtc".to_string(), "ltcbtc".to_string()]);
+}
+
+#[test]
+fn test_fees_config(){
+
+    let data = r#"{
+        "binance": {
+            "websocket_base_url": "wss://stream.binance.com:9443/stream",
+            "websocket_rate_ms": 100,
+            "symbols":["ETHBTC","LTCBTC","BNBBTC"],
+            "snapshot_depth": 10,
+            "snapshot_base_url":"https://api.binance.com/api/v3/depth"
+       },
+       "bitstamp": {
+           "websocket_base_url": "wss://ws.bitstamp.net",
+           "symbols":["ETHBTC","LTCBTC","BNBBTC"],
+           "snapshot_base_url":"https://www.bitstamp.net/api/v2/order_book"
+       },
+       "fees": {
+           "binance": {
+               "maker_bps": 10,
+               "taker_bps": 10,
+               "symbols": {"bnbbtc": {"maker_bps": 7.5, "taker_bps": 7.5}}
+           }
+       },
+       "grpc_server": "127.0.0.1:50051",
+       "web_server": "127.0.0.1:8080",
+       "client_websocket":"ws://127.0.0.1:8080/rates"
+    }"#;
+
+    let result = serde_json::from_str::<ExchangesConfig>(data).unwrap();
+
+    assert_eq!(result.fees.taker_bps(&Exchange::Binance, "ETHBTC"), Decimal::from_str("10").unwrap());
+    assert_eq!(result.fees.taker_bps(&Exchange::Binance, "BNBBTC"), Decimal::from_str("7.5").unwrap());
+    assert_eq!(result.fees.taker_bps(&Exchange::Bitstamp, "ETHBTC"), Decimal::from_str("0").unwrap());
+    assert_eq!(result.fees.bitstamp, ExchangeFees::default());
+}
+
+#[test]
+fn test_analytics_config(){
+
+    let data = r#"{
+        "binance": {
+            "websocket_base_url": "wss://stream.binance.com:9443/stream",
+            "websocket_rate_ms": 100,
+            "symbols":["ETHBTC"],
+            "snapshot_depth": 10,
+            "snapshot_base_url":"https://api.binance.com/api/v3/depth"
+       },
+       "bitstamp": {
+           "websocket_base_url": "wss://ws.bitstamp.net",
+           "symbols":["ETHBTC"],
+           "snapshot_base_url":"https://www.bitstamp.net/api/v2/order_book"
+       },
+       "analytics": {
+           "metrics": ["mid", "depth", "weighted_spread"],
+           "depth_bps": [10, 50]
+       },
+       "grpc_server": "127.0.0.1:50051",
+       "web_server": "127.0.0.1:8080",
+       "client_websocket":"ws://127.0.0.1:8080/rates"
+    }"#;
+
+    let result = serde_json::from_str::<ExchangesConfig>(data).unwrap();
+
+    assert_eq!(result.analytics.metrics, vec![Metric::Mid, Metric::Depth, Metric::WeightedSpread]);
+    assert!(!result.analytics.has(Metric::Imbalance));
+    assert_eq!(result.analytics.depth_bps, vec![Decimal::from_str("10").unwrap(), Decimal::from_str("50").unwrap()]);
+    assert_eq!(result.analytics.imbalance_levels, AnalyticsConfig::default().imbalance_levels);
+    assert_eq!(result.analytics.weighted_spread_volume, Decimal::ONE);
+}
+
+#[test]
+fn test_depth_config(){
+
+    assert!(DepthConfig::default().check().is_ok());
+    assert!(DepthConfig{default_depth: 10, max_depth: 10}.check().is_ok());
+    assert!(DepthConfig{default_depth: 20, max_depth: 10}.check().is_err());
+    assert!(DepthConfig{default_depth: 0, max_depth: 0}.check().is_err());
+
+    // the max_depth alone keeps the default_depth of 20
+    let depth = serde_json::from_str::<DepthConfig>(r#"{"max_depth": 10}"#).unwrap();
+    assert_eq!(depth, DepthConfig{default_depth: 20, max_depth: 10});
+    assert!(depth.check().is_err());
+}
+
+/// Test certificates, ca.pem signs server.pem (localhost) and client.pem, other_ca.pem signs other_client.pem
+fn tls_file(name: &str) -> String {
+    format!("{}/test_data/tls/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+fn client_files(ca: &str, identity: Option<&str>) -> ClientTlsFiles {
+    ClientTlsFiles{
+        ca_path: ca.to_string(),
+        cert_path: identity.map(|name| tls_file(&format!("{}.pem", name))),
+        key_path: identity.map(|name| tls_file(&format!("{}.key", name))),
+        domain: None,
+    }
+}
+
+/// Accepts TLS connections until the listener is dropped, each sends one byte once the handshake is done
+async fn tls_server(files: ServerTlsFiles) -> String {
+    use tokio::io::AsyncWriteExt;
+    let server = ReloadingTls::server(&files).unwrap();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let acceptor = tokio_rustls::TlsAcceptor::from(server.get());
+            if let Ok(mut stream) = acceptor.accept(stream).await {
+                let _ = stream.write_all(&[1]).await;
+            }
+        }
+    });
+    addr
+}
+
+/// The client has completed the handshake and the server accepted it
+async fn handshake(client: &ReloadingTls<tokio_rustls::rustls::ClientConfig>, addr: &str) -> bool {
+    use tokio::io::AsyncReadExt;
+    match client.connect(addr, "localhost").await {
+        Ok(mut stream) => stream.read_u8().await.is_ok(),
+        Err(_) => false
+    }
+}
+
+#[test]
+fn test_auth_config(){
+
+    let data = r#"{
+        "binance": {
+            "websocket_base_url": "wss://stream.binance.com:9443/stream",
+            "websocket_rate_ms": 100,
+            "symbols":["ETHBTC"],
+            "snapshot_depth": 10,
+            "snapshot_base_url":"https://api.binance.com/api/v3/depth"
+       },
+       "bitstamp": {
+           "websocket_base_url": "wss://ws.bitstamp.net",
+           "symbols":["ETHBTC"],
+           "snapshot_base_url":"https://www.bitstamp.net/api/v2/order_book"
+       },
+       "auth": {
+           "clients": {
+               "webserver": {},
+               "desk": {"symbols": ["ETHBTC"], "exchanges": ["Binance"], "max_depth": 10, "max_streams": 2}
+           },
+           "tokens": {"secret-token": "webserver"},
+           "client_token": "secret-token"
+       },
+       "grpc_server": "127.0.0.1:50051",
+       "web_server": "127.0.0.1:8080",
+       "client_websocket":"ws://127.0.0.1:8080/rates"
+    }"#;
+
+    let result = serde_json::from_str::<ExchangesConfig>(data).unwrap();
+
+    assert!(result.auth.is_enabled());
+    assert_eq!(result.auth.clients["webserver"], ClientGrant::default());
+    assert_eq!(result.auth.clients["desk"].max_depth, Some(10));
+    assert_eq!(result.auth.clients["desk"].exchanges, vec!["Binance".to_string()]);
+    assert_eq!(result.auth.tokens["secret-token"], "webserver");
+    assert_eq!(result.auth.jwt_secret, None);
+    assert!(!AuthConfig::default().is_enabled());
+}
+
+#[test]
+fn test_tls_config(){
+
+    let data = r#"{
+        "binance": {
+            "websocket_base_url": "wss://stream.binance.com:9443/stream",
+            "websocket_rate_ms": 100,
+            "symbols":["ETHBTC"],
+            "snapshot_depth": 10,
+            "snapshot_base_url":"https://api.binance.com/api/v3/depth"
+       },
+       "bitstamp": {
+           "websocket_base_url": "wss://ws.bitstamp.net",
+           "symbols":["ETHBTC"],
+           "snapshot_base_url":"https://www.bitstamp.net/api/v2/order_book"
+       },
+       "tls": {
+           "server": {"cert_path": "server.pem", "key_path": "server.key", "client_ca_path": "ca.pem"},
+           "client": {"ca_path": "ca.pem", "domain": "localhost"}
+       },
+       "grpc_server": "127.0.0.1:50051",
+       "web_server": "127.0.0.1:8080",
+       "client_websocket":"ws://127.0.0.1:8080/rates"
+    }"#;
+
+    let result = serde_json::from_str::<ExchangesConfig>(data).unwrap();
+
+    let server = result.tls.server.unwrap();
+    assert_eq!(server.client_ca_path, Some("ca.pem".to_string()));
+    let client = result.tls.client.unwrap();
+    assert_eq!(client.cert_path, None);
+    assert_eq!(client.domain, Some("localhost".to_string()));
+    assert_eq!(TlsConfig::default(), TlsConfig{server: None, client: None});
+}
+
+#[tokio::test]
+async fn test_tls_mutual_authentication(){
+
+    let addr = tls_server(ServerTlsFiles{
+        cert_path: tls_file("server.pem"),
+        key_path: tls_file("server.key"),
+        client_ca_path: Some(tls_file("ca.pem")),
+    }).await;
+
+    let client = ReloadingTls::client(&client_files(&tls_file("ca.pem"), Some("client"))).unwrap();
+    assert!(handshake(&client, &addr).await);
+    // no certificate, or one signed by another CA
+    let client = ReloadingTls::client(&client_files(&tls_file("ca.pem"), None)).unwrap();
+    assert!(!handshake(&client, &addr).await);
+    let client = ReloadingTls::client(&client_files(&tls_file("ca.pem"), Some("other_client"))).unwrap();
+    assert!(!handshake(&client, &addr).await);
+    // the server certificate is not trusted
+    let client = ReloadingTls::client(&client_files(&tls_file("other_ca.pem"), Some("client"))).unwrap();
+    assert!(!handshake(&client, &addr).await);
+
+    assert!(ReloadingTls::client(&client_files(&tls_file("client.key"), None)).is_err());
+    assert!(ServerTlsFiles{cert_path: tls_file("server.pem"), key_path: tls_file("ca.pem"), client_ca_path: None}.load().is_err());
+}
+
+#[tokio::test]
+async fn test_tls_reload_on_change(){
+
+    let addr = tls_server(ServerTlsFiles{
+        cert_path: tls_file("server.pem"),
+        key_path: tls_file("server.key"),
+        client_ca_path: None,
+    }).await;
+    let dir = std::env::temp_dir().join(format!("orderbook_tls_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let ca_path = dir.join("ca.pem").to_str().unwrap().to_string();
+    let replace_ca = |content: &[u8], age: u64| {
+        std::fs::write(&ca_path, content).unwrap();
+        let file = std::fs::File::options().write(true).open(&ca_path).unwrap();
+        file.set_modified(std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(age)).unwrap();
+    };
+
+    replace_ca(&std::fs::read(tls_file("other_ca.pem")).unwrap(), 1);
+    let client = ReloadingTls::client(&client_files(&ca_path, None)).unwrap();
+    assert!(!handshake(&client, &addr).await);
+
+    // an invalid file keeps the previous roots
+    replace_ca(b"not a certificate", 2);
+    assert!(!handshake(&client, &addr).await);
+
+    replace_ca(&std::fs::read(tls_file("ca.pem")).unwrap(), 3);
+    assert!(handshake(&client, &addr).await);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,206 @@
+use std::{
+    fs::{self, File},
+    io::BufReader,
+    sync::{Arc, Mutex},
+    time::SystemTime
+};
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use tokio::net::TcpStream;
+use tokio_rustls::{
+    client,
+    rustls::{
+        internal::pemfile, AllowAnyAuthenticatedClient, Certificate, ClientConfig, NoClientAuth, PrivateKey,
+        RootCertStore, ServerConfig
+    },
+    webpki::DNSNameRef,
+    TlsConnector
+};
+
+/// The tokio-rustls used by tonic, to accept and open TLS connections with the same types
+pub use tokio_rustls;
+
+/// gRPC only runs over HTTP/2
+const ALPN_H2: &[u8] = b"h2";
+
+/// TLS of the gRPC connections, plaintext on the side not configured
+#[derive(Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TlsConfig {
+    #[serde(default)]
+    pub server: Option<ServerTlsFiles>,
+
+    #[serde(default)]
+    pub client: Option<ClientTlsFiles>,
+}
+
+/// PEM files of the orderbook_server, clients must present a certificate signed by
+/// `client_ca_path` when set (mutual TLS)
+#[derive(Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ServerTlsFiles {
+    pub cert_path: String,
+    pub key_path: String,
+
+    #[serde(default)]
+    pub client_ca_path: Option<String>,
+}
+
+/// PEM files of the gRPC clients, `ca_path` holds the roots trusted to sign the server certificate,
+/// the identity is presented to a server requiring mutual TLS
+#[derive(Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClientTlsFiles {
+    pub ca_path: String,
+
+    #[serde(default)]
+    pub cert_path: Option<String>,
+
+    #[serde(default)]
+    pub key_path: Option<String>,
+
+    /// Name checked against the server certificate, the host of the server address when not set
+    #[serde(default)]
+    pub domain: Option<String>,
+}
+
+impl ServerTlsFiles {
+    fn paths(&self) -> Vec<String> {
+        let mut paths = vec![self.cert_path.clone(), self.key_path.clone()];
+        paths.extend(self.client_ca_path.iter().cloned());
+        paths
+    }
+
+    pub fn load(&self) -> Result<ServerConfig> {
+        let mut config = match &self.client_ca_path {
+            None => ServerConfig::new(NoClientAuth::new()),
+            Some(path) => ServerConfig::new(AllowAnyAuthenticatedClient::new(load_roots(path)?))
+        };
+        config.set_single_cert(load_certs(&self.cert_path)?, load_key(&self.key_path)?)
+            .with_context(|| format!("invalid server identity {} {}", self.cert_path, self.key_path))?;
+        config.set_protocols(&[ALPN_H2.to_vec()]);
+        Ok(config)
+    }
+}
+
+impl ClientTlsFiles {
+    fn paths(&self) -> Vec<String> {
+        let mut paths = vec![self.ca_path.clone()];
+        paths.extend(self.cert_path.iter().cloned());
+        paths.extend(self.key_path.iter().cloned());
+        paths
+    }
+
+    pub fn load(&self) -> Result<ClientConfig> {
+        let mut config = ClientConfig::new();
+        config.root_store = load_roots(&self.ca_path)?;
+        match (&self.cert_path, &self.key_path) {
+            (Some(cert_path), Some(key_path)) => {
+                config.set_single_client_cert(load_certs(cert_path)?, load_key(key_path)?)
+                    .with_context(|| format!("invalid client identity {} {}", cert_path, key_path))?;
+            },
+            (None, None) => {},
+            _ => return Err(anyhow!("the client identity needs both cert_path and key_path"))
+        }
+        config.set_protocols(&[ALPN_H2.to_vec()]);
+        Ok(config)
+    }
+}
+
+fn load_certs(path: &str) -> Result<Vec<Certificate>> {
+    let file = File::open(path).with_context(|| format!("cannot open {}", path))?;
+    let certs = pemfile::certs(&mut BufReader::new(file))
+        .map_err(|_| anyhow!("invalid certificate in {}", path))?;
+    if certs.is_empty() {
+        return Err(anyhow!("no certificate in {}", path));
+    }
+    Ok(certs)
+}
+
+/// First PKCS8 or RSA key of the file
+fn load_key(path: &str) -> Result<PrivateKey> {
+    let file = File::open(path).with_context(|| format!("cannot open {}", path))?;
+    let mut keys = pemfile::pkcs8_private_keys(&mut BufReader::new(file))
+        .map_err(|_| anyhow!("invalid private key in {}", path))?;
+    if keys.is_empty() {
+        let file = File::open(path).with_context(|| format!("cannot open {}", path))?;
+        keys = pemfile::rsa_private_keys(&mut BufReader::new(file))
+            .map_err(|_| anyhow!("invalid private key in {}", path))?;
+    }
+    keys.into_iter().next().ok_or_else(|| anyhow!("no private key in {}", path))
+}
+
+fn load_roots(path: &str) -> Result<RootCertStore> {
+    let file = File::open(path).with_context(|| format!("cannot open {}", path))?;
+    let mut roots = RootCertStore::empty();
+    match roots.add_pem_file(&mut BufReader::new(file)) {
+        Ok((valid, _)) if valid > 0 => Ok(roots),
+        _ => Err(anyhow!("no valid CA certificate in {}", path))
+    }
+}
+
+/// TLS config rebuilt from its files when one of them is modified, checked on each connection.
+/// New files which do not load are logged and the previous config is kept
+pub struct ReloadingTls<T> {
+    paths: Vec<String>,
+    load: Box<dyn Fn() -> Result<T> + Send + Sync>,
+    current: Mutex<(Vec<Option<SystemTime>>, Arc<T>)>,
+}
+
+impl<T> ReloadingTls<T> {
+    fn new(paths: Vec<String>, load: Box<dyn Fn() -> Result<T> + Send + Sync>) -> Result<Self> {
+        let modified = modified_times(&paths);
+        let config = load()?;
+        Ok(ReloadingTls{
+            paths: paths,
+            load: load,
+            current: Mutex::new((modified, Arc::new(config))),
+        })
+    }
+
+    pub fn get(&self) -> Arc<T> {
+        let mut current = self.current.lock().unwrap_or_else(|err| err.into_inner());
+        let modified = modified_times(&self.paths);
+        if modified != current.0 {
+            match (self.load)() {
+                Ok(config) => {
+                    log::info!("TLS config reloaded from {:?}", self.paths);
+                    current.1 = Arc::new(config);
+                },
+                Err(err) => log::warn!("Warning in TLS reload:\n{:?}, keeping the previous config", err)
+            }
+            current.0 = modified;
+        }
+        current.1.clone()
+    }
+}
+
+fn modified_times(paths: &[String]) -> Vec<Option<SystemTime>> {
+    paths.iter()
+        .map(|path| fs::metadata(path).and_then(|metadata| metadata.modified()).ok())
+        .collect()
+}
+
+impl ReloadingTls<ServerConfig> {
+    pub fn server(files: &ServerTlsFiles) -> Result<Self> {
+        let loaded = files.clone();
+        ReloadingTls::new(files.paths(), Box::new(move || loaded.load()))
+    }
+}
+
+impl ReloadingTls<ClientConfig> {
+    pub fn client(files: &ClientTlsFiles) -> Result<Self> {
+        let loaded = files.clone();
+        ReloadingTls::new(files.paths(), Box::new(move || loaded.load()))
+    }
+
+    /// Opens a TLS connection to `addr` (host:port), checking the server certificate against `domain`
+    pub async fn connect(&self, addr: &str, domain: &str) -> Result<client::TlsStream<TcpStream>> {
+        let domain = DNSNameRef::try_from_ascii_str(domain)
+            .map_err(|_| anyhow!("invalid domain name {}", domain))?;
+        let stream = TcpStream::connect(addr).await.with_context(|| format!("cannot connect to {}", addr))?;
+        let stream = TlsConnector::from(self.get()).connect(domain, stream).await
+            .with_context(|| format!("TLS handshake with {} failed", addr))?;
+        Ok(stream)
+    }
+}
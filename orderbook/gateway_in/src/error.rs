@@ -0,0 +1,109 @@
+use thiserror::Error;
+use tokio::sync::broadcast::error::RecvError;
+use common::{Exchange, Symbol, metrics};
+
+/// What a pipeline should do after an error
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Recovery {
+    /// Drop the faulty message and keep going
+    Skip,
+    /// The local book can no longer be trusted, rebuild it from a fresh snapshot
+    Resync,
+    /// Tear the pipeline down and start it again
+    Retry,
+    /// Stop the pipeline for good
+    Abort,
+}
+
+/// Errors raised by the exchange pipelines, every variant carries the exchange and symbol it came from
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum GatewayError {
+    #[error("{exchange} {symbol}: missing configuration: {what}")]
+    Config { exchange: Exchange, symbol: Symbol, what: String },
+
+    #[error("{exchange} {symbol}: unknown symbol: {reason}")]
+    UnknownSymbol { exchange: Exchange, symbol: Symbol, reason: String },
+
+    #[error("{exchange} {symbol}: websocket connection failed: {reason}")]
+    Connect { exchange: Exchange, symbol: Symbol, reason: String },
+
+    #[error("{exchange} {symbol}: upstream stream ended")]
+    Disconnected { exchange: Exchange, symbol: Symbol },
+
+    #[error("{exchange} {symbol}: subscription failed: {reason}")]
+    Subscribe { exchange: Exchange, symbol: Symbol, reason: String },
+
+    #[error("{exchange} {symbol}: could not decode message: {reason}")]
+    Decode { exchange: Exchange, symbol: Symbol, reason: String },
+
+    #[error("{exchange} {symbol}: sequence gap, expected {expected} received {received}")]
+    SequenceGap { exchange: Exchange, symbol: Symbol, expected: u64, received: u64 },
+
+    #[error("{exchange} {symbol}: snapshot fetch failed: {reason}")]
+    SnapshotFetch { exchange: Exchange, symbol: Symbol, reason: String },
+
+    #[error("{exchange} {symbol}: channel {channel} closed")]
+    ChannelClosed { exchange: Exchange, symbol: Symbol, channel: String },
+
+    #[error("{exchange} {symbol}: channel {channel} lagged, {skipped} messages skipped")]
+    Lagged { exchange: Exchange, symbol: Symbol, channel: String, skipped: u64 },
+}
+
+impl GatewayError {
+    pub fn exchange(&self) -> &Exchange {
+        match self {
+            GatewayError::Config { exchange, .. } |
+            GatewayError::UnknownSymbol { exchange, .. } |
+            GatewayError::Connect { exchange, .. } |
+            GatewayError::Disconnected { exchange, .. } |
+            GatewayError::Subscribe { exchange, .. } |
+            GatewayError::Decode { exchange, .. } |
+            GatewayError::SequenceGap { exchange, .. } |
+            GatewayError::SnapshotFetch { exchange, .. } |
+            GatewayError::ChannelClosed { exchange, .. } |
+            GatewayError::Lagged { exchange, .. } => exchange
+        }
+    }
+
+    pub fn symbol(&self) -> &Symbol {
+        match self {
+            GatewayError::Config { symbol, .. } |
+            GatewayError::UnknownSymbol { symbol, .. } |
+            GatewayError::Connect { symbol, .. } |
+            GatewayError::Disconnected { symbol, .. } |
+            GatewayError::Subscribe { symbol, .. } |
+            GatewayError::Decode { symbol, .. } |
+            GatewayError::SequenceGap { symbol, .. } |
+            GatewayError::SnapshotFetch { symbol, .. } |
+            GatewayError::ChannelClosed { symbol, .. } |
+            GatewayError::Lagged { symbol, .. } => symbol
+        }
+    }
+
+    /// Maps a broadcast receive error on `channel`, the messages skipped are counted in the lag metric
+    pub fn from_recv_error(err: RecvError, exchange: Exchange, symbol: Symbol, channel: &str) -> Self {
+        match err {
+            RecvError::Lagged(skipped) => {
+                metrics::BROADCAST_LAGGED.with_label_values(&[channel]).inc_by(skipped);
+                GatewayError::Lagged { exchange: exchange, symbol: symbol, channel: channel.to_string(), skipped: skipped }
+            },
+            RecvError::Closed => GatewayError::ChannelClosed { exchange: exchange, symbol: symbol, channel: channel.to_string() }
+        }
+    }
+
+    /// Recovery decision for this error
+    pub fn recovery(&self) -> Recovery {
+        match self {
+            GatewayError::Config { .. } => Recovery::Abort,
+            GatewayError::UnknownSymbol { .. } => Recovery::Abort,
+            GatewayError::Connect { .. } => Recovery::Retry,
+            GatewayError::Disconnected { .. } => Recovery::Retry,
+            GatewayError::Subscribe { .. } => Recovery::Retry,
+            GatewayError::Decode { .. } => Recovery::Skip,
+            GatewayError::SequenceGap { .. } => Recovery::Resync,
+            GatewayError::SnapshotFetch { .. } => Recovery::Retry,
+            GatewayError::ChannelClosed { .. } => Recovery::Abort,
+            GatewayError::Lagged { .. } => Recovery::Resync,
+        }
+    }
+}
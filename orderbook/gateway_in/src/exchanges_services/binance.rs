@@ -1,65 +1,108 @@
 
 use std::collections::BTreeMap;
-use anyhow::{Context, Result};
 use futures_util::StreamExt;
 use url::Url;
 use tokio_tungstenite::{
     connect_async, 
     tungstenite::protocol::Message,
 };
-use tokio::sync::{broadcast, mpsc};
+use tokio::{
+    sync::{broadcast, mpsc},
+    task::JoinSet
+};
+use tokio_util::sync::CancellationToken;
 
 use async_trait::async_trait;
 use common::*;
 use crate::*;
 use crate::settings::DeserializeSettings;
 use crate::exchanges_services::*;
+use crate::error::{GatewayError, Recovery};
+
+async fn fetch_snapshot(symbol: &Symbol, snapshot_url: &Url) -> Result<SnapshotData, GatewayError> {
+    let timer = metrics::SNAPSHOT_FETCH_SECONDS.with_label_values(&[&Exchange::Binance.to_string()]).start_timer();
+    let snapshot = get_snapshot(snapshot_url.clone()).await;
+    timer.observe_duration();
+    let snapshot = snapshot
+        .map_err(|err| GatewayError::SnapshotFetch { exchange: Exchange::Binance, symbol: symbol.clone(), reason: err.to_string() })?;
+    <BinanceService as ExchangeService>::deserialize_snapshot(symbol.clone(), snapshot)
+        .map_err(|err| {
+            metrics::inc(&metrics::DECODE_ERRORS, &Exchange::Binance, symbol);
+            GatewayError::Decode { exchange: Exchange::Binance, symbol: symbol.clone(), reason: err.to_string() }
+        })
+}
 
+/// Binance pipeline for one symbol of the config
 pub struct BinanceService{
-    pub config: BinanceConfig
+    pub config: BinanceConfig,
+    pub symbol: Symbol
 }
 impl BinanceService{
-    pub fn new(config: BinanceConfig) -> Self{
+    pub fn new(config: BinanceConfig, symbol: Symbol) -> Self{
         BinanceService{
-            config: config
+            config: config,
+            symbol: symbol
         }
     }
 }
 #[async_trait]
 impl ExchangeInit for BinanceService{
-    async fn stream_init_task(&mut self, output_stream_tx_ch: Sender<SnapshotData>) -> Result<()> {
-        let task_name = "--Binance Stream Management Task--";
+    async fn check_symbol(&mut self) -> Result<(), GatewayError> {
+        let snapshot_url = self.config.snapshot_urls.get(&self.symbol)
+            .ok_or_else(|| GatewayError::Config { exchange: Exchange::Binance, symbol: self.symbol.clone(), what: "snapshot url".to_string() })?;
+        check_snapshot(&Exchange::Binance, &self.symbol, snapshot_url, <BinanceService as ExchangeService>::deserialize_snapshot).await
+    }
 
-        let symbol = self.config.symbols.get(0)
-            .context(format!("Error in {:?}:\ninput_rx_ch:\n", task_name))?;
+    async fn stream_init_task(&mut self, output_stream_tx_ch: Sender<SnapshotData>, 
+        status_tx_ch: Sender<FeedStatus>, cancel_token: CancellationToken) -> Result<(), GatewayError> {
+        let exchange = Exchange::Binance;
+
+        let symbol = &self.symbol;
 
         let web_socket_url = self.config.websocket_urls.get(symbol)
-            .context(format!("Error in {:?}:\ninput_rx_ch:\n", task_name))?;
+            .ok_or_else(|| GatewayError::Config { exchange: exchange.clone(), symbol: symbol.clone(), what: "websocket url".to_string() })?;
 
         let snapshot_url = self.config.snapshot_urls.get(symbol)
-            .context(format!("Error in {:?}:\ninput_rx_ch:\n", task_name))?;
-
-        let (ws_stream, _) = connect_async(web_socket_url).await
-            .context(format!("Error in {:?}:\ninput_rx_ch:\n", task_name))?;
+            .ok_or_else(|| GatewayError::Config { exchange: exchange.clone(), symbol: symbol.clone(), what: "snapshot url".to_string() })?;
+
+        publish_status(&status_tx_ch, &exchange, symbol, FeedState::Connecting, None);
+        let (ws_stream, _) = match connect_async(web_socket_url).await {
+            Ok(connection) => connection,
+            Err(err) => {
+                let result = Err(GatewayError::Connect { exchange: exchange.clone(), symbol: symbol.clone(), reason: err.to_string() });
+                publish_end(&status_tx_ch, &exchange, symbol, &result);
+                return result;
+            }
+        };
+        // The stream is picked by the url, connected means subscribed
+        publish_status(&status_tx_ch, &exchange, symbol, FeedState::Subscribed, None);
 
         let (writer, reader) = ws_stream.split();
+        // Every task of the pipeline stops with this token, cancelled by the caller or when the snapshot task ends
+        let pipeline_token = cancel_token.child_token();
+        let mut tasks = JoinSet::new();
 
         let (writer_tx_ch, writer_rx_ch): (mpsc::Sender<Message>, mpsc::Receiver<Message>) = mpsc::channel(20);
         
-        let writer_settings = WriterSettings::new(symbol.clone(), writer, writer_rx_ch);
-        tokio::spawn(writer_task(writer_settings));
+        let writer_settings = WriterSettings::new(symbol.clone(), writer, writer_rx_ch, pipeline_token.clone());
+        tasks.spawn(writer_task(writer_settings));
 
         let (reader_tx_ch, reader_rx_ch) = broadcast::channel(10);
-        let reader_settings = ReaderSettings::new(symbol.clone(), reader, reader_tx_ch);
-        tokio::spawn(reader_task(reader_settings));
+        let reader_settings = ReaderSettings::new(symbol.clone(), reader, reader_tx_ch, pipeline_token.clone());
+        tasks.spawn(reader_task(reader_settings));
 
         let (output_tx_ch, output_rx_ch) =  broadcast::channel(10);    
-        let deserialize_settings = DeserializeSettings::new(symbol.clone(), reader_rx_ch, output_tx_ch, writer_tx_ch);
-        tokio::spawn(<BinanceService as ExchangeService>::stream_management_task(deserialize_settings));
+        let deserialize_settings = DeserializeSettings::new(symbol.clone(), reader_rx_ch, output_tx_ch, writer_tx_ch, pipeline_token.clone());
+        tasks.spawn(<BinanceService as ExchangeService>::stream_management_task(deserialize_settings));
         
-        <BinanceService as ExchangeService>::
-            snapshot_task(symbol.clone(), snapshot_url.clone(), output_rx_ch, output_stream_tx_ch).await?;
-        Ok(())
+        let result = tokio::select! {
+            result = <BinanceService as ExchangeService>::
+                snapshot_task(symbol.clone(), snapshot_url.clone(), output_rx_ch, output_stream_tx_ch, status_tx_ch.clone()) => result,
+            _ = pipeline_token.cancelled() => Ok(())
+        };
+        shutdown_pipeline(&pipeline_token, &mut tasks).await;
+        publish_end(&status_tx_ch, &exchange, symbol, &result);
+        result
     }
 }
 
@@ -69,39 +112,36 @@ impl ExchangeService for BinanceService{
     async fn stream_management_task(mut deserialize_settings: DeserializeSettings){
       let task_name = "--Binance Stream Management Task--";
       log::info!("{:?} Init", task_name);
+      let cancel_token = deserialize_settings.cancel_token.clone();
       loop{
-          match <BinanceService as ExchangeService>::websocket_msg_process(&mut deserialize_settings).await {
-              Ok(_)=> continue,
-              Err(err) => {
-                  log::error!("{:?}", err);
-
-                  match &err.downcast_ref::<broadcast::error::RecvError>() {
-                      Some(err) => {
-                          match err {
-                              broadcast::error::RecvError::Lagged(x) => {
-                                  log::trace!("Trace in {:?}:\ninput_rx_ch lagged:\n{:?}\n", task_name, x); 
-                                  continue;
-                              },
-                              broadcast::error::RecvError::Closed => {
-                                  log::warn!("Warning in {:?}:\ninput_rx_ch closed:\n", task_name); 
-                                  break;
-                              }
-                  
-                          }
-                      },
-                      None =>  log::warn!("Warning in {:?}:\ninput_rx_ch closed:\n", task_name)
-                  };          
-              }
+          let result = tokio::select! {
+              _ = cancel_token.cancelled() => break,
+              result = <BinanceService as ExchangeService>::websocket_msg_process(&mut deserialize_settings) => result
           };
+          if let Err(err) = result {
+              match err.recovery() {
+                  // Messages lost between the reader and here surface as a sequence gap in the snapshot task
+                  Recovery::Skip | Recovery::Resync => {
+                      log::warn!("Warning in {:?}:\n{}", task_name, err);
+                      continue;
+                  },
+                  Recovery::Retry | Recovery::Abort => {
+                      log::error!("Error in {:?}:\n{}", task_name, err);
+                      break;
+                  }
+              }
+          }
       } 
       log::info!("{:?} End", task_name);
     }
 
-    async fn websocket_msg_process(deserialize_settings: &mut DeserializeSettings) -> Result<()> {
+    async fn websocket_msg_process(deserialize_settings: &mut DeserializeSettings) -> Result<(), GatewayError> {
         let task_name = "--Binance Stream Management Task--";
+        let exchange = Exchange::Binance;
+        let symbol = deserialize_settings.symbol.clone();
 
         let input_msg = deserialize_settings.input_rx_ch.recv().await
-            .context(format!("Error in {:?}:\ninput_rx_ch:\n", task_name))?;
+            .map_err(|err| GatewayError::from_recv_error(err, exchange.clone(), symbol.clone(), "input_rx_ch"))?;
         log::trace!("{:?}:\nReceived message from reader\n{:?}", task_name, input_msg);
 
         match input_msg {
@@ -111,17 +151,21 @@ impl ExchangeService for BinanceService{
                 let pong_msg = Message::Pong(ping_data);
 
                 deserialize_settings.writer_tx_ch.send(pong_msg).await
-                    .context(format!("Error in {:?}:\nSending pong:\n", task_name))?;
+                    .map_err(|_| GatewayError::ChannelClosed { exchange: exchange.clone(), symbol: symbol.clone(), channel: "writer_tx_ch".to_string() })?;
                 log::trace!("Trace in {:?}:\nSent pong", task_name)
             },
             Message::Pong(pong_data) => log::warn!("Warning in {:?}:\nPong message received:\n {:?}", task_name, pong_data),
             Message::Text(text_data) => {
+                metrics::inc(&metrics::MESSAGES_RECEIVED, &exchange, &symbol);
 
                 let data = <BinanceService as ExchangeService>::deserialize_stream(text_data)
-                    .context(format!("Error in {:?}:\nDesirializing:\n", task_name))?;
+                    .map_err(|err| {
+                        metrics::inc(&metrics::DECODE_ERRORS, &exchange, &symbol);
+                        GatewayError::Decode { exchange: exchange.clone(), symbol: symbol.clone(), reason: err.to_string() }
+                    })?;
 
                 deserialize_settings.output_tx_ch.send(data)
-                    .context(format!("Error in {:?}:\nSending data:\n", task_name))?;
+                    .map_err(|_| GatewayError::ChannelClosed { exchange: exchange.clone(), symbol: symbol.clone(), channel: "output_tx_ch".to_string() })?;
 
             },
             Message::Binary(_) => log::warn!("Warning in {:?}: binary data sent:\n", task_name)
@@ -155,23 +199,20 @@ impl ExchangeService for BinanceService{
     /// 8 If the quantity is 0, remove the price level.
     /// 
     /// 9 Receiving an event that removes a price level that is not in your local order book can happen and is normal.
-    /// 
-
     async fn snapshot_task(
         symbol: Symbol, 
         snapshot_url: Url, 
         mut output_rx_ch: Receiver<DepthData>, 
-        output_stream_tx_ch: Sender<SnapshotData>) -> Result<()> {
+        output_stream_tx_ch: Sender<SnapshotData>,
+        status_tx_ch: Sender<FeedStatus>) -> Result<(), GatewayError> {
 
         let task_name = "--Binance Snapshot Task Task--";
-        let snapshot = get_snapshot(snapshot_url.clone()).await
-            .context(format!("Error in {:?}:\n({:?})get_snapshot:\n", task_name, 1))?;
+        let exchange = Exchange::Binance;
 
-        let mut snapshot_message = <BinanceService as ExchangeService>::
-            deserialize_snapshot(symbol.clone(), snapshot)
-            .context(format!("Error in {:?}:\n({:?}) deserialize_snapshot:\n", task_name,1))?;
+        let mut snapshot_message = fetch_snapshot(&symbol, &snapshot_url).await?;
 
         let mut is_first_event = true;
+        let mut is_stale = false;
         let mut previuos_event_last_timestamp:u64 = 0;
             
         let update_book_func = |message: DepthData, snapshot_message: &mut SnapshotData| { 
@@ -195,48 +236,73 @@ impl ExchangeService for BinanceService{
             }
 
             output_stream_tx_ch.send(snapshot_message.clone())
+                .map_err(|_| GatewayError::ChannelClosed { exchange: exchange.clone(), symbol: symbol.clone(), channel: "output_stream_tx_ch".to_string() })
         };
 
-        while let Ok(message) = output_rx_ch.recv().await{
-            if is_first_event && message.last_update_id_timestamp <= snapshot_message.timestamp {
-                continue;      
-            }
-            else if is_first_event && (message.first_update_id_timestamp <= (snapshot_message.timestamp + 1)) 
-                && (message.last_update_id_timestamp >= (snapshot_message.timestamp + 1)){
-
-                is_first_event = false;
-                previuos_event_last_timestamp = message.last_update_id_timestamp;
-
-                update_book_func(message, &mut snapshot_message)?;
-            }
-            else if !is_first_event && message.first_update_id_timestamp == (previuos_event_last_timestamp + 1){
-                update_book_func(message, &mut snapshot_message)?;
+        loop {
+            let received = match tokio::time::timeout(STALE_AFTER, output_rx_ch.recv()).await {
+                Ok(received) => received,
+                Err(_) => {
+                    if !is_stale {
+                        is_stale = true;
+                        publish_status(&status_tx_ch, &exchange, &symbol, FeedState::Stale, Some(format!("no update for {:?}", STALE_AFTER)));
+                    }
+                    continue;
+                }
+            };
+            if is_stale && received.is_ok() {
+                is_stale = false;
+                if !is_first_event {
+                    publish_status(&status_tx_ch, &exchange, &symbol, FeedState::Synced, None);
+                }
             }
-            else{
-                let snapshot = get_snapshot(snapshot_url.clone()).await
-                    .context(format!("Error in {:?}:\n{:?})get_snapshot:\n", task_name, 2))?;
-
-                snapshot_message =  <BinanceService as ExchangeService>::
-                    deserialize_snapshot(symbol.clone(), snapshot)
-                        .context(format!("Error in {:?}:\n({:?}) deserialize_snapshot:\n", task_name, 2))?;
-
-                is_first_event = true;
-                continue;
+            let result = match received {
+                Err(broadcast::error::RecvError::Closed) => return Err(GatewayError::Disconnected { exchange: exchange, symbol: symbol }),
+                Err(err) => Err(GatewayError::from_recv_error(err, exchange.clone(), symbol.clone(), "output_rx_ch")),
+                Ok(message) if is_first_event && message.last_update_id_timestamp <= snapshot_message.timestamp => continue,
+                Ok(message) if is_first_event && (message.first_update_id_timestamp <= (snapshot_message.timestamp + 1)) 
+                    && (message.last_update_id_timestamp >= (snapshot_message.timestamp + 1)) => {
+
+                    is_first_event = false;
+                    previuos_event_last_timestamp = message.last_update_id_timestamp;
+                    publish_status(&status_tx_ch, &exchange, &symbol, FeedState::Synced, None);
+                    update_book_func(message, &mut snapshot_message).map(|_| ())
+                },
+                Ok(message) if !is_first_event && message.first_update_id_timestamp == (previuos_event_last_timestamp + 1) => {
+                    previuos_event_last_timestamp = message.last_update_id_timestamp;
+                    update_book_func(message, &mut snapshot_message).map(|_| ())
+                },
+                Ok(message) => {
+                    metrics::inc(&metrics::SEQUENCE_GAPS, &exchange, &symbol);
+                    Err(GatewayError::SequenceGap { 
+                        exchange: exchange.clone(), 
+                        symbol: symbol.clone(), 
+                        expected: if is_first_event {snapshot_message.timestamp + 1} else {previuos_event_last_timestamp + 1}, 
+                        received: message.first_update_id_timestamp 
+                    })
+                }
+            };
+
+            if let Err(err) = result {
+                match err.recovery() {
+                    Recovery::Skip => log::warn!("Warning in {:?}:\n{}", task_name, err),
+                    Recovery::Resync => {
+                        log::warn!("Warning in {:?}:\n{}", task_name, err);
+                        publish_status(&status_tx_ch, &exchange, &symbol, FeedState::Resyncing, Some(err.to_string()));
+                        metrics::inc(&metrics::RESYNCS, &exchange, &symbol);
+                        snapshot_message = fetch_snapshot(&symbol, &snapshot_url).await?;
+                        is_first_event = true;
+                    },
+                    Recovery::Retry | Recovery::Abort => return Err(err)
+                }
             }
-            // log::debug!("4\n\n{:?}-{:?}-{:?}\n\n", message.first_update_id_timestamp, 
-            // message.last_update_id_timestamp ,snapshot_message.timestamp);
-    
-
-
         }
-        Ok(())
     }
 
-    fn deserialize_stream(json_str: String) -> Result<DepthData>{
+    fn deserialize_stream(json_str: String) -> serde_json::Result<DepthData>{
         log::info!("binance deserialize stream Init");
     
-        let outer_binance: OuterBinance = serde_json::from_str(&json_str)
-            .context("JSON was not well-formatted deserialize_stream binance")?;
+        let outer_binance: OuterBinance = serde_json::from_str(&json_str)?;
     
         let mut bid_to_update : BTreeMap<Price, Volume>= BTreeMap::new();
         let mut ask_to_update : BTreeMap<Price, Volume>= BTreeMap::new();
@@ -262,10 +328,9 @@ impl ExchangeService for BinanceService{
         Ok(result)
     }
 
-    fn deserialize_snapshot(symbol: Symbol, json_str: String) -> Result<SnapshotData>{
+    fn deserialize_snapshot(symbol: Symbol, json_str: String) -> serde_json::Result<SnapshotData>{
         
-        let outer_binance_snapshot: OuterBinanceSnapshot = serde_json::from_str(&json_str)
-            .context("JSON was not well-formatted deserialize_snapshot binance")?;
+        let outer_binance_snapshot: OuterBinanceSnapshot = serde_json::from_str(&json_str)?;
         let mut bid_to_update : BTreeMap<Price, Volume>= BTreeMap::new();
         let mut ask_to_update : BTreeMap<Price, Volume>= BTreeMap::new();
     
@@ -1,72 +1,108 @@
 
 use std::collections::BTreeMap;
-use anyhow::{Context, Result};
 use futures_util::StreamExt;
 use url::Url;
 use tokio_tungstenite::{
     connect_async, 
     tungstenite::protocol::Message,
 };
-use tokio::sync::{broadcast, mpsc};
+use tokio::{
+    sync::{broadcast, mpsc},
+    task::JoinSet
+};
+use tokio_util::sync::CancellationToken;
 
 use async_trait::async_trait;
 use common::*;
 use crate::*;
 use crate::settings::DeserializeSettings;
 use crate::exchanges_services::*;
+use crate::error::{GatewayError, Recovery};
 
 
+/// Bitstamp pipeline for one symbol of the config
 pub struct BitstampService{
-    pub config: BitstampConfig
+    pub config: BitstampConfig,
+    pub symbol: Symbol
 }
 impl BitstampService{
-    pub fn new(config: BitstampConfig) -> Self{
+    pub fn new(config: BitstampConfig, symbol: Symbol) -> Self{
         BitstampService{
-            config: config
+            config: config,
+            symbol: symbol
         }
     }
 }
 #[async_trait]
 impl ExchangeInit for BitstampService{
-    async fn stream_init_task(&mut self, output_stream_tx_ch: Sender<SnapshotData>) -> Result<()> {
-        let task_name = "--Binance Stream Management Task--";
+    async fn check_symbol(&mut self) -> Result<(), GatewayError> {
+        let symbol = canonical_symbol(&self.symbol);
+        let snapshot_url = self.config.snapshot_urls.get(&self.symbol)
+            .ok_or_else(|| GatewayError::Config { exchange: Exchange::Bitstamp, symbol: symbol.clone(), what: "snapshot url".to_string() })?;
+        check_snapshot(&Exchange::Bitstamp, &symbol, snapshot_url, <BitstampService as ExchangeService>::deserialize_snapshot).await
+    }
 
-        let symbol = self.config.symbols.get(0)
-            .context(format!("Error in {:?}:\ninput_rx_ch:\n", task_name))?;
+    async fn stream_init_task(&mut self, output_stream_tx_ch: Sender<SnapshotData>, 
+        status_tx_ch: Sender<FeedStatus>, cancel_token: CancellationToken) -> Result<(), GatewayError> {
+        let exchange = Exchange::Bitstamp;
+
+        // Bitstamp is configured with its native lower case symbols, the rest of the pipeline uses the canonical one
+        let native_symbol = &self.symbol;
+        let symbol = &canonical_symbol(native_symbol);
         
         let web_socket_url = self.config.websocket_url.clone();
 
-        let snapshot_url = self.config.snapshot_urls.get(symbol)
-            .context(format!("Error in {:?}:\ninput_rx_ch:\n", task_name))?;
+        let snapshot_url = self.config.snapshot_urls.get(native_symbol)
+            .ok_or_else(|| GatewayError::Config { exchange: exchange.clone(), symbol: symbol.clone(), what: "snapshot url".to_string() })?;
 
-        let websocket_payload_init = self.config.websocket_payloads.get(symbol)
-            .context(format!("Error in {:?}:\ninput_rx_ch:\n", task_name))?;
+        let websocket_payload_init = self.config.websocket_payloads.get(native_symbol)
+            .ok_or_else(|| GatewayError::Config { exchange: exchange.clone(), symbol: symbol.clone(), what: "websocket payload".to_string() })?;
 
-        let (ws_stream, _) = connect_async(web_socket_url).await
-            .context(format!("Error in {:?}:\ninput_rx_ch:\n", task_name))?;
+        publish_status(&status_tx_ch, &exchange, symbol, FeedState::Connecting, None);
+        let (ws_stream, _) = match connect_async(web_socket_url).await {
+            Ok(connection) => connection,
+            Err(err) => {
+                let result = Err(GatewayError::Connect { exchange: exchange.clone(), symbol: symbol.clone(), reason: err.to_string() });
+                publish_end(&status_tx_ch, &exchange, symbol, &result);
+                return result;
+            }
+        };
 
         let (writer, reader) = ws_stream.split();
+        // Every task of the pipeline stops with this token, cancelled by the caller or when the snapshot task ends
+        let pipeline_token = cancel_token.child_token();
+        let mut tasks = JoinSet::new();
 
         let (writer_tx_ch, writer_rx_ch): (mpsc::Sender<Message>, mpsc::Receiver<Message>) = mpsc::channel(20);
         
-        let writer_settings = WriterSettings::new(symbol.clone(), writer, writer_rx_ch);
+        let writer_settings = WriterSettings::new(symbol.clone(), writer, writer_rx_ch, pipeline_token.clone());
 
-        tokio::spawn(writer_task(writer_settings));
+        tasks.spawn(writer_task(writer_settings));
 
         let (reader_tx_ch, reader_rx_ch) = broadcast::channel(10);
-        let reader_settings = ReaderSettings::new(symbol.clone(), reader, reader_tx_ch);
-        tokio::spawn(reader_task(reader_settings));
+        let reader_settings = ReaderSettings::new(symbol.clone(), reader, reader_tx_ch, pipeline_token.clone());
+        tasks.spawn(reader_task(reader_settings));
 
-        writer_tx_ch.send(websocket_payload_init.clone()).await
-            .context(format!("Error in {:?}:\ninput_rx_ch:\n", task_name))?;
+        if let Err(err) = writer_tx_ch.send(websocket_payload_init.clone()).await {
+            let result = Err(GatewayError::Subscribe { exchange: exchange.clone(), symbol: symbol.clone(), reason: err.to_string() });
+            shutdown_pipeline(&pipeline_token, &mut tasks).await;
+            publish_end(&status_tx_ch, &exchange, symbol, &result);
+            return result;
+        }
+        publish_status(&status_tx_ch, &exchange, symbol, FeedState::Subscribed, None);
 
         let (output_tx_ch, output_rx_ch) =  broadcast::channel(10);    
-        let deserialize_settings = DeserializeSettings::new(symbol.clone(), reader_rx_ch, output_tx_ch, writer_tx_ch);
-        tokio::spawn(<BitstampService as ExchangeService>::stream_management_task(deserialize_settings));
+        let deserialize_settings = DeserializeSettings::new(symbol.clone(), reader_rx_ch, output_tx_ch, writer_tx_ch, pipeline_token.clone());
+        tasks.spawn(<BitstampService as ExchangeService>::stream_management_task(deserialize_settings));
         
-        <BitstampService as ExchangeService>::
-            snapshot_task(symbol.clone(), snapshot_url.clone(), output_rx_ch, output_stream_tx_ch).await?;
-        Ok(())
+        let result = tokio::select! {
+            result = <BitstampService as ExchangeService>::
+                snapshot_task(symbol.clone(), snapshot_url.clone(), output_rx_ch, output_stream_tx_ch, status_tx_ch.clone()) => result,
+            _ = pipeline_token.cancelled() => Ok(())
+        };
+        shutdown_pipeline(&pipeline_token, &mut tasks).await;
+        publish_end(&status_tx_ch, &exchange, symbol, &result);
+        result
     }
 }
 #[async_trait]
@@ -76,39 +112,36 @@ impl ExchangeService for BitstampService{
 
         let task_name = "--Bitstamp Stream Management Task--";
         log::info!("{:?} Init", task_name);
+        let cancel_token = deserialize_settings.cancel_token.clone();
         loop{
-            match <BitstampService as ExchangeService>::websocket_msg_process(&mut deserialize_settings).await {
-                Ok(_)=> continue,
-                Err(err) => {
-                    log::error!("{:?}", err);
-
-                    match &err.downcast_ref::<broadcast::error::RecvError>() {
-                        Some(err) => {
-                            match err {
-                                broadcast::error::RecvError::Lagged(x) => {
-                                    log::trace!("Trace in {:?}:\ninput_rx_ch lagged:\n{:?}\n", task_name, x); 
-                                    continue;
-                                },
-                                broadcast::error::RecvError::Closed => {
-                                    log::warn!("Warning in {:?}:\ninput_rx_ch closed:\n", task_name); 
-                                    break;
-                                }
-                    
-                            }
-                        },
-                        None =>  log::warn!("Warning in {:?}:\ninput_rx_ch closed:\n", task_name)
-                    };          
-                }
+            let result = tokio::select! {
+                _ = cancel_token.cancelled() => break,
+                result = <BitstampService as ExchangeService>::websocket_msg_process(&mut deserialize_settings) => result
             };
+            if let Err(err) = result {
+                match err.recovery() {
+                    // Every bitstamp message carries the whole book, nothing to rebuild
+                    Recovery::Skip | Recovery::Resync => {
+                        log::warn!("Warning in {:?}:\n{}", task_name, err);
+                        continue;
+                    },
+                    Recovery::Retry | Recovery::Abort => {
+                        log::error!("Error in {:?}:\n{}", task_name, err);
+                        break;
+                    }
+                }
+            }
         } 
         log::info!("{:?} End", task_name);  
     }
 
-    async fn websocket_msg_process(deserialize_settings: &mut DeserializeSettings) -> Result<()> {
+    async fn websocket_msg_process(deserialize_settings: &mut DeserializeSettings) -> Result<(), GatewayError> {
         let task_name = "--Bitstamp websocket_msg_process--";
+        let exchange = Exchange::Bitstamp;
+        let symbol = deserialize_settings.symbol.clone();
      
         let input_msg = deserialize_settings.input_rx_ch.recv().await
-            .context(format!("Error in {:?}:\ninput_rx_ch:\n", task_name))?;
+            .map_err(|err| GatewayError::from_recv_error(err, exchange.clone(), symbol.clone(), "input_rx_ch"))?;
             
         log::trace!("{:?}:\nReceived message from reader\n{:?}", task_name, input_msg);
 
@@ -119,11 +152,12 @@ impl ExchangeService for BitstampService{
                 let pong_msg = Message::Pong(ping_data);
 
                 deserialize_settings.writer_tx_ch.send(pong_msg).await
-                    .context(format!("Error in {:?}:\nSending pong:\n", task_name))?;
+                    .map_err(|_| GatewayError::ChannelClosed { exchange: exchange.clone(), symbol: symbol.clone(), channel: "writer_tx_ch".to_string() })?;
                 log::trace!("Trace in {:?}:\nSent pong", task_name)
             },
             Message::Pong(pong_data) => log::warn!("Warning in {:?}:\nPong message received:\n {:?}", task_name, pong_data),
             Message::Text(text_data) => {
+                metrics::inc(&metrics::MESSAGES_RECEIVED, &exchange, &symbol);
                 // log::info!("text_data \n\n{:?}:\n", text_data);
                 if let Ok(outter) = serde_json::from_str::<OuterBitstampNoData>(&text_data){
                     if outter.event == "bts:subscription_succeeded" {
@@ -141,10 +175,13 @@ impl ExchangeService for BitstampService{
 
 
                 let data = <BitstampService as ExchangeService>::deserialize_stream(text_data)
-                    .context(format!("Error in {:?}:\nDesirializing:\n", task_name))?;
+                    .map_err(|err| {
+                        metrics::inc(&metrics::DECODE_ERRORS, &exchange, &symbol);
+                        GatewayError::Decode { exchange: exchange.clone(), symbol: symbol.clone(), reason: err.to_string() }
+                    })?;
 
                 deserialize_settings.output_tx_ch.send(data)
-                    .context(format!("Error in {:?}:\nSending data:\n", task_name))?;
+                    .map_err(|_| GatewayError::ChannelClosed { exchange: exchange.clone(), symbol: symbol.clone(), channel: "output_tx_ch".to_string() })?;
 
             },
             Message::Binary(_) => log::warn!("Warning in {:?}: binary data sent:\n", task_name)
@@ -154,12 +191,36 @@ impl ExchangeService for BitstampService{
     }
 
     async fn snapshot_task(
-        _: Symbol, 
+        symbol: Symbol, 
         _: Url, 
         mut output_rx_ch: Receiver<DepthData>, 
-        output_stream_tx_ch: Sender<SnapshotData>) -> Result<()> {
+        output_stream_tx_ch: Sender<SnapshotData>,
+        status_tx_ch: Sender<FeedStatus>) -> Result<(), GatewayError> {
 
-        while let Ok(message) = output_rx_ch.recv().await{
+        let task_name = "--Bitstamp Snapshot Task--";
+        let exchange = Exchange::Bitstamp;
+        let mut is_synced = false;
+        loop {
+            let received = match tokio::time::timeout(STALE_AFTER, output_rx_ch.recv()).await {
+                Ok(received) => received,
+                Err(_) => {
+                    if is_synced {
+                        is_synced = false;
+                        publish_status(&status_tx_ch, &exchange, &symbol, FeedState::Stale, Some(format!("no update for {:?}", STALE_AFTER)));
+                    }
+                    continue;
+                }
+            };
+            let message = match received {
+                Ok(message) => message,
+                // Every message is a full book, the next one brings us back in sync
+                Err(broadcast::error::RecvError::Lagged(x)) => {
+                    log::warn!("Warning in {:?}:\n{}", task_name, 
+                        GatewayError::from_recv_error(broadcast::error::RecvError::Lagged(x), exchange.clone(), symbol.clone(), "output_rx_ch"));
+                    continue;
+                },
+                Err(broadcast::error::RecvError::Closed) => return Err(GatewayError::Disconnected { exchange: exchange, symbol: symbol })
+            };
  
             let snapshot_data = SnapshotData{
                 exchange: message.exchange,
@@ -169,17 +230,19 @@ impl ExchangeService for BitstampService{
                 ask_to_update: message.ask_to_update
             };
             output_stream_tx_ch.send(snapshot_data)
-                .context("JSON was not well-formatted deserialize_snapshot binance")?;
+                .map_err(|_| GatewayError::ChannelClosed { exchange: exchange.clone(), symbol: symbol.clone(), channel: "output_stream_tx_ch".to_string() })?;
+            // Every message is a full book, the first one means we are in sync
+            if !is_synced {
+                is_synced = true;
+                publish_status(&status_tx_ch, &exchange, &symbol, FeedState::Synced, None);
+            }
         }
-        Ok(())
     }
 
-    fn deserialize_stream(json_str: String) -> Result<DepthData> {
-        let task_name = "--Bitstamp deserialize_stream Task--";
+    fn deserialize_stream(json_str: String) -> serde_json::Result<DepthData> {
         log::info!("bitstamp deserialize stream Init");
     
-        let outer_bitstamp: OuterBitstamp = serde_json::from_str(&json_str)
-            .context(format!("Error in {:?}:\n", task_name))?;
+        let outer_bitstamp: OuterBitstamp = serde_json::from_str(&json_str)?;
         
         let mut bid_to_update : BTreeMap<Price, Volume>= BTreeMap::new();
         let mut ask_to_update : BTreeMap<Price, Volume>= BTreeMap::new();
@@ -196,11 +259,9 @@ impl ExchangeService for BitstampService{
         }
         let result = DepthData {    
             exchange: Exchange::Bitstamp,
-            symbol: outer_bitstamp.symbol.replace( "order_book_", "").to_uppercase(),
-            first_update_id_timestamp: serde_json::from_str(&outer_bitstamp.data.first_update_id_timestamp)
-                .context(format!("Error in {:?}:\n", task_name))?,
-            last_update_id_timestamp: serde_json::from_str(&outer_bitstamp.data.last_update_id_timestamp)            
-                .context(format!("Error in {:?}:\n", task_name))?,
+            symbol: canonical_symbol(&outer_bitstamp.symbol.replace( "order_book_", "")),
+            first_update_id_timestamp: serde_json::from_str(&outer_bitstamp.data.first_update_id_timestamp)?,
+            last_update_id_timestamp: serde_json::from_str(&outer_bitstamp.data.last_update_id_timestamp)?,
             bid_to_update: bid_to_update,
             ask_to_update: ask_to_update
         };
@@ -208,10 +269,9 @@ impl ExchangeService for BitstampService{
         Ok(result)
     }
     
-    fn deserialize_snapshot(symbol: Symbol, json_str: String) -> Result<SnapshotData> {
+    fn deserialize_snapshot(symbol: Symbol, json_str: String) -> serde_json::Result<SnapshotData> {
     
-        let outer_bitstamp_snapshot: OuterBitstampSnapshot = serde_json::from_str(&json_str)
-            .context("JSON was not well-formatted deserialize_snapshot bitstamp")?;
+        let outer_bitstamp_snapshot: OuterBitstampSnapshot = serde_json::from_str(&json_str)?;
     
         let mut bid_to_update : BTreeMap<Price, Volume>= BTreeMap::new();
         let mut ask_to_update : BTreeMap<Price, Volume>= BTreeMap::new();
@@ -230,8 +290,7 @@ impl ExchangeService for BitstampService{
         let result = SnapshotData {
             exchange: Exchange::Bitstamp,
             symbol: symbol,
-            timestamp: serde_json::from_str(&outer_bitstamp_snapshot.micro_timestamp)
-                .context("timestamp JSON was not well-formatted deserialize_snapshot bitstamp")?,
+            timestamp: serde_json::from_str(&outer_bitstamp_snapshot.micro_timestamp)?,
             bid_to_update: bid_to_update,
             ask_to_update: ask_to_update
         };
@@ -3,11 +3,14 @@ pub mod bitstamp;
 use serde::{Serialize, Deserialize};
 use url::Url;
 use common::*;
-use anyhow::Result;
 use async_trait::async_trait;
 use crate::settings::DeserializeSettings;
+use crate::error::GatewayError;
+use crate::get_snapshot;
+use std::time::Duration;
 use rust_decimal::Decimal;
 use tokio::sync::broadcast::{Sender, Receiver};
+use tokio_util::sync::CancellationToken;
 ////////////////////////////////////////////////////////////////////////////////////////
 
 #[derive(Serialize, Deserialize)]
@@ -46,15 +49,10 @@ pub struct OuterBinanceSnapshot {
 
 #[derive(Deserialize)]
 struct OuterBitstampNoData {
-    pub event: String,
-
-    #[serde(alias = "channel")]
-    pub symbol: String
+    pub event: String
 } 
 #[derive(Deserialize)]
 struct OuterBitstamp {
-    pub event: String,
-
     #[serde(alias = "channel")]
     pub symbol: String,
 
@@ -94,26 +92,72 @@ struct OuterBitstampSnapshot {
 
 }
 
+////////////////////////////////////////////////////////////////////////////////////////
+
+/// A feed with no update for this long is reported as stale
+pub(crate) const STALE_AFTER: Duration = Duration::from_secs(10);
+
+/// Publishes a feed state change, nobody listening is not an error
+pub(crate) fn publish_status(status_tx_ch: &Sender<FeedStatus>, exchange: &Exchange, symbol: &Symbol, state: FeedState, reason: Option<String>) {
+    let status = FeedStatus::new(exchange.clone(), symbol.clone(), state, reason);
+    log::info!("Feed status:\n{:?}", status);
+    let _ = status_tx_ch.send(status);
+}
+
+/// Publishes the end of a pipeline with the error that stopped it, if any
+pub(crate) fn publish_end(status_tx_ch: &Sender<FeedStatus>, exchange: &Exchange, symbol: &Symbol, result: &Result<(), GatewayError>) {
+    let reason = match result {
+        Ok(_) => "cancelled".to_string(),
+        Err(err) => err.to_string()
+    };
+    publish_status(status_tx_ch, exchange, symbol, FeedState::Disconnected, Some(reason));
+}
+
+/// Fetches the snapshot of `symbol` once, an exchange answering with a client error or with
+/// something that is not a book does not list the symbol
+pub(crate) async fn check_snapshot(exchange: &Exchange, symbol: &Symbol, snapshot_url: &Url,
+    deserialize_snapshot: fn(Symbol, String) -> serde_json::Result<SnapshotData>) -> Result<(), GatewayError> {
+    let snapshot = get_snapshot(snapshot_url.clone()).await.map_err(|err| match err.status() {
+        Some(status) if status.is_client_error() => GatewayError::UnknownSymbol { exchange: exchange.clone(), symbol: symbol.clone(), reason: err.to_string() },
+        _ => GatewayError::SnapshotFetch { exchange: exchange.clone(), symbol: symbol.clone(), reason: err.to_string() }
+    })?;
+    deserialize_snapshot(symbol.clone(), snapshot)
+        .map_err(|err| GatewayError::UnknownSymbol { exchange: exchange.clone(), symbol: symbol.clone(), reason: err.to_string() })?;
+    Ok(())
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////
 #[async_trait]
 pub(crate) trait ExchangeService{
 
     async fn stream_management_task(deserialize_settings: DeserializeSettings);
 
-    async fn websocket_msg_process(deserialize_settings: &mut DeserializeSettings) -> Result<()>;
+    async fn websocket_msg_process(deserialize_settings: &mut DeserializeSettings) -> Result<(), GatewayError>;
 
     async fn snapshot_task(
         symbol: Symbol, 
         snapshot_url: Url, 
         output_rx_ch: Receiver<DepthData>, 
-        output_stream_tx_ch: Sender<SnapshotData>) -> Result<()>;
+        output_stream_tx_ch: Sender<SnapshotData>,
+        status_tx_ch: Sender<FeedStatus>) -> Result<(), GatewayError>;
 
-    fn deserialize_stream(json_str: String) -> Result<DepthData>;
+    fn deserialize_stream(json_str: String) -> serde_json::Result<DepthData>;
 
-    fn deserialize_snapshot(symbol: Symbol, json_str: String) -> Result<SnapshotData>;
+    fn deserialize_snapshot(symbol: Symbol, json_str: String) -> serde_json::Result<SnapshotData>;
 }
 
 #[async_trait]
 pub trait ExchangeInit{
-    async fn stream_init_task(&mut self, output_stream_tx_ch: Sender<SnapshotData>) -> Result<()>;
+    /// Checks the exchange streams the symbol before the pipeline is started, an unknown one is an
+    /// UnknownSymbol error
+    async fn check_symbol(&mut self) -> Result<(), GatewayError> {
+        Ok(())
+    }
+
+    /// Runs the exchange pipeline until `cancel_token` is cancelled or the pipeline fails,
+    /// the upstream socket is closed and every spawned task has finished when it returns.
+    ///
+    /// Every state change of the feed is published on `status_tx_ch`
+    async fn stream_init_task(&mut self, output_stream_tx_ch: Sender<SnapshotData>, 
+        status_tx_ch: Sender<FeedStatus>, cancel_token: CancellationToken) -> Result<(), GatewayError>;
 }
\ No newline at end of file
@@ -7,14 +7,16 @@
       unused_qualifications,
       warnings
   )]
+// fields are named in full, `field: field`, across the crates
+#![allow(clippy::redundant_field_names)]
 
 pub mod settings;
 pub mod exchanges_services;
+pub mod error;
 
 #[cfg(test)]
 mod tests;
 
-use anyhow::{Context, Result};
 use url::Url;
 use settings::{ReaderSettings, WriterSettings};
 
@@ -29,6 +31,8 @@ use tokio_tungstenite::{
     tungstenite::protocol::Message,
     tungstenite::error::Error as WsError
 };
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
 
 
 async fn reader_task<S>(mut settings: ReaderSettings<S>) 
@@ -36,7 +40,14 @@ async fn reader_task<S>(mut settings: ReaderSettings<S>)
 {
     let task_name = "--Reader Task--";
     log::info!("{:?} Init", task_name);   
-    while let Some(message) = settings.websocket_reader.next().await {      
+    loop {
+        let message = tokio::select! {
+            _ = settings.cancel_token.cancelled() => break,
+            message = settings.websocket_reader.next() => match message {
+                Some(message) => message,
+                None => break
+            }
+        };
         match message {
             Ok(message) => {
                 log::trace!("{:?}:\n{:?}", task_name, message);
@@ -56,7 +67,14 @@ async fn writer_task<S>(mut settings: WriterSettings<S>)
 {
     let task_name = "--Writer Task--";
     log::info!("{:?} Init", task_name);
-    while let Some(message) = settings.input_rx_ch.recv().await { 
+    loop {
+        let message = tokio::select! {
+            _ = settings.cancel_token.cancelled() => break,
+            message = settings.input_rx_ch.recv() => match message {
+                Some(message) => message,
+                None => break
+            }
+        };
         if let  Err(err) = settings.websocket_writer.send(message.clone()).await {
             log::error!("Error in {:?}:\nSending message to stream:\n{:?}", task_name, err)
         }
@@ -64,14 +82,31 @@ async fn writer_task<S>(mut settings: WriterSettings<S>)
             log::trace!("{:?}:\n{:?}", task_name, message);
         }
     }
+    // Closing handshake so the exchange sees a clean disconnect
+    if let Err(err) = settings.websocket_writer.send(Message::Close(None)).await {
+        log::warn!("Warning in {:?}:\nSending close message:\n{:?}", task_name, err);
+    }
+    if let Err(err) = settings.websocket_writer.close().await {
+        log::warn!("Warning in {:?}:\nClosing stream:\n{:?}", task_name, err);
+    }
     log::info!("{:?} End", task_name);
 
 }
 
-async fn get_snapshot(url: Url) -> Result<String>{
+/// Cancels the remaining tasks of an exchange pipeline and waits for them to finish
+async fn shutdown_pipeline(pipeline_token: &CancellationToken, tasks: &mut JoinSet<()>) {
+    pipeline_token.cancel();
+    while let Some(result) = tasks.join_next().await {
+        if let Err(err) = result {
+            log::error!("Error in pipeline task:\n{:?}", err);
+        }
+    }
+}
+
+async fn get_snapshot(url: Url) -> reqwest::Result<String>{
     let client = reqwest::Client::new();
-    let request = client.get(url).send().await.context("Request snapshot error")?;
-    let body = request.text().await.context("Request (body) snapshot error")?;
+    let request = client.get(url).send().await?;
+    let body = request.error_for_status()?.text().await?;
     Ok(body)
 }
 
@@ -7,6 +7,7 @@ use tokio_tungstenite::{
     tungstenite::error::Error as WsError
 };
 use tokio::sync::{mpsc, broadcast};
+use tokio_util::sync::CancellationToken;
 use futures_util::{
     stream::{SplitSink, SplitStream, Stream},
     sink::Sink
@@ -18,16 +19,18 @@ where S: Stream<Item=Result<Message, WsError>> + Unpin {
     pub symbol: Symbol,
     pub websocket_reader: SplitStream<S>,
     pub output_tx_ch: broadcast::Sender<Message>,
+    pub cancel_token: CancellationToken,
 }
 impl<S> ReaderSettings<S>
     where  S: Stream<Item=Result<Message, WsError>> + Unpin {
 
     pub fn new(symbol: Symbol, websocket_reader: SplitStream<S>, 
-        output_tx_ch: broadcast::Sender<Message>) -> Self {
+        output_tx_ch: broadcast::Sender<Message>, cancel_token: CancellationToken) -> Self {
         ReaderSettings{
             symbol: symbol,
             websocket_reader: websocket_reader,
-            output_tx_ch: output_tx_ch
+            output_tx_ch: output_tx_ch,
+            cancel_token: cancel_token
         }
     }
 }
@@ -38,17 +41,19 @@ pub struct WriterSettings<S>
 {
     pub symbol: Symbol,
     pub websocket_writer: SplitSink<S, Message>,
-    pub input_rx_ch: mpsc::Receiver<Message>
+    pub input_rx_ch: mpsc::Receiver<Message>,
+    pub cancel_token: CancellationToken
 }
 impl<S> WriterSettings<S>
     where  S: Sink<Message> + Unpin{
 
     pub fn new(symbol: Symbol, websocket_writer: SplitSink<S, Message>, 
-        input_rx_ch:mpsc::Receiver<Message>) -> Self {
+        input_rx_ch:mpsc::Receiver<Message>, cancel_token: CancellationToken) -> Self {
         WriterSettings{
             symbol: symbol,
             websocket_writer: websocket_writer,
             input_rx_ch: input_rx_ch,
+            cancel_token: cancel_token
 
         }
     }
@@ -61,7 +66,8 @@ pub struct DeserializeSettings
     pub symbol: Symbol,
     pub input_rx_ch: broadcast::Receiver<Message>, 
     pub output_tx_ch: broadcast::Sender<DepthData>, 
-    pub writer_tx_ch: mpsc::Sender<Message>
+    pub writer_tx_ch: mpsc::Sender<Message>,
+    pub cancel_token: CancellationToken
 }
 impl DeserializeSettings
  {
@@ -70,12 +76,14 @@ impl DeserializeSettings
         symbol: Symbol,
         input_rx_ch: broadcast::Receiver<Message>, 
         output_tx_ch: broadcast::Sender<DepthData>, 
-        writer_tx_ch: mpsc::Sender<Message>) -> Self {
+        writer_tx_ch: mpsc::Sender<Message>,
+        cancel_token: CancellationToken) -> Self {
             DeserializeSettings{
                 symbol: symbol,
                 input_rx_ch: input_rx_ch, 
                 output_tx_ch: output_tx_ch, 
-                writer_tx_ch: writer_tx_ch
+                writer_tx_ch: writer_tx_ch,
+                cancel_token: cancel_token
 
         }
     }
@@ -17,6 +17,7 @@ use crate::exchanges_services::{
     binance::*,
     ExchangeService
 };
+use tokio_util::sync::CancellationToken;
 use crate::settings::DeserializeSettings;
 
 
@@ -120,7 +121,7 @@ async fn test_stream_management_task_binance() {
     let (input_tx_ch, input_rx_ch) =  broadcast::channel(10);
     let (writer_tx_ch, mut writer_rx_ch): (mpsc::Sender<Message>, mpsc::Receiver<Message>) = mpsc::channel(20);
     let (output_tx_ch, mut output_rx_ch) =  broadcast::channel(10);
-    let deserialize_settings = DeserializeSettings::new(symbol.clone(), input_rx_ch, output_tx_ch, writer_tx_ch);
+    let deserialize_settings = DeserializeSettings::new(symbol.clone(), input_rx_ch, output_tx_ch, writer_tx_ch, CancellationToken::new());
    
     tokio::task::spawn(async move {
         <BinanceService as ExchangeService>::stream_management_task(deserialize_settings).await
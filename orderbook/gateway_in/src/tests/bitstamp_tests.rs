@@ -12,6 +12,7 @@ use crate::exchanges_services::{
     bitstamp::*,
     ExchangeService
 };
+use tokio_util::sync::CancellationToken;
 use crate::settings::DeserializeSettings;
 
 
@@ -134,7 +135,7 @@ async fn test_stream_management_task_bitstamp() {
     let (input_tx_ch, input_rx_ch) =  broadcast::channel(10);
     let (writer_tx_ch, mut writer_rx_ch): (mpsc::Sender<Message>, mpsc::Receiver<Message>) = mpsc::channel(20);
     let (output_tx_ch, mut output_rx_ch) =  broadcast::channel(10);
-    let deserialize_settings = DeserializeSettings::new(symbol.clone(), input_rx_ch, output_tx_ch, writer_tx_ch);
+    let deserialize_settings = DeserializeSettings::new(symbol.clone(), input_rx_ch, output_tx_ch, writer_tx_ch, CancellationToken::new());
       
     tokio::task::spawn(async move {
         <BitstampService as ExchangeService>::stream_management_task(deserialize_settings).await
@@ -169,3 +170,34 @@ async fn test_stream_management_task_bitstamp() {
     assert_eq!(output_rx_ch.recv().await, Ok(expected));
     assert_eq!(writer_rx_ch.recv().await, Some(Message::Pong(vec![1_u8, 2, 3])));
 }
+
+#[tokio::test]
+async fn test_snapshot_task_publishes_feed_status_bitstamp() {
+
+    let symbol = "ETHBTC".to_string();
+    let (depth_tx_ch, depth_rx_ch) =  broadcast::channel(10);
+    let (output_stream_tx_ch, mut output_stream_rx_ch) =  broadcast::channel(10);
+    let (status_tx_ch, mut status_rx_ch) =  broadcast::channel(10);
+    let url = url::Url::parse("https://www.bitstamp.net/api/v2/order_book/ethbtc").unwrap();
+
+    let handle = tokio::spawn(<BitstampService as ExchangeService>::
+        snapshot_task(symbol.clone(), url, depth_rx_ch, output_stream_tx_ch, status_tx_ch));
+
+    depth_tx_ch.send(DepthData {
+        exchange: Exchange::Bitstamp,
+        symbol: symbol.clone(),
+        first_update_id_timestamp: 1833980193,
+        last_update_id_timestamp: 1833980193555559,
+        bid_to_update: BTreeMap::new(),
+        ask_to_update: BTreeMap::new()
+    }).unwrap();
+
+    assert_eq!(output_stream_rx_ch.recv().await.unwrap().timestamp, 1833980193555559);
+    let status: FeedStatus = status_rx_ch.recv().await.unwrap();
+    assert_eq!((status.exchange, status.symbol, status.state), (Exchange::Bitstamp, symbol.clone(), FeedState::Synced));
+
+    // The upstream going away ends the task with a retryable error
+    drop(depth_tx_ch);
+    let result = handle.await.unwrap();
+    assert_eq!(result, Err(crate::error::GatewayError::Disconnected { exchange: Exchange::Bitstamp, symbol: symbol }));
+}
@@ -0,0 +1,69 @@
+use tokio::sync::{broadcast, mpsc};
+use tokio_tungstenite::tungstenite::protocol::Message;
+use tokio_util::sync::CancellationToken;
+use pretty_assertions::assert_eq;
+use common::*;
+use crate::error::{GatewayError, Recovery};
+use crate::exchanges_services::{
+    binance::*,
+    ExchangeService
+};
+use crate::settings::DeserializeSettings;
+
+#[test]
+fn test_recovery_by_variant(){
+
+    let exchange = Exchange::Binance;
+    let symbol = "BNBBTC".to_string();
+
+    let decode = GatewayError::Decode { exchange: exchange.clone(), symbol: symbol.clone(), reason: "bad json".to_string() };
+    let gap = GatewayError::SequenceGap { exchange: exchange.clone(), symbol: symbol.clone(), expected: 161, received: 170 };
+    let connect = GatewayError::Connect { exchange: exchange.clone(), symbol: symbol.clone(), reason: "refused".to_string() };
+    let closed = GatewayError::ChannelClosed { exchange: exchange.clone(), symbol: symbol.clone(), channel: "output_stream_tx_ch".to_string() };
+
+    assert_eq!(decode.recovery(), Recovery::Skip);
+    assert_eq!(gap.recovery(), Recovery::Resync);
+    assert_eq!(connect.recovery(), Recovery::Retry);
+    assert_eq!(closed.recovery(), Recovery::Abort);
+    let unknown = GatewayError::UnknownSymbol { exchange: exchange.clone(), symbol: symbol.clone(), reason: "400 Bad Request".to_string() };
+    assert_eq!(unknown.recovery(), Recovery::Abort);
+    assert_eq!(gap.exchange(), &exchange);
+    assert_eq!(gap.symbol(), &symbol);
+    assert_eq!(gap.to_string(), "Binance BNBBTC: sequence gap, expected 161 received 170");
+}
+
+#[test]
+fn test_recv_error_variants(){
+
+    let lagged = GatewayError::from_recv_error(broadcast::error::RecvError::Lagged(3), Exchange::Bitstamp, "ETHBTC".to_string(), "input_rx_ch");
+    let closed = GatewayError::from_recv_error(broadcast::error::RecvError::Closed, Exchange::Bitstamp, "ETHBTC".to_string(), "input_rx_ch");
+
+    assert_eq!(lagged, GatewayError::Lagged { exchange: Exchange::Bitstamp, symbol: "ETHBTC".to_string(), channel: "input_rx_ch".to_string(), skipped: 3 });
+    assert_eq!(closed.recovery(), Recovery::Abort);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_stream_management_task_skips_decode_errors() {
+
+    let data = r#"{        
+            "e": "depthUpdate",
+            "E": 123456789,
+            "s": "BNBBTC",
+            "U": 157,
+            "u": 160,
+            "b": [],
+            "a": []
+        }"#;
+
+    let (input_tx_ch, input_rx_ch) =  broadcast::channel(10);
+    let (writer_tx_ch, _writer_rx_ch): (mpsc::Sender<Message>, mpsc::Receiver<Message>) = mpsc::channel(20);
+    let (output_tx_ch, mut output_rx_ch) =  broadcast::channel(10);
+    let deserialize_settings = DeserializeSettings::new("BNBBTC".to_string(), input_rx_ch, output_tx_ch, writer_tx_ch, CancellationToken::new());
+
+    tokio::task::spawn(<BinanceService as ExchangeService>::stream_management_task(deserialize_settings));
+    input_tx_ch.send(Message::Text("not json".to_string())).ok();
+    input_tx_ch.send(Message::Text(data.to_string())).ok();
+
+    let result: DepthData = output_rx_ch.recv().await.unwrap();
+    assert_eq!(result.first_update_id_timestamp, 157);
+}
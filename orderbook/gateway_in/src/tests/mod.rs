@@ -5,6 +5,7 @@ mod binance_tests;
 
 
 mod bitstamp_tests;
+mod error_tests;
 // use std::sync::Once;
 // const CONFIG_PATH: &str = "src/tests/config.json"; 
 
@@ -1,10 +1,15 @@
+use std::time::Duration;
 use tokio::{
-    sync::{broadcast, mpsc}
+    sync::{broadcast, mpsc},
+    task::JoinSet,
+    time::timeout
 };
+use tokio_util::sync::CancellationToken;
 use futures_util::StreamExt;
 use tokio_tungstenite::tungstenite::protocol::Message;
 use pretty_assertions::assert_eq;
-use crate::settings::{ReaderSettings, WriterSettings};
+use crate::settings::{ReaderSettings, WriterSettings, DeserializeSettings};
+use crate::exchanges_services::{binance::BinanceService, ExchangeService};
 use super::mocks::MockWebSocketStream;
 
 #[tokio::test]
@@ -19,7 +24,7 @@ async fn test_reader_task() {
     let (_, reader) = stream.split();
 
     let (output_tx_ch, mut input_rx_ch) = broadcast::channel(10);
-    let settings = ReaderSettings::new("BNBBTC".to_string(), reader, output_tx_ch);
+    let settings = ReaderSettings::new("BNBBTC".to_string(), reader, output_tx_ch, CancellationToken::new());
  
     tokio::spawn(crate::reader_task(settings));
 
@@ -37,7 +42,7 @@ async fn test_writer_task() {
     let (writer, _) = stream.split();
     let (output_tx_ch, input_rx_ch): (mpsc::Sender<Message>, mpsc::Receiver<Message>) = mpsc::channel(20);
     
-    let settings = WriterSettings::new("BNBBTC".to_string(), writer, input_rx_ch);
+    let settings = WriterSettings::new("BNBBTC".to_string(), writer, input_rx_ch, CancellationToken::new());
     
     tokio::spawn(crate::writer_task(settings));
     let _ = output_tx_ch.send(Message::Text("Msg 1".to_string())).await;
@@ -49,4 +54,45 @@ async fn test_writer_task() {
     assert_eq!(w_receiver.recv().await, Ok(Message::Text("Msg 3".to_string())));
 }
 
+#[tokio::test]
+async fn test_writer_task_closes_stream_on_cancel() {
+
+    let (_, r_receiver) =  broadcast::channel(3);
+    let (w_sender, mut w_receiver)=  broadcast::channel(3); 
+    let stream = MockWebSocketStream::new(r_receiver, w_sender);
+    let (writer, _) = stream.split();
+    let (_output_tx_ch, input_rx_ch): (mpsc::Sender<Message>, mpsc::Receiver<Message>) = mpsc::channel(20);
+    let cancel_token = CancellationToken::new();
 
+    let settings = WriterSettings::new("BNBBTC".to_string(), writer, input_rx_ch, cancel_token.clone());
+    let handle = tokio::spawn(crate::writer_task(settings));
+
+    cancel_token.cancel();
+    timeout(Duration::from_secs(1), handle).await.unwrap().unwrap();
+    assert_eq!(w_receiver.recv().await, Ok(Message::Close(None)));
+}
+
+#[tokio::test]
+async fn test_shutdown_pipeline_leaves_no_tasks() {
+
+    let (_input_tx_ch, input_rx_ch) =  broadcast::channel(10);
+    let (writer_tx_ch, _writer_rx_ch) = mpsc::channel(20);
+    let (output_tx_ch, _output_rx_ch) =  broadcast::channel(10);
+    let (_, r_receiver) =  broadcast::channel(3);
+    let (w_sender, _w_receiver)=  broadcast::channel(3); 
+    let (writer, _) = MockWebSocketStream::new(r_receiver, w_sender).split();
+    let (_writer_input_tx_ch, writer_input_rx_ch) = mpsc::channel(20);
+    let cancel_token = CancellationToken::new();
+    let pipeline_token = cancel_token.child_token();
+
+    let mut tasks = JoinSet::new();
+    let deserialize_settings = DeserializeSettings::new("BNBBTC".to_string(), input_rx_ch, output_tx_ch, writer_tx_ch, pipeline_token.clone());
+    tasks.spawn(<BinanceService as ExchangeService>::stream_management_task(deserialize_settings));
+    let writer_settings = WriterSettings::new("BNBBTC".to_string(), writer, writer_input_rx_ch, pipeline_token.clone());
+    tasks.spawn(crate::writer_task(writer_settings));
+    assert_eq!(tasks.len(), 2);
+
+    cancel_token.cancel();
+    timeout(Duration::from_secs(1), crate::shutdown_pipeline(&pipeline_token, &mut tasks)).await.unwrap();
+    assert!(tasks.is_empty());
+}
@@ -0,0 +1,87 @@
+//! Top of book of 5 venues × 5000 levels, collect-and-sort against the k-way merge.
+//!
+//! Run with `cargo bench`.
+
+#![allow(clippy::redundant_field_names)]
+
+use std::collections::BTreeMap;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rust_decimal::Decimal;
+use common::*;
+
+#[allow(dead_code)]
+#[path = "../src/aggregated_order_book.rs"]
+mod aggregated_order_book;
+
+use aggregated_order_book::{MergedLadder, Level};
+
+const VENUES: usize = 5;
+const LEVELS: i64 = 5000;
+const TOP: usize = 20;
+
+fn venue_book(exchange: Exchange, venue: i64) -> SnapshotData {
+    let mut bid_to_update: BTreeMap<Price, Volume> =  BTreeMap::new();
+    let mut ask_to_update: BTreeMap<Price, Volume> =  BTreeMap::new();
+    for level in 0..LEVELS {
+        // venues interleave by a tick so the merge walks every ladder
+        let offset = Decimal::new(level * VENUES as i64 + venue, 4);
+        bid_to_update.insert(Decimal::new(1000, 0) - offset, Decimal::new(level + 1, 2));
+        ask_to_update.insert(Decimal::new(1001, 0) + offset, Decimal::new(level + 1, 2));
+    }
+    SnapshotData {
+        exchange: exchange,
+        symbol: "ETHBTC".to_string(),
+        timestamp: 1833980193,
+        bid_to_update: bid_to_update,
+        ask_to_update: ask_to_update
+    }
+}
+
+/// The collect-and-sort of every level, the baseline
+fn sorted_top_asks(books: &[SnapshotData], top_num: usize) -> Vec<Level> {
+    let mut aggregated_book: Vec<Level> = Vec::new();
+    books.iter().for_each(|book|
+        book.ask_to_update.iter().for_each(|(price, volume)|
+            aggregated_book.push(Level::new(book.exchange.clone(), *price, *volume)))
+    );
+    aggregated_book.sort_by(|a, b| a.price.partial_cmp(&b.price).unwrap());
+    aggregated_book.into_iter().take(top_num).collect::<Vec<Level>>()
+}
+
+fn sorted_top_bids(books: &[SnapshotData], top_num: usize) -> Vec<Level> {
+    let mut aggregated_book: Vec<Level> = Vec::new();
+    books.iter().for_each(|book|
+        book.bid_to_update.iter().for_each(|(price, volume)|
+            aggregated_book.push(Level::new(book.exchange.clone(), *price, *volume)))
+    );
+    aggregated_book.sort_by(|a, b| b.price.partial_cmp(&a.price).unwrap());
+    aggregated_book.into_iter().take(top_num).collect::<Vec<Level>>()
+}
+
+fn top_of_book(c: &mut Criterion) {
+    let exchanges = [Exchange::Binance, Exchange::Bitstamp];
+    let books: Vec<SnapshotData> = (0..VENUES)
+        .map(|venue| venue_book(exchanges[venue % exchanges.len()].clone(), venue as i64))
+        .collect();
+    // AggregatedBook keeps one book per Exchange, the merge is benched on the venue ladders directly
+    let merged_top_asks = |books: &[SnapshotData]| MergedLadder::new(
+        books.iter().map(|book| (&book.exchange, book.ask_to_update.iter())).collect(), |price: &Price, best_price: &Price| price < best_price)
+        .take(TOP).collect::<Vec<Level>>();
+    let merged_top_bids = |books: &[SnapshotData]| MergedLadder::new(
+        books.iter().map(|book| (&book.exchange, book.bid_to_update.iter().rev())).collect(), |price: &Price, best_price: &Price| price > best_price)
+        .take(TOP).collect::<Vec<Level>>();
+    assert_eq!(sorted_top_asks(&books, TOP), merged_top_asks(&books));
+    assert_eq!(sorted_top_bids(&books, TOP), merged_top_bids(&books));
+
+    let mut group = c.benchmark_group("top_of_book_5x5000");
+    group.bench_function("collect_and_sort", |b| b.iter(|| {
+        (sorted_top_asks(black_box(&books), TOP), sorted_top_bids(black_box(&books), TOP))
+    }));
+    group.bench_function("k_way_merge", |b| b.iter(|| {
+        (merged_top_asks(black_box(&books)), merged_top_bids(black_box(&books)))
+    }));
+    group.finish();
+}
+
+criterion_group!(benches, top_of_book);
+criterion_main!(benches);
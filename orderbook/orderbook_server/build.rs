@@ -1,6 +1,9 @@
 fn main()->Result<(),Box<dyn std::error::Error>>{
   
-    // compiling protos using path on build time
-       tonic_build::compile_protos("proto/orderbook.proto")?;
+    // compiling protos using path on build time, their descriptors are served by reflection
+       let out_dir = std::path::PathBuf::from(std::env::var("OUT_DIR")?);
+       tonic_build::configure()
+           .file_descriptor_set_path(out_dir.join("orderbook_descriptor.bin"))
+           .compile(&["proto/orderbook.proto", "proto/health.proto", "proto/reflection.proto"], &["proto"])?;
        Ok(())
-    }
\ No newline at end of file
+    }
@@ -0,0 +1,293 @@
+use std::{
+    collections::BTreeMap,
+    str::FromStr,
+    sync::Arc,
+    time::Duration
+};
+use tokio::{
+    sync::{broadcast, Mutex},
+    task::JoinHandle
+};
+use tokio_util::sync::CancellationToken;
+use tonic::{Request, Response, Status};
+use common::*;
+use gateway_in::exchanges_services::{
+    binance::BinanceService,
+    bitstamp::BitstampService,
+    ExchangeInit
+};
+use gateway_in::error::{GatewayError, Recovery};
+use crate::book_feed::BookFeed;
+use crate::orderbook::{Empty, Subscription, SubscriptionList, SubscriptionRequest};
+use crate::orderbook::admin_server::Admin;
+
+/// How long to wait before restarting a failed exchange pipeline
+pub const RETRY_DELAY: Duration = Duration::from_secs(5);
+/// How long a pipeline gets to close its socket when stopped before being aborted
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Builds the pipeline streaming a symbol from an exchange
+pub type PipelineFactory = Arc<dyn Fn(&Exchange, &str) -> Box<dyn ExchangeInit + Send> + Send + Sync>;
+
+/// Pipelines of the exchanges, the urls of any symbol are built from the base urls of `binance`
+/// and `bitstamp`, in the case of each exchange
+pub fn exchange_pipelines(binance: BinanceConfig, bitstamp: BitstampConfig) -> PipelineFactory {
+    Arc::new(move |exchange: &Exchange, symbol: &str| -> Box<dyn ExchangeInit + Send> {
+        match exchange {
+            Exchange::Binance => {
+                let mut config = binance.clone();
+                let symbol = config.add_symbol(symbol);
+                Box::new(BinanceService::new(config, symbol))
+            },
+            Exchange::Bitstamp => {
+                let mut config = bitstamp.clone();
+                let symbol = config.add_symbol(symbol);
+                Box::new(BitstampService::new(config, symbol))
+            }
+        }
+    })
+}
+
+struct Pipeline {
+    cancel_token: CancellationToken,
+    handle: JoinHandle<()>,
+    /// Milliseconds since the unix epoch
+    started_time: Timestamp,
+}
+
+/// The exchange pipelines running, by exchange and symbol, each one is started and stopped
+/// on its own without touching the others
+#[derive(Clone)]
+pub struct Subscriptions {
+    pipelines: Arc<Mutex<BTreeMap<(Exchange, Symbol), Pipeline>>>,
+    factory: PipelineFactory,
+    feed: BookFeed,
+    snapshot_tx_ch: broadcast::Sender<SnapshotData>,
+    status_tx_ch: broadcast::Sender<FeedStatus>,
+    cancel_token: CancellationToken,
+}
+
+impl Subscriptions {
+    pub fn new(factory: PipelineFactory, feed: BookFeed, snapshot_tx_ch: broadcast::Sender<SnapshotData>,
+        status_tx_ch: broadcast::Sender<FeedStatus>, cancel_token: CancellationToken) -> Self {
+        Subscriptions{
+            pipelines: Arc::new(Mutex::new(BTreeMap::new())),
+            factory: factory,
+            feed: feed,
+            snapshot_tx_ch: snapshot_tx_ch,
+            status_tx_ch: status_tx_ch,
+            cancel_token: cancel_token,
+        }
+    }
+
+    pub async fn contains(&self, exchange: &Exchange, symbol: &str) -> bool {
+        let pipelines = self.pipelines.lock().await;
+        pipelines.contains_key(&(exchange.clone(), canonical_symbol(symbol)))
+    }
+
+    /// Checks `exchange` streams `symbol` before it is added
+    pub async fn check(&self, exchange: &Exchange, symbol: &str) -> Result<(), GatewayError> {
+        (self.factory)(exchange, symbol).check_symbol().await
+    }
+
+    /// Starts streaming `symbol` from `exchange`, None when it already is
+    pub async fn add(&self, exchange: &Exchange, symbol: &str) -> Option<Subscription> {
+        let key = (exchange.clone(), canonical_symbol(symbol));
+        let mut pipelines = self.pipelines.lock().await;
+        if pipelines.contains_key(&key) {
+            return None;
+        }
+        self.feed.add_book(exchange, symbol);
+        let pipeline = self.start(exchange, symbol);
+        let subscription = subscription(&key, &pipeline);
+        pipelines.insert(key, pipeline);
+        Some(subscription)
+    }
+
+    /// Stops the pipeline and drops its book, the symbol is no longer streamed once its last
+    /// exchange is removed. False when there was no such pipeline
+    pub async fn remove(&self, exchange: &Exchange, symbol: &str) -> bool {
+        let key = (exchange.clone(), canonical_symbol(symbol));
+        let mut pipelines = self.pipelines.lock().await;
+        let pipeline = match pipelines.remove(&key) {
+            Some(pipeline) => pipeline,
+            None => return false
+        };
+        stop(pipeline).await;
+        self.remove_book(&pipelines, exchange, symbol);
+        true
+    }
+
+    /// Drops the book of a pipeline that stopped for good, unless it was stopped meanwhile
+    async fn remove_failed(&self, exchange: &Exchange, symbol: &str, cancel_token: &CancellationToken) {
+        let key = (exchange.clone(), canonical_symbol(symbol));
+        // whoever stops the pipeline holds the lock until it returns
+        let mut pipelines = tokio::select! {
+            biased;
+            _ = cancel_token.cancelled() => return,
+            pipelines = self.pipelines.lock() => pipelines
+        };
+        pipelines.remove(&key);
+        self.remove_book(&pipelines, exchange, symbol);
+        log::warn!("Subscription dropped:\n{} {}", exchange, key.1);
+    }
+
+    /// Drops the book, the symbol is no longer streamed once its last exchange is removed
+    fn remove_book(&self, pipelines: &BTreeMap<(Exchange, Symbol), Pipeline>, exchange: &Exchange, symbol: &str) {
+        let symbol = canonical_symbol(symbol);
+        self.feed.remove_book(exchange, &symbol);
+        if !pipelines.keys().any(|(_, other)| other == &symbol) {
+            self.feed.remove_symbol(&symbol);
+        }
+    }
+
+    /// Restarts the pipeline, its book is dropped until the new snapshot arrives
+    pub async fn resync(&self, exchange: &Exchange, symbol: &str) -> Option<Subscription> {
+        let key = (exchange.clone(), canonical_symbol(symbol));
+        let mut pipelines = self.pipelines.lock().await;
+        let pipeline = pipelines.remove(&key)?;
+        stop(pipeline).await;
+        self.feed.remove_book(exchange, symbol);
+        self.feed.add_book(exchange, symbol);
+        let pipeline = self.start(exchange, symbol);
+        let subscription = subscription(&key, &pipeline);
+        pipelines.insert(key, pipeline);
+        Some(subscription)
+    }
+
+    pub async fn list(&self) -> Vec<Subscription> {
+        let pipelines = self.pipelines.lock().await;
+        pipelines.iter().map(|(key, pipeline)| subscription(key, pipeline)).collect()
+    }
+
+    /// Waits for every pipeline to stop once `cancel_token` is cancelled, aborting the late ones
+    pub async fn join(&self) {
+        let mut pipelines = self.pipelines.lock().await;
+        for (_, pipeline) in std::mem::take(&mut *pipelines) {
+            stop(pipeline).await;
+        }
+    }
+
+    /// Spawns the pipeline, it is removed with its book when it fails for good
+    fn start(&self, exchange: &Exchange, symbol: &str) -> Pipeline {
+        let cancel_token = self.cancel_token.child_token();
+        let pipeline = run_pipeline((self.factory)(exchange, symbol), self.snapshot_tx_ch.clone(),
+            self.status_tx_ch.clone(), cancel_token.clone());
+        let (subscriptions, exchange, symbol, pipeline_cancel_token) = (self.clone(), exchange.clone(), symbol.to_string(), cancel_token.clone());
+        let handle = tokio::spawn(async move {
+            if let Some(err) = pipeline.await {
+                let status = FeedStatus::new(exchange.clone(), canonical_symbol(&symbol), FeedState::Disconnected, Some(err.to_string()));
+                let _ = subscriptions.status_tx_ch.send(status);
+                subscriptions.remove_failed(&exchange, &symbol, &pipeline_cancel_token).await;
+            }
+        });
+        Pipeline{
+            cancel_token: cancel_token,
+            handle: handle,
+            started_time: now_millis(),
+        }
+    }
+}
+
+fn subscription(key: &(Exchange, Symbol), pipeline: &Pipeline) -> Subscription {
+    Subscription{
+        exchange: key.0.to_string(),
+        symbol: key.1.clone(),
+        started_time: pipeline.started_time,
+    }
+}
+
+/// Cancels the pipeline, aborting it when it does not stop in time
+async fn stop(mut pipeline: Pipeline) {
+    pipeline.cancel_token.cancel();
+    if tokio::time::timeout(SHUTDOWN_TIMEOUT, &mut pipeline.handle).await.is_err() {
+        log::warn!("Exchange pipeline did not stop in {:?}, aborting it", SHUTDOWN_TIMEOUT);
+        pipeline.handle.abort();
+    }
+}
+
+/// Runs the pipeline until `cancel_token` is cancelled, restarting it after the errors it may recover from,
+/// the error that stopped it for good if any
+async fn run_pipeline(mut service: Box<dyn ExchangeInit + Send>, output_stream_tx_ch: broadcast::Sender<SnapshotData>,
+    status_tx_ch: broadcast::Sender<FeedStatus>, cancel_token: CancellationToken) -> Option<GatewayError> {
+    loop {
+        let err = match service.stream_init_task(output_stream_tx_ch.clone(), status_tx_ch.clone(), cancel_token.clone()).await {
+            Ok(_) => return None,
+            Err(err) => err
+        };
+        match err.recovery() {
+            Recovery::Abort => {
+                log::error!("\n{}", err);
+                return Some(err);
+            },
+            Recovery::Skip | Recovery::Resync | Recovery::Retry => {
+                log::warn!("\n{}\nRestarting in {:?}", err, RETRY_DELAY);
+                tokio::select! {
+                    _ = cancel_token.cancelled() => return None,
+                    _ = tokio::time::sleep(RETRY_DELAY) => continue
+                }
+            }
+        }
+    }
+}
+
+/// Admin gRPC service, starts and stops the exchange pipelines while the server runs
+#[derive(Clone)]
+pub struct AdminService {
+    subscriptions: Subscriptions,
+}
+
+impl AdminService {
+    pub fn new(subscriptions: Subscriptions) -> Self {
+        AdminService{subscriptions: subscriptions}
+    }
+}
+
+fn parse_request(request: &SubscriptionRequest) -> Result<(Exchange, Symbol), Status> {
+    let exchange = Exchange::from_str(&request.exchange)
+        .map_err(|_| Status::invalid_argument(format!("unknown exchange {:?}", request.exchange)))?;
+    if request.symbol.is_empty() || !request.symbol.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(Status::invalid_argument(format!("invalid symbol {:?}", request.symbol)));
+    }
+    Ok((exchange, request.symbol.clone()))
+}
+
+#[tonic::async_trait]
+impl Admin for AdminService {
+    async fn add_subscription(&self, request: Request<SubscriptionRequest>) -> Result<Response<Subscription>, Status> {
+        let (exchange, symbol) = parse_request(request.get_ref())?;
+        let already_exists = || Status::already_exists(format!("{} {} is already streamed", exchange, canonical_symbol(&symbol)));
+        if self.subscriptions.contains(&exchange, &symbol).await {
+            return Err(already_exists());
+        }
+        self.subscriptions.check(&exchange, &symbol).await.map_err(|err| match err.recovery() {
+            Recovery::Abort => Status::invalid_argument(err.to_string()),
+            Recovery::Skip | Recovery::Resync | Recovery::Retry => Status::unavailable(err.to_string())
+        })?;
+        let subscription = self.subscriptions.add(&exchange, &symbol).await
+            .ok_or_else(already_exists)?;
+        log::info!("Subscription added:\n{:?}", subscription);
+        Ok(Response::new(subscription))
+    }
+
+    async fn remove_subscription(&self, request: Request<SubscriptionRequest>) -> Result<Response<Empty>, Status> {
+        let (exchange, symbol) = parse_request(request.get_ref())?;
+        if !self.subscriptions.remove(&exchange, &symbol).await {
+            return Err(Status::not_found(format!("{} {} is not streamed", exchange, canonical_symbol(&symbol))));
+        }
+        log::info!("Subscription removed:\n{} {}", exchange, canonical_symbol(&symbol));
+        Ok(Response::new(Empty{}))
+    }
+
+    async fn list_subscriptions(&self, _request: Request<Empty>) -> Result<Response<SubscriptionList>, Status> {
+        Ok(Response::new(SubscriptionList{subscriptions: self.subscriptions.list().await}))
+    }
+
+    async fn resync_book(&self, request: Request<SubscriptionRequest>) -> Result<Response<Subscription>, Status> {
+        let (exchange, symbol) = parse_request(request.get_ref())?;
+        let subscription = self.subscriptions.resync(&exchange, &symbol).await
+            .ok_or_else(|| Status::not_found(format!("{} {} is not streamed", exchange, canonical_symbol(&symbol))))?;
+        log::info!("Book resynced:\n{:?}", subscription);
+        Ok(Response::new(subscription))
+    }
+}
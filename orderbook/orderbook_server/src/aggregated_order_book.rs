@@ -1,3 +1,8 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    iter::Peekable
+};
+use rust_decimal::Decimal;
 use crate::*;
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -5,6 +10,8 @@ pub struct Level {
     pub exchange: Exchange, 
     pub price:  Price,
     pub volume: Volume,
+    /// Price quoted by the exchange, differs from `price` when fees are included
+    pub raw_price: Price,
 }
 impl Level {
     pub fn new(exchange: Exchange, price:  Price, volume: Volume) -> Self {
@@ -12,22 +19,40 @@ impl Level {
             exchange: exchange, 
             price:  price,
             volume: volume,
+            raw_price: price,
         }       
     }  
 }
 
+/// Basis points in one
+pub const BPS: i64 = 10_000;
+
 #[derive(Clone, Debug)]
 pub struct AggregatedBook {
    books: Vec<SnapshotData>,
+   /// Taker fee of each exchange in basis points, none when missing
+   taker_fees: BTreeMap<Exchange, Decimal>,
+   /// When the last snapshot of each exchange was received
+   received_times: BTreeMap<Exchange, Timestamp>,
 }
 
 impl AggregatedBook {
     pub fn new() -> Self{
         AggregatedBook{
             books: Vec::new(),
+            taker_fees: BTreeMap::new(),
+            received_times: BTreeMap::new(),
         }       
     }
 
+    pub fn set_taker_fee(&mut self, exchange: Exchange, taker_bps: Decimal) {
+        self.taker_fees.insert(exchange, taker_bps);
+    }
+
+    pub fn set_received_time(&mut self, exchange: Exchange, received_time: Timestamp) {
+        self.received_times.insert(exchange, received_time);
+    }
+
 
     pub fn update_book(&mut self, snapshot_data: SnapshotData){
         let mut index_to_remove: Option<usize>= None;
@@ -45,23 +70,254 @@ impl AggregatedBook {
         
     }
 
+    /// Best `top_num` asks across every exchange, lowest price first
     pub fn get_top_asks(&self, top_num: usize) -> Vec<Level> {
-        let mut aggregated_book: Vec<Level> = Vec::new();
-        self.books.iter().for_each(|book|            
-            book.ask_to_update.iter().for_each(|(price, volume)| 
-                aggregated_book.push(Level::new(book.exchange.clone(), *price, *volume)))            
-        );
-        aggregated_book.sort_by(|a, b| a.price.partial_cmp(&b.price).unwrap());
-        aggregated_book.into_iter().take(top_num).collect::<Vec<Level>>()
+        self.asks().take(top_num).collect()
     }
 
+    /// Best `top_num` bids across every exchange, highest price first
     pub fn get_top_bids(&self, top_num: usize) -> Vec<Level> {
-        let mut aggregated_book: Vec<Level> = Vec::new();
-        self.books.iter().for_each(|book|            
-            book.bid_to_update.iter().for_each(|(price, volume)| 
-                aggregated_book.push(Level::new(book.exchange.clone(), *price, *volume)))             
-        );      
-        aggregated_book.sort_by(|a, b| b.price.partial_cmp(&a.price).unwrap());
-        aggregated_book.into_iter().take(top_num).collect::<Vec<Level>>()
+        self.bids().take(top_num).collect()
+    }
+
+    /// Best `top_num` ask prices, each with the total volume quoted and its per-exchange breakdown
+    pub fn get_top_consolidated_asks(&self, top_num: usize) -> Vec<ConsolidatedLevel> {
+        consolidate(self.asks(), top_num)
+    }
+
+    /// Best `top_num` bid prices, each with the total volume quoted and its per-exchange breakdown
+    pub fn get_top_consolidated_bids(&self, top_num: usize) -> Vec<ConsolidatedLevel> {
+        consolidate(self.bids(), top_num)
+    }
+
+    /// Best `top_num` asks once the taker fee is paid, the price raised by the fee of its exchange
+    pub fn get_top_fee_adjusted_asks(&self, top_num: usize) -> Vec<Level> {
+        self.fee_adjusted_asks().take(top_num).collect()
+    }
+
+    /// Best `top_num` bids once the taker fee is paid, the price lowered by the fee of its exchange
+    pub fn get_top_fee_adjusted_bids(&self, top_num: usize) -> Vec<Level> {
+        self.fee_adjusted_bids().take(top_num).collect()
+    }
+
+    /// Drops the book of `exchange`
+    pub fn remove_exchange(&mut self, exchange: &Exchange) {
+        self.books.retain(|book| &book.exchange != exchange);
+        self.taker_fees.remove(exchange);
+        self.received_times.remove(exchange);
+    }
+
+    /// Exchanges quoting this book
+    pub fn exchanges(&self) -> Vec<Exchange> {
+        self.books.iter().map(|book| book.exchange.clone()).collect()
+    }
+
+    /// Update id and receive time of the last snapshot of each exchange
+    pub fn last_updates(&self) -> Vec<(Exchange, Timestamp, Timestamp)> {
+        self.books.iter()
+            .map(|book| (book.exchange.clone(), book.timestamp, self.received_times.get(&book.exchange).copied().unwrap_or_default()))
+            .collect()
+    }
+
+    /// The same book quoted by `exchanges` only
+    pub fn only(&self, exchanges: &[Exchange]) -> AggregatedBook {
+        AggregatedBook{
+            books: self.books.iter().filter(|book| exchanges.contains(&book.exchange)).cloned().collect(),
+            ..self.clone()
+        }
+    }
+
+    /// Asks across every exchange, lowest price first, merged as they are taken
+    pub fn asks(&self) -> impl Iterator<Item = Level> + '_ {
+        let ladders = self.books.iter()
+            .map(|book| (&book.exchange, book.ask_to_update.iter()))
+            .collect();
+        MergedLadder::new(ladders, |price: &Price, best_price: &Price| price < best_price)
+    }
+
+    /// Bids across every exchange, highest price first, merged as they are taken
+    pub fn bids(&self) -> impl Iterator<Item = Level> + '_ {
+        let ladders = self.books.iter()
+            .map(|book| (&book.exchange, book.bid_to_update.iter().rev()))
+            .collect();
+        MergedLadder::new(ladders, |price: &Price, best_price: &Price| price > best_price)
+    }
+
+    pub fn fee_adjusted_asks(&self) -> impl Iterator<Item = Level> + '_ {
+        let ladders = self.books.iter()
+            .map(|book| (&book.exchange, Decimal::ONE + self.taker_fee(&book.exchange), book.ask_to_update.iter()))
+            .collect();
+        MergedLadder::fee_adjusted(ladders, |price: &Price, best_price: &Price| price < best_price)
+    }
+
+    pub fn fee_adjusted_bids(&self) -> impl Iterator<Item = Level> + '_ {
+        let ladders = self.books.iter()
+            .map(|book| (&book.exchange, Decimal::ONE - self.taker_fee(&book.exchange), book.bid_to_update.iter().rev()))
+            .collect();
+        MergedLadder::fee_adjusted(ladders, |price: &Price, best_price: &Price| price > best_price)
+    }
+
+    /// Taker fee of `exchange` as a fraction of the price
+    fn taker_fee(&self, exchange: &Exchange) -> Decimal {
+        self.taker_fees.get(exchange).copied().unwrap_or(Decimal::ZERO) / Decimal::from(BPS)
+    }
+}
+
+/// Lazy k-way merge of the per-exchange ladders, each already sorted best price first.
+///
+/// Levels are only visited and allocated as they are taken, the best head is found by
+/// scanning the heads, cheaper than a heap for the handful of venues aggregated.
+/// Equal prices keep the order of the ladders.
+pub struct MergedLadder<'a, I, F>
+where
+    I: Iterator<Item = (&'a Price, &'a Volume)>
+{
+    /// Exchange, factor applied to its prices if any, and its ladder
+    ladders: Vec<(&'a Exchange, Option<Decimal>, Peekable<I>)>,
+    is_better: F,
+}
+
+impl<'a, I, F> MergedLadder<'a, I, F>
+where
+    I: Iterator<Item = (&'a Price, &'a Volume)>,
+    F: Fn(&Price, &Price) -> bool
+{
+    pub fn new(ladders: Vec<(&'a Exchange, I)>, is_better: F) -> Self {
+        MergedLadder{
+            ladders: ladders.into_iter()
+                .map(|(exchange, ladder)| (exchange, None, ladder.peekable()))
+                .collect(),
+            is_better: is_better,
+        }
+    }
+
+    /// Ranks each ladder on its prices times its factor, the quoted price is kept as `raw_price`
+    pub fn fee_adjusted(ladders: Vec<(&'a Exchange, Decimal, I)>, is_better: F) -> Self {
+        MergedLadder{
+            ladders: ladders.into_iter()
+                .map(|(exchange, factor, ladder)| (exchange, Some(factor), ladder.peekable()))
+                .collect(),
+            is_better: is_better,
+        }
+    }
+}
+
+impl<'a, I, F> Iterator for MergedLadder<'a, I, F>
+where
+    I: Iterator<Item = (&'a Price, &'a Volume)>,
+    F: Fn(&Price, &Price) -> bool
+{
+    type Item = Level;
+
+    fn next(&mut self) -> Option<Level> {
+        let is_better = &self.is_better;
+        let mut best: Option<(usize, Price)> = None;
+        for (i, (_, factor, ladder)) in self.ladders.iter_mut().enumerate() {
+            if let Some((price, _)) = ladder.peek() {
+                let price = factor.map_or(**price, |factor| **price * factor);
+                if best.is_none_or(|(_, best_price)| is_better(&price, &best_price)) {
+                    best = Some((i, price));
+                }
+            }
+        }
+        let (i, price) = best?;
+        let (exchange, _, ladder) = &mut self.ladders[i];
+        ladder.next().map(|(raw_price, volume)| Level{
+            exchange: (*exchange).clone(),
+            price: price,
+            volume: *volume,
+            raw_price: *raw_price,
+        })
+    }
+}
+
+/// Price level of the consolidated book, the volume of every exchange quoting `price` summed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConsolidatedLevel {
+    pub price:  Price,
+    pub volume: Volume,
+    pub exchanges: Vec<Level>,
+}
+
+/// Merges the levels of equal price of a merged ladder, up to `top_num` prices
+pub fn consolidate(levels: impl Iterator<Item = Level>, top_num: usize) -> Vec<ConsolidatedLevel> {
+    let mut top_levels: Vec<ConsolidatedLevel> = Vec::new();
+    for level in levels {
+        match top_levels.last_mut() {
+            // a merged ladder yields equal prices one after the other
+            Some(last) if last.price == level.price => {
+                last.volume += level.volume;
+                last.exchanges.push(level);
+            },
+            _ => {
+                if top_levels.len() == top_num {
+                    break;
+                }
+                top_levels.push(ConsolidatedLevel{
+                    price: level.price,
+                    volume: level.volume,
+                    exchanges: vec![level],
+                });
+            }
+        }
+    }
+    top_levels
+}
+
+/// One consolidated book per instrument, venues quoting different symbols are never merged
+#[derive(Clone, Debug)]
+pub struct AggregatedBookSet {
+   books: BTreeMap<Symbol, AggregatedBook>,
+   fees: FeesConfig,
+   /// Exchange books removed at runtime, their snapshots still in flight are ignored
+   removed: BTreeSet<(Exchange, Symbol)>,
+}
+
+impl AggregatedBookSet {
+    pub fn new(fees: FeesConfig) -> Self{
+        AggregatedBookSet{
+            books: BTreeMap::new(),
+            fees: fees,
+            removed: BTreeSet::new(),
+        }       
+    }
+
+    /// Drops the book of `exchange` from the book of `symbol` and returns what is left of it,
+    /// None once no exchange quotes the symbol any more
+    pub fn remove_exchange(&mut self, exchange: &Exchange, symbol: &str) -> Option<&AggregatedBook> {
+        let symbol = canonical_symbol(symbol);
+        self.removed.insert((exchange.clone(), symbol.clone()));
+        let book = self.books.get_mut(&symbol)?;
+        book.remove_exchange(exchange);
+        if book.exchanges().is_empty() {
+            self.books.remove(&symbol);
+            return None;
+        }
+        self.books.get(&symbol)
+    }
+
+    /// Takes the snapshots of an exchange book removed before again
+    pub fn add_exchange(&mut self, exchange: &Exchange, symbol: &str) {
+        self.removed.remove(&(exchange.clone(), canonical_symbol(symbol)));
+    }
+
+    pub fn is_removed(&self, exchange: &Exchange, symbol: &str) -> bool {
+        self.removed.contains(&(exchange.clone(), canonical_symbol(symbol)))
+    }
+
+    /// Updates the book of the snapshot symbol and returns it
+    pub fn update_book(&mut self, snapshot_data: SnapshotData) -> &AggregatedBook {
+        let symbol = canonical_symbol(&snapshot_data.symbol);
+        let taker_bps = self.fees.taker_bps(&snapshot_data.exchange, &symbol);
+        let book = self.books.entry(symbol)
+            .or_insert_with(AggregatedBook::new);
+        book.set_taker_fee(snapshot_data.exchange.clone(), taker_bps);
+        book.set_received_time(snapshot_data.exchange.clone(), now_millis());
+        book.update_book(snapshot_data);
+        book
+    }
+
+    pub fn get_book(&self, symbol: &str) -> Option<&AggregatedBook> {
+        self.books.get(&canonical_symbol(symbol))
     }
 }
\ No newline at end of file
@@ -0,0 +1,138 @@
+use rust_decimal::Decimal;
+use crate::*;
+use crate::aggregated_order_book::{AggregatedBook, Level, consolidate, BPS};
+
+/// Volume quoted within `bps` of the mid
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DepthWithin {
+    pub bps: Decimal,
+    pub bid_volume: Volume,
+    pub ask_volume: Volume,
+}
+
+/// Metrics of a book, `None` when not configured or not computable
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BookStats {
+    /// `None` for the aggregated book
+    pub exchange: Option<Exchange>,
+    pub mid: Option<Price>,
+    /// Mid weighted by the volume on the other side of the top price
+    pub microprice: Option<Price>,
+    /// Bid volume less ask volume over their sum, on the top price levels
+    pub imbalance: Option<Decimal>,
+    pub depth: Vec<DepthWithin>,
+    /// Vwap to buy less vwap to sell the configured volume, in basis points of the mid
+    pub weighted_spread_bps: Option<Decimal>,
+}
+
+/// Computes the configured metrics of the aggregated book and of the book of each exchange
+#[derive(Clone, Debug, Default)]
+pub struct BookAnalytics {
+    config: AnalyticsConfig,
+}
+
+impl BookAnalytics {
+    pub fn new(config: AnalyticsConfig) -> Self {
+        BookAnalytics{
+            config: config,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.config.metrics.is_empty()
+    }
+
+    /// Metrics of the book merged across every exchange
+    pub fn stats(&self, book: &AggregatedBook) -> BookStats {
+        self.ladder_stats(None, || book.bids(), || book.asks())
+    }
+
+    /// Metrics of the book of each exchange on its own, ordered as `AggregatedBook::exchanges`
+    pub fn venue_stats(&self, book: &AggregatedBook) -> Vec<BookStats> {
+        book.exchanges().into_iter()
+            .map(|exchange| {
+                let bids = || book.bids().filter(|level| level.exchange == exchange);
+                let asks = || book.asks().filter(|level| level.exchange == exchange);
+                self.ladder_stats(Some(exchange.clone()), bids, asks)
+            })
+            .collect()
+    }
+
+    /// `bids` and `asks` give a fresh ladder, best price first, each time they are called
+    fn ladder_stats<B, A, I, J>(&self, exchange: Option<Exchange>, bids: B, asks: A) -> BookStats
+    where
+        B: Fn() -> I,
+        A: Fn() -> J,
+        I: Iterator<Item = Level>,
+        J: Iterator<Item = Level>
+    {
+        let mut stats = BookStats{
+            exchange: exchange,
+            ..Default::default()
+        };
+        let (best_bid, best_ask) = match (consolidate(bids(), 1).pop(), consolidate(asks(), 1).pop()) {
+            (Some(best_bid), Some(best_ask)) => (best_bid, best_ask),
+            _ => return stats
+        };
+        let mid = (best_bid.price + best_ask.price) / Decimal::from(2);
+        if mid <= Decimal::ZERO {
+            return stats;
+        }
+
+        if self.config.has(Metric::Mid) {
+            stats.mid = Some(mid);
+        }
+        if self.config.has(Metric::Microprice) {
+            let top_volume = best_bid.volume + best_ask.volume;
+            if !top_volume.is_zero() {
+                stats.microprice = Some((best_bid.price * best_ask.volume + best_ask.price * best_bid.volume) / top_volume);
+            }
+        }
+        if self.config.has(Metric::Imbalance) {
+            let top_num = self.config.imbalance_levels;
+            let bid_volume: Volume = consolidate(bids(), top_num).iter().map(|level| level.volume).sum();
+            let ask_volume: Volume = consolidate(asks(), top_num).iter().map(|level| level.volume).sum();
+            let volume = bid_volume + ask_volume;
+            if !volume.is_zero() {
+                stats.imbalance = Some((bid_volume - ask_volume) / volume);
+            }
+        }
+        if self.config.has(Metric::Depth) {
+            stats.depth = self.config.depth_bps.iter()
+                .map(|bps| {
+                    let distance = mid * *bps / Decimal::from(BPS);
+                    DepthWithin{
+                        bps: *bps,
+                        bid_volume: bids().take_while(|level| level.price >= mid - distance).map(|level| level.volume).sum(),
+                        ask_volume: asks().take_while(|level| level.price <= mid + distance).map(|level| level.volume).sum(),
+                    }
+                })
+                .collect();
+        }
+        if self.config.has(Metric::WeightedSpread) {
+            let volume = self.config.weighted_spread_volume;
+            if let (Some(buy), Some(sell)) = (vwap(asks(), volume), vwap(bids(), volume)) {
+                stats.weighted_spread_bps = Some((buy - sell) / mid * Decimal::from(BPS));
+            }
+        }
+        stats
+    }
+}
+
+/// Average price of the first `volume` of the ladder, `None` when it quotes less
+fn vwap(levels: impl Iterator<Item = Level>, volume: Volume) -> Option<Price> {
+    if volume <= Decimal::ZERO {
+        return None;
+    }
+    let mut left = volume;
+    let mut notional = Decimal::ZERO;
+    for level in levels {
+        let filled = level.volume.min(left);
+        notional += filled * level.price;
+        left -= filled;
+        if left.is_zero() {
+            return Some(notional / volume);
+        }
+    }
+    None
+}
@@ -0,0 +1,218 @@
+use std::{
+    collections::BTreeMap,
+    str::FromStr,
+    sync::{Arc, Mutex}
+};
+use anyhow::{anyhow, Result};
+use ring::{constant_time, hmac};
+use serde::Deserialize;
+use tonic::{metadata::MetadataMap, Request, Status};
+use common::*;
+
+/// Metadata key of the bearer token
+pub const AUTHORIZATION: &str = "authorization";
+
+/// Checks the bearer tokens of the OrderbookAggregator calls and what each client may ask for
+#[derive(Clone, Default)]
+pub struct Authenticator {
+    config: Arc<AuthConfig>,
+    jwt_key: Option<hmac::Key>,
+    grants: Arc<BTreeMap<String, Grant>>,
+    /// Streams open per client
+    streams: Arc<Mutex<BTreeMap<String, usize>>>,
+}
+
+/// What an authenticated client may ask for, no limit on what is empty or None
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Grant {
+    pub client: String,
+    symbols: Vec<Symbol>,
+    exchanges: Vec<Exchange>,
+    max_depth: Option<usize>,
+    max_streams: Option<usize>,
+    admin: bool,
+}
+
+#[derive(Deserialize)]
+struct JwtHeader {
+    alg: String,
+}
+
+#[derive(Deserialize)]
+struct JwtClaims {
+    sub: String,
+    /// Seconds since the unix epoch
+    #[serde(default)]
+    exp: Option<u64>,
+}
+
+impl Authenticator {
+    pub fn new(config: &AuthConfig) -> Result<Self> {
+        let mut grants = BTreeMap::new();
+        for (client, grant) in config.clients.iter() {
+            let exchanges = grant.exchanges.iter()
+                .map(|exchange| Exchange::from_str(exchange).map_err(|_| anyhow!("unknown exchange {} granted to {}", exchange, client)))
+                .collect::<Result<Vec<Exchange>>>()?;
+            grants.insert(client.clone(), Grant{
+                client: client.clone(),
+                symbols: grant.symbols.iter().map(|symbol| canonical_symbol(symbol)).collect(),
+                exchanges: exchanges,
+                max_depth: grant.max_depth,
+                max_streams: grant.max_streams,
+                admin: grant.admin,
+            });
+        }
+        Ok(Authenticator{
+            config: Arc::new(config.clone()),
+            jwt_key: config.jwt_secret.as_ref().map(|secret| hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes())),
+            grants: Arc::new(grants),
+            streams: Arc::new(Mutex::new(BTreeMap::new())),
+        })
+    }
+
+    /// Grant of the client presenting the token of `metadata`, everything is granted when auth is disabled
+    pub fn grant(&self, metadata: &MetadataMap) -> Result<Grant, Status> {
+        if !self.config.is_enabled() {
+            return Ok(Grant::default());
+        }
+        let token = metadata.get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| Status::unauthenticated("a bearer token is required"))?;
+        let client = match self.static_client(token) {
+            Some(client) => client,
+            None => self.jwt_client(token)?
+        };
+        self.grants.get(&client).cloned()
+            .ok_or_else(|| Status::permission_denied(format!("client {} is not granted any access", client)))
+    }
+
+    /// Rejects the calls without a valid token before they reach the service
+    pub fn interceptor(&self) -> impl Fn(Request<()>) -> Result<Request<()>, Status> + Send + Sync + 'static {
+        let auth = self.clone();
+        move |request: Request<()>| {
+            auth.grant(request.metadata())?;
+            Ok(request)
+        }
+    }
+
+    /// True when auth is enabled and some client is granted admin, the Admin service is not served otherwise
+    pub fn admin_enabled(&self) -> bool {
+        self.config.is_enabled() && self.grants.values().any(|grant| grant.admin)
+    }
+
+    /// Rejects the calls of clients not granted admin, every call when auth is disabled
+    pub fn admin_interceptor(&self) -> impl Fn(Request<()>) -> Result<Request<()>, Status> + Send + Sync + 'static {
+        let auth = self.clone();
+        move |request: Request<()>| {
+            if !auth.config.is_enabled() {
+                return Err(Status::permission_denied("the Admin service requires auth"));
+            }
+            if !auth.grant(request.metadata())?.admin {
+                return Err(Status::permission_denied("admin is not granted"));
+            }
+            Ok(request)
+        }
+    }
+
+    /// Counts a stream of the client until the permit is dropped
+    pub fn open_stream(&self, grant: &Grant) -> Result<StreamPermit, Status> {
+        let mut streams = self.streams.lock().unwrap_or_else(|err| err.into_inner());
+        let open = streams.entry(grant.client.clone()).or_insert(0);
+        if let Some(max_streams) = grant.max_streams {
+            if *open >= max_streams {
+                return Err(Status::permission_denied(format!("client {} already has {} streams open", grant.client, open)));
+            }
+        }
+        *open += 1;
+        metrics::GRPC_SUBSCRIBERS.with_label_values(&[&grant.client]).inc();
+        Ok(StreamPermit{
+            client: grant.client.clone(),
+            streams: self.streams.clone(),
+        })
+    }
+
+    fn static_client(&self, token: &str) -> Option<String> {
+        // every token is compared in constant time
+        let mut found = None;
+        for (known, client) in self.config.tokens.iter() {
+            if constant_time::verify_slices_are_equal(known.as_bytes(), token.as_bytes()).is_ok() {
+                found = Some(client.clone());
+            }
+        }
+        found
+    }
+
+    /// Client named by a HS256 JWT signed with the configured secret
+    fn jwt_client(&self, token: &str) -> Result<String, Status> {
+        let invalid = || Status::unauthenticated("invalid token");
+        let key = self.jwt_key.as_ref().ok_or_else(invalid)?;
+        let parts: Vec<&str> = token.split('.').collect();
+        if parts.len() != 3 {
+            return Err(invalid());
+        }
+        let decode = |part: &str| base64::decode_config(part, base64::URL_SAFE_NO_PAD).map_err(|_| invalid());
+        let header: JwtHeader = serde_json::from_slice(&decode(parts[0])?).map_err(|_| invalid())?;
+        if header.alg != "HS256" {
+            return Err(invalid());
+        }
+        let signed = format!("{}.{}", parts[0], parts[1]);
+        hmac::verify(key, signed.as_bytes(), &decode(parts[2])?).map_err(|_| invalid())?;
+        let claims: JwtClaims = serde_json::from_slice(&decode(parts[1])?).map_err(|_| invalid())?;
+        match claims.exp {
+            Some(exp) if exp <= now_millis() / 1000 => Err(Status::unauthenticated("expired token")),
+            _ => Ok(claims.sub)
+        }
+    }
+}
+
+impl Grant {
+    pub fn check_symbol(&self, symbol: &str) -> Result<(), Status> {
+        if self.symbols.is_empty() || self.symbols.contains(&canonical_symbol(symbol)) {
+            Ok(())
+        } else {
+            Err(Status::permission_denied(format!("client {} may not ask for {}", self.client, symbol)))
+        }
+    }
+
+    /// The exchanges asked for, the granted ones when none is
+    pub fn exchanges(&self, requested: Vec<Exchange>) -> Result<Vec<Exchange>, Status> {
+        if self.exchanges.is_empty() {
+            return Ok(requested);
+        }
+        if requested.is_empty() {
+            return Ok(self.exchanges.clone());
+        }
+        match requested.iter().find(|exchange| !self.exchanges.contains(exchange)) {
+            Some(exchange) => Err(Status::permission_denied(format!("client {} may not ask for {}", self.client, exchange))),
+            None => Ok(requested)
+        }
+    }
+
+    /// An event of `symbol` involving `exchanges` may be sent to the client
+    pub fn allows(&self, symbol: &str, exchanges: &[&str]) -> bool {
+        self.check_symbol(symbol).is_ok() && (self.exchanges.is_empty() || exchanges.iter()
+            .all(|exchange| Exchange::from_str(exchange).is_ok_and(|exchange| self.exchanges.contains(&exchange))))
+    }
+
+    pub fn max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+}
+
+/// An open stream of a client, counted in the gRPC subscribers metric, released on drop
+#[derive(Debug)]
+pub struct StreamPermit {
+    client: String,
+    streams: Arc<Mutex<BTreeMap<String, usize>>>,
+}
+
+impl Drop for StreamPermit {
+    fn drop(&mut self) {
+        let mut streams = self.streams.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(open) = streams.get_mut(&self.client) {
+            *open = open.saturating_sub(1);
+        }
+        metrics::GRPC_SUBSCRIBERS.with_label_values(&[&self.client]).dec();
+    }
+}
@@ -0,0 +1,85 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant}
+};
+use crate::*;
+use orderbook::{BookDelta, LevelChange, level_change::Action};
+
+/// How often a delta stream resends the whole book
+pub const DELTA_REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Level of a ladder by exchange and exact price
+type LevelKey = (String, String);
+
+/// Turns the summaries sent to one subscriber into a full book, then the level changes
+/// since the previous message, then a full book again every `refresh_interval`.
+pub struct DeltaEncoder {
+    sequence: u64,
+    refresh_interval: Duration,
+    last_refresh: Option<Instant>,
+    bids: BTreeMap<LevelKey, Level>,
+    asks: BTreeMap<LevelKey, Level>,
+}
+
+impl DeltaEncoder {
+    pub fn new(refresh_interval: Duration) -> Self {
+        DeltaEncoder{
+            sequence: 0,
+            refresh_interval: refresh_interval,
+            last_refresh: None,
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+        }
+    }
+
+    /// The changes from the previous summary to `summary`, a full book when a refresh is due
+    pub fn encode(&mut self, summary: Summary) -> BookDelta {
+        self.sequence += 1;
+        let snapshot = self.last_refresh.is_none_or(|last_refresh| last_refresh.elapsed() >= self.refresh_interval);
+        if snapshot {
+            self.last_refresh = Some(Instant::now());
+            self.bids.clear();
+            self.asks.clear();
+        }
+        let bids = ladder(summary.bids);
+        let asks = ladder(summary.asks);
+        let delta = BookDelta{
+            symbol: summary.symbol,
+            sequence: self.sequence,
+            snapshot: snapshot,
+            bids: changes(&self.bids, &bids),
+            asks: changes(&self.asks, &asks),
+            publish_time: summary.publish_time,
+        };
+        self.bids = bids;
+        self.asks = asks;
+        delta
+    }
+}
+
+fn ladder(levels: Vec<Level>) -> BTreeMap<LevelKey, Level> {
+    levels.into_iter()
+        .map(|level| ((level.exchange.clone(), level.price_decimal.clone()), level))
+        .collect()
+}
+
+/// Deletes, then inserts and updates, turning `previous` into `current`
+fn changes(previous: &BTreeMap<LevelKey, Level>, current: &BTreeMap<LevelKey, Level>) -> Vec<LevelChange> {
+    let deletes = previous.iter()
+        .filter(|(key, _)| !current.contains_key(*key))
+        .map(|(_, level)| level_change(Action::Delete, level));
+    let upserts = current.iter()
+        .filter_map(|(key, level)| match previous.get(key) {
+            None => Some(level_change(Action::Insert, level)),
+            Some(previous_level) if previous_level != level => Some(level_change(Action::Update, level)),
+            Some(_) => None
+        });
+    deletes.chain(upserts).collect()
+}
+
+fn level_change(action: Action, level: &Level) -> LevelChange {
+    LevelChange{
+        action: action as i32,
+        level: Some(level.clone()),
+    }
+}
@@ -0,0 +1,626 @@
+use std::{
+    str::FromStr,
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex, RwLock},
+    time::Duration
+};
+use tokio::{
+    sync::{broadcast, mpsc},
+    task::JoinSet,
+    time::Instant
+};
+use tokio_util::sync::CancellationToken;
+use tonic::Status;
+use rust_decimal::Decimal;
+use crate::*;
+use crate::aggregated_order_book::{AggregatedBook, AggregatedBookSet};
+use crate::synthetic_book::SyntheticBook;
+use crate::crossed_market::CrossedMarketDetector;
+use crate::analytics::BookAnalytics;
+
+/// Capacity of the summary broadcast, a subscriber lagging behind this loses the oldest summaries
+const SUMMARY_CHANNEL_CAPACITY: usize = 16;
+/// Capacity of the crossed market broadcast
+const CROSSED_CHANNEL_CAPACITY: usize = 32;
+
+/// What the aggregation task streams and computes on each update
+#[derive(Clone, Debug, Default)]
+pub struct BookFeedSettings {
+    pub symbols: Vec<Symbol>,
+    pub fees: FeesConfig,
+    /// Streamed as any other symbol, updated whenever one of their legs is
+    pub synthetics: Vec<SyntheticBook>,
+    pub crossed_market: CrossedMarketConfig,
+    /// Metrics added to the summaries of the symbols, not of the synthetics
+    pub analytics: AnalyticsConfig,
+    /// Summaries hold `max_depth` levels per side, each subscriber keeps the depth it asked for
+    pub depth: DepthConfig,
+}
+
+/// The `Summary` of one book update in each aggregation mode
+#[derive(Clone, Debug, PartialEq)]
+pub struct SummaryViews {
+    pub by_venue: Summary,
+    pub consolidated: Summary,
+    pub fee_adjusted: Summary,
+    /// Built from the book of the exchanges kept, once per publish for each filter subscribed
+    pub filtered: HashMap<SummaryFilter, SummaryViews>,
+}
+
+impl SummaryViews {
+    pub fn view(&self, mode: AggregationMode) -> Summary {
+        match mode {
+            AggregationMode::ByVenue => self.by_venue.clone(),
+            AggregationMode::Consolidated => self.consolidated.clone(),
+            AggregationMode::FeeAdjusted => self.fee_adjusted.clone(),
+        }
+    }
+
+    /// Sets the last update of each exchange book, the same in every view
+    pub fn set_exchange_updates(&mut self, exchange_updates: &[ExchangeUpdate]) {
+        for summary in [&mut self.by_venue, &mut self.consolidated, &mut self.fee_adjusted].iter_mut() {
+            summary.exchange_updates = exchange_updates.to_vec();
+        }
+    }
+
+    /// Sets the fields of the publish, the same in every view and in the filtered ones
+    fn stamp(&mut self, sequence: u64, publish_time: Timestamp) {
+        for summary in [&mut self.by_venue, &mut self.consolidated, &mut self.fee_adjusted].iter_mut() {
+            summary.sequence = sequence;
+            summary.publish_time = publish_time;
+        }
+        for filtered in self.filtered.values_mut() {
+            filtered.stamp(sequence, publish_time);
+        }
+    }
+}
+
+/// What one subscriber keeps of the shared summaries
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SummaryFilter {
+    depth: usize,
+    /// Every exchange when empty
+    exchanges: Vec<Exchange>,
+}
+
+impl SummaryFilter {
+    pub fn new(depth: usize, exchanges: Vec<Exchange>) -> Self {
+        SummaryFilter{
+            depth: depth,
+            exchanges: exchanges,
+        }
+    }
+
+    /// The summary a subscriber gets for the shared `summary`, numbered as it is.
+    ///
+    /// With exchanges to keep it is the summary built from the book quoted by them only,
+    /// published with the shared one, so each side holds the `depth` best levels they quote
+    /// and the stats describe their book.
+    pub fn view(&self, summary: &SummaryViews, mode: AggregationMode) -> Summary {
+        match summary.filtered.get(self) {
+            Some(filtered) => filtered.view(mode),
+            None => self.apply(summary.view(mode))
+        }
+    }
+
+    /// Drops the levels of the other exchanges then keeps the best `depth` levels of each side.
+    ///
+    /// For the summaries with no book to build them from, the synthetic ones, or the books
+    /// the exchanges kept quote on one side only.
+    pub fn apply(&self, mut summary: Summary) -> Summary {
+        if !self.exchanges.is_empty() {
+            summary.asks.retain(|level| self.keeps(&level.exchange));
+            summary.bids.retain(|level| self.keeps(&level.exchange));
+            for level in summary.consolidated_asks.iter_mut().chain(summary.consolidated_bids.iter_mut()) {
+                level.exchanges.retain(|level| self.keeps(&level.exchange));
+                level.amount = level.exchanges.iter().map(|level| level.amount).sum();
+                level.amount_decimal = level.exchanges.iter()
+                    .filter_map(|level| Decimal::from_str(&level.amount_decimal).ok())
+                    .sum::<Decimal>()
+                    .to_string();
+            }
+            summary.consolidated_asks.retain(|level| !level.exchanges.is_empty());
+            summary.consolidated_bids.retain(|level| !level.exchanges.is_empty());
+            summary.venue_stats.retain(|stats| self.keeps(&stats.exchange));
+            let best_prices = match (summary.asks.last(), summary.bids.last()) {
+                (Some(ask), Some(bid)) => Some((ask.price_decimal.clone(), bid.price_decimal.clone())),
+                _ => summary.consolidated_asks.last().zip(summary.consolidated_bids.last())
+                    .map(|(ask, bid)| (ask.price_decimal.clone(), bid.price_decimal.clone()))
+            };
+            if let Some((ask_price, bid_price)) = best_prices {
+                if let (Ok(ask_price), Ok(bid_price)) = (Decimal::from_str(&ask_price), Decimal::from_str(&bid_price)) {
+                    let spread = ask_price - bid_price;
+                    summary.spread = spread.to_string().parse::<f64>().unwrap_or(summary.spread);
+                    summary.spread_decimal = spread.to_string();
+                }
+            }
+        }
+        keep_best(&mut summary.asks, self.depth);
+        keep_best(&mut summary.bids, self.depth);
+        keep_best(&mut summary.consolidated_asks, self.depth);
+        keep_best(&mut summary.consolidated_bids, self.depth);
+        summary
+    }
+
+    /// Synthetic levels are named after both their exchanges, both must be kept
+    fn keeps(&self, exchange: &str) -> bool {
+        exchange.split('/').all(|exchange| Exchange::from_str(exchange)
+            .is_ok_and(|exchange| self.exchanges.contains(&exchange)))
+    }
+}
+
+/// Ladders are sent best price last
+fn keep_best<T>(levels: &mut Vec<T>, depth: usize) {
+    if levels.len() > depth {
+        levels.drain(..levels.len() - depth);
+    }
+}
+
+/// A filter subscribed to, its summaries are built on each publish until the last one is dropped
+#[derive(Debug)]
+pub struct FilterSubscription {
+    symbol: Symbol,
+    filter: SummaryFilter,
+    filters: Arc<RwLock<HashMap<Symbol, HashMap<SummaryFilter, usize>>>>,
+}
+
+impl Drop for FilterSubscription {
+    fn drop(&mut self) {
+        let mut filters = self.filters.write().unwrap_or_else(|err| err.into_inner());
+        if let Some(symbol_filters) = filters.get_mut(&self.symbol) {
+            if let Some(subscribers) = symbol_filters.get_mut(&self.filter) {
+                *subscribers -= 1;
+                if *subscribers == 0 {
+                    symbol_filters.remove(&self.filter);
+                }
+            }
+            if symbol_filters.is_empty() {
+                filters.remove(&self.symbol);
+            }
+        }
+    }
+}
+
+/// Crossings still active, by symbol, bid exchange and ask exchange
+type ActiveCrossings = BTreeMap<(Symbol, String, String), CrossedMarketEvent>;
+/// The summary published last, if any, and a receiver for the following ones
+type SummarySubscription = (Option<Arc<SummaryViews>>, broadcast::Receiver<Arc<SummaryViews>>);
+
+/// Aggregated books shared by every gRPC subscriber.
+///
+/// The `AggregatedBookSet` is started once, each update produces the `SummaryViews`
+/// of its instrument, fanned out through that instrument's broadcast channel.
+/// Exchange books are added and removed as their pipelines are started and stopped.
+#[derive(Clone, Debug)]
+pub struct BookFeed {
+    summary_tx_chs: Arc<RwLock<HashMap<Symbol, broadcast::Sender<Arc<SummaryViews>>>>>,
+    last_summary: Arc<RwLock<HashMap<Symbol, Arc<SummaryViews>>>>,
+    crossed_tx_ch: broadcast::Sender<CrossedMarketEvent>,
+    active_crossings: Arc<RwLock<ActiveCrossings>>,
+    /// Checked by the aggregation task on each update, the crossings of a removed book end with it
+    crossed_detector: Arc<Mutex<CrossedMarketDetector>>,
+    /// Only written by the aggregation task, read by the unary requests
+    books: Arc<RwLock<AggregatedBookSet>>,
+    /// Native symbol of each book streamed, by canonical symbol and exchange
+    instruments: Arc<RwLock<BTreeMap<(Symbol, Exchange), Symbol>>>,
+    /// Subscribers of each filter keeping some exchanges only, by canonical symbol
+    filters: Arc<RwLock<HashMap<Symbol, HashMap<SummaryFilter, usize>>>>,
+    depth_config: DepthConfig,
+    analytics: BookAnalytics,
+}
+
+impl BookFeed {
+    /// Spawns the aggregation task reading the snapshots published by the exchange pipelines
+    /// of `settings.symbols`, the task ends when `cancel_token` is cancelled
+    pub fn start(settings: BookFeedSettings, snapshot_rx_ch: broadcast::Receiver<SnapshotData>, 
+        cancel_token: CancellationToken, tasks: &mut JoinSet<()>) -> Self {
+
+        let summary_tx_chs = settings.symbols.iter()
+            .chain(settings.synthetics.iter().map(|synthetic| &synthetic.symbol))
+            .map(|symbol| (canonical_symbol(symbol), broadcast::channel(SUMMARY_CHANNEL_CAPACITY).0))
+            .collect();
+        let feed = BookFeed {
+            summary_tx_chs: Arc::new(RwLock::new(summary_tx_chs)),
+            last_summary: Arc::new(RwLock::new(HashMap::new())),
+            crossed_tx_ch: broadcast::channel(CROSSED_CHANNEL_CAPACITY).0,
+            active_crossings: Arc::new(RwLock::new(BTreeMap::new())),
+            crossed_detector: Arc::new(Mutex::new(CrossedMarketDetector::new(settings.crossed_market.clone()))),
+            books: Arc::new(RwLock::new(AggregatedBookSet::new(settings.fees.clone()))),
+            instruments: Arc::new(RwLock::new(BTreeMap::new())),
+            filters: Arc::new(RwLock::new(HashMap::new())),
+            depth_config: settings.depth.clone(),
+            analytics: BookAnalytics::new(settings.analytics.clone()),
+        };
+        tasks.spawn(feed.clone().aggregation_task(settings, snapshot_rx_ch, cancel_token));
+        feed
+    }
+
+    /// Streams `symbol`, if not yet, and takes the snapshots of `exchange` for it again
+    pub fn add_book(&self, exchange: &Exchange, symbol: &str) {
+        let native_symbol = symbol.to_string();
+        let symbol = canonical_symbol(symbol);
+        self.instruments.write().unwrap_or_else(|err| err.into_inner()).insert((symbol.clone(), exchange.clone()), native_symbol);
+        self.books.write().unwrap_or_else(|err| err.into_inner()).add_exchange(exchange, &symbol);
+        let mut summary_tx_chs = self.summary_tx_chs.write().unwrap_or_else(|err| err.into_inner());
+        summary_tx_chs.entry(symbol).or_insert_with(|| broadcast::channel(SUMMARY_CHANNEL_CAPACITY).0);
+    }
+
+    /// Drops the book of `exchange` from the book of `symbol`, the summary of what is left is published
+    /// and the crossings of `exchange` end
+    pub fn remove_book(&self, exchange: &Exchange, symbol: &str) {
+        let symbol = canonical_symbol(symbol);
+        self.instruments.write().unwrap_or_else(|err| err.into_inner()).remove(&(symbol.clone(), exchange.clone()));
+        let mut books = self.books.write().unwrap_or_else(|err| err.into_inner());
+        let book = match books.remove_exchange(exchange, &symbol) {
+            Some(book) => book,
+            None => return
+        };
+        match self.summaries(&symbol, book) {
+            Ok(response) => self.publish(&symbol, response),
+            Err(err) => log::error!("\nError in {:?} {:?}  :\n {:?}", exchange, symbol, err)
+        };
+        let uncrossed = self.crossed_detector.lock().unwrap_or_else(|err| err.into_inner()).remove_exchange(&symbol, exchange, now_millis());
+        for uncrossed in uncrossed {
+            self.publish_crossed(uncrossed.into());
+        }
+    }
+
+    /// Stops streaming `symbol`, its subscriber streams end
+    pub fn remove_symbol(&self, symbol: &str) {
+        let symbol = canonical_symbol(symbol);
+        self.summary_tx_chs.write().unwrap_or_else(|err| err.into_inner()).remove(&symbol);
+        self.last_summary.write().unwrap_or_else(|err| err.into_inner()).remove(&symbol);
+    }
+
+    /// Books streamed as (canonical symbol, exchange, native symbol), by canonical symbol then exchange
+    pub fn instruments(&self) -> Vec<(Symbol, Exchange, Symbol)> {
+        let instruments = self.instruments.read().unwrap_or_else(|err| err.into_inner());
+        instruments.iter().map(|((symbol, exchange), native_symbol)| (symbol.clone(), exchange.clone(), native_symbol.clone())).collect()
+    }
+
+    pub fn depth_config(&self) -> &DepthConfig {
+        &self.depth_config
+    }
+
+    /// Runs `f` on the current book of `symbol`, `None` before its first update
+    pub fn with_book<R>(&self, symbol: &str, f: impl FnOnce(&AggregatedBook) -> R) -> Option<R> {
+        let books = self.books.read().unwrap_or_else(|err| err.into_inner());
+        books.get_book(symbol).map(f)
+    }
+
+    /// Summaries of the current book of `symbol` quoted by `exchanges`, all of them when empty,
+    /// `None` before its first update.
+    ///
+    /// Numbered as the last summary published, built from the book as it is now.
+    pub fn snapshot(&self, symbol: &str, depth: usize, exchanges: &[Exchange]) -> Option<Result<SummaryViews>> {
+        let symbol = canonical_symbol(symbol);
+        let sequence = self.last_summary(&symbol).map_or(0, |last| last.by_venue.sequence);
+        self.with_book(&symbol, |book| {
+            let mut summary = self.filtered_summary(&symbol, book, depth, exchanges)?;
+            summary.stamp(sequence, now_millis());
+            Ok(summary)
+        })
+    }
+
+    /// Summaries of `book` quoted by `exchanges`, all of them when empty, not yet stamped
+    fn filtered_summary(&self, symbol: &Symbol, book: &AggregatedBook, depth: usize, exchanges: &[Exchange]) -> Result<SummaryViews> {
+        let filtered_book;
+        let book = if exchanges.is_empty() {
+            book
+        } else {
+            filtered_book = book.only(exchanges);
+            &filtered_book
+        };
+        let mut summary = set_response_stream(symbol, book, depth, &self.analytics)?;
+        summary.set_exchange_updates(&exchange_updates(symbol, book));
+        Ok(summary)
+    }
+
+    /// Summaries to publish for `book`, with those of each filter subscribed to.
+    ///
+    /// A filter whose exchanges quote one side only has none, its subscribers filter the shared summary.
+    fn summaries(&self, symbol: &Symbol, book: &AggregatedBook) -> Result<SummaryViews> {
+        let mut summary = self.filtered_summary(symbol, book, self.depth_config.max_depth, &[])?;
+        let filters = self.filters.read().unwrap_or_else(|err| err.into_inner());
+        for filter in filters.get(symbol).into_iter().flat_map(|filters| filters.keys()) {
+            if let Ok(filtered) = self.filtered_summary(symbol, book, filter.depth, &filter.exchanges) {
+                summary.filtered.insert(filter.clone(), filtered);
+            }
+        }
+        Ok(summary)
+    }
+
+    /// Last summary published for `symbol`
+    pub fn last_summary(&self, symbol: &str) -> Option<Arc<SummaryViews>> {
+        let last_summary = self.last_summary.read().unwrap_or_else(|err| err.into_inner());
+        last_summary.get(&canonical_symbol(symbol)).cloned()
+    }
+
+    /// Returns the crossings still active and a receiver for the following crossed market events
+    pub fn subscribe_crossed(&self) -> (Vec<CrossedMarketEvent>, broadcast::Receiver<CrossedMarketEvent>) {
+        let active_crossings = self.active_crossings.read().unwrap_or_else(|err| err.into_inner());
+        (active_crossings.values().cloned().collect(), self.crossed_tx_ch.subscribe())
+    }
+
+    fn publish_crossed(&self, event: CrossedMarketEvent) {
+        let mut active_crossings = self.active_crossings.write().unwrap_or_else(|err| err.into_inner());
+        let key = (event.symbol.clone(), event.bid_exchange.clone(), event.ask_exchange.clone());
+        if event.active {
+            active_crossings.insert(key, event.clone());
+        } else {
+            active_crossings.remove(&key);
+        }
+        let _ = self.crossed_tx_ch.send(event);
+    }
+
+    /// Returns the current summary of `symbol`, if any, and a receiver for the following ones,
+    /// `None` when the symbol is not streamed.
+    ///
+    /// Both are taken under the same lock so no update falls between them.
+    pub fn subscribe(&self, symbol: &str) -> Option<SummarySubscription> {
+        let symbol = canonical_symbol(symbol);
+        let summary_tx_chs = self.summary_tx_chs.read().unwrap_or_else(|err| err.into_inner());
+        let summary_tx_ch = summary_tx_chs.get(&symbol)?;
+        let last_summary = self.last_summary.read().unwrap_or_else(|err| err.into_inner());
+        Some((last_summary.get(&symbol).cloned(), summary_tx_ch.subscribe()))
+    }
+
+    /// As `subscribe`, the summaries also carry the ones of `filter` while the `FilterSubscription` is kept.
+    ///
+    /// The current summary, published before the subscription, gets the one of `filter` built from the book as it is now.
+    pub fn subscribe_filtered(&self, symbol: &str, filter: &SummaryFilter)
+        -> Option<(SummarySubscription, Option<FilterSubscription>)> {
+        if filter.exchanges.is_empty() {
+            return self.subscribe(symbol).map(|subscription| (subscription, None));
+        }
+        let symbol = canonical_symbol(symbol);
+        // registered first, every summary published after the subscription carries it
+        let subscription = {
+            let mut filters = self.filters.write().unwrap_or_else(|err| err.into_inner());
+            *filters.entry(symbol.clone()).or_default().entry(filter.clone()).or_insert(0) += 1;
+            FilterSubscription{
+                symbol: symbol.clone(),
+                filter: filter.clone(),
+                filters: self.filters.clone(),
+            }
+        };
+        let (last_summary, summary_rx_ch) = self.subscribe(&symbol)?;
+        let last_summary = last_summary.map(|last| {
+            if last.filtered.contains_key(filter) {
+                return last;
+            }
+            let mut last = (*last).clone();
+            if let Some(Ok(mut filtered)) = self.with_book(&symbol, |book| self.filtered_summary(&symbol, book, filter.depth, &filter.exchanges)) {
+                filtered.stamp(last.by_venue.sequence, last.by_venue.publish_time);
+                last.filtered.insert(filter.clone(), filtered);
+            }
+            Arc::new(last)
+        });
+        Some(((last_summary, summary_rx_ch), Some(subscription)))
+    }
+
+    /// Publishes the summary of `symbol`, numbered after the previous one
+    fn publish(&self, symbol: &Symbol, mut summary: SummaryViews) {
+        let summary_tx_chs = self.summary_tx_chs.read().unwrap_or_else(|err| err.into_inner());
+        let summary_tx_ch = match summary_tx_chs.get(symbol) {
+            Some(summary_tx_ch) => summary_tx_ch,
+            None => {
+                log::warn!("Warning in BookFeed:\nSummary of a symbol not streamed:\n{:?}", symbol);
+                return;
+            }
+        };
+        let mut last_summary = self.last_summary.write().unwrap_or_else(|err| err.into_inner());
+        let sequence = last_summary.get(symbol).map_or(1, |last| last.by_venue.sequence + 1);
+        summary.stamp(sequence, now_millis());
+        let summary = Arc::new(summary);
+        last_summary.insert(symbol.clone(), summary.clone());
+        metrics::SUMMARIES_PUBLISHED.with_label_values(&[symbol]).inc();
+        // No subscribers is not an error, the summary is kept for the next one
+        let _ = summary_tx_ch.send(summary);
+    }
+
+    async fn aggregation_task(self, settings: BookFeedSettings, mut snapshot_rx_ch: broadcast::Receiver<SnapshotData>, 
+        cancel_token: CancellationToken) {
+        let task_name = "--Aggregation Task--";
+        log::info!("{:?} Init", task_name);
+        loop {
+            let val = tokio::select! {
+                _ = cancel_token.cancelled() => break,
+                val = snapshot_rx_ch.recv() => val
+            };
+            match val {
+                Ok(snap_shot) => {
+                    let exchange = snap_shot.exchange.clone();
+                    let symbol = canonical_symbol(&snap_shot.symbol);
+                    {
+                        let mut books = self.books.write().unwrap_or_else(|err| err.into_inner());
+                        // a pipeline just stopped
+                        if books.is_removed(&exchange, &symbol) {
+                            continue;
+                        }
+                        books.update_book(snap_shot);
+                    }
+                    let agrregate_books = self.books.read().unwrap_or_else(|err| err.into_inner());
+                    let agrregate_book_result = match agrregate_books.get_book(&symbol) {
+                        Some(agrregate_book_result) => agrregate_book_result,
+                        None => continue
+                    };
+                    match self.summaries(&symbol, agrregate_book_result){
+                        Ok(response) => self.publish(&symbol, response),
+                        Err(err) => log::error!("\nError in {:?} {:?}  :\n {:?}", exchange, symbol, err)
+                    };
+                    let crossed = self.crossed_detector.lock().unwrap_or_else(|err| err.into_inner()).check(&symbol, agrregate_book_result, now_millis());
+                    for crossed in crossed {
+                        self.publish_crossed(crossed.into());
+                    }
+                    for synthetic in settings.synthetics.iter().filter(|synthetic| synthetic.is_leg(&symbol)) {
+                        let (base, quote) = match (agrregate_books.get_book(&synthetic.base_leg), agrregate_books.get_book(&synthetic.quote_leg)) {
+                            (Some(base), Some(quote)) => (base, quote),
+                            // waiting for the first update of the other leg
+                            _ => continue
+                        };
+                        let mut updates = exchange_updates(&synthetic.base_leg, base);
+                        updates.extend(exchange_updates(&synthetic.quote_leg, quote));
+                        match synthetic_response_stream(synthetic, base, quote, settings.depth.max_depth){
+                            Ok(mut response) => {
+                                response.set_exchange_updates(&updates);
+                                self.publish(&synthetic.symbol, response)
+                            },
+                            Err(err) => log::error!("\nError in synthetic {:?}  :\n {:?}", synthetic.symbol, err)
+                        };
+                    }
+                },
+                Err(broadcast::error::RecvError::Lagged(x)) => {
+                    log::warn!("Warning in {:?}:\nsnapshot_rx_ch lagged:\n{:?}\n", task_name, x);
+                    metrics::BROADCAST_LAGGED.with_label_values(&["snapshot_rx_ch"]).inc_by(x);
+                    continue;
+                },
+                Err(broadcast::error::RecvError::Closed) => break
+            };
+        }
+        log::info!("{:?} End", task_name);
+    }
+}
+
+/// Last update of each exchange quoting the book of `symbol`
+fn exchange_updates(symbol: &str, book: &AggregatedBook) -> Vec<ExchangeUpdate> {
+    let symbol = canonical_symbol(symbol);
+    book.last_updates().into_iter()
+        .map(|(exchange, update_id, received_time)| ExchangeUpdate{
+            exchange: exchange.to_string(),
+            symbol: symbol.clone(),
+            sequence: update_id,
+            received_time: received_time,
+        })
+        .collect()
+}
+
+/// Forwards the updates of a shared feed to one gRPC client, starting with the current state,
+/// `view` shapes each update for this client and may keep what it sent before, the updates
+/// it maps to None are not sent.
+///
+/// Ends when the client goes away or `cancel_token` is cancelled, dropping `tx` closes the client stream.
+pub async fn subscriber_task<T, U, V>(
+    current_state: Vec<T>,
+    mut update_rx_ch: broadcast::Receiver<T>,
+    tx: mpsc::Sender<Result<U, Status>>,
+    cancel_token: CancellationToken,
+    mut view: V)
+where
+    T: Clone + Send + 'static,
+    U: Send + 'static,
+    V: FnMut(T) -> Option<U> + Send + 'static
+{
+
+    let task_name = "--Subscriber Task--";
+    // A new subscriber gets the current state straight away
+    for update in current_state {
+        let update = match view(update) {
+            Some(update) => update,
+            None => continue
+        };
+        if tx.send(Ok(update)).await.is_err() {
+            return;
+        }
+    }
+    loop{
+        let val = tokio::select! {
+            _ = cancel_token.cancelled() => break,
+            _ = tx.closed() => {
+                log::info!("Info in {:?}:\nClient disconnected", task_name);
+                break;
+            },
+            val = update_rx_ch.recv() => val
+        };
+        match val {
+            Ok(update) => {
+                let update = match view(update) {
+                    Some(update) => update,
+                    None => continue
+                };
+                let sent = tokio::select! {
+                    _ = cancel_token.cancelled() => break,
+                    sent = tx.send(Ok(update)) => sent
+                };
+                if sent.is_err() {
+                    log::info!("Info in {:?}:\nClient disconnected", task_name);
+                    break;
+                }
+            },
+            Err(broadcast::error::RecvError::Lagged(x)) => {
+                log::warn!("Warning in {:?}:\nupdate_rx_ch lagged:\n{:?}\n", task_name, x);
+                metrics::BROADCAST_LAGGED.with_label_values(&["update_rx_ch"]).inc_by(x);
+                continue;
+            },
+            Err(broadcast::error::RecvError::Closed) => break
+        };
+    }
+}
+
+/// Time between two messages of a subscriber asking for at most `max_update_rate` per second, none when 0
+pub fn update_interval(max_update_rate: u32) -> Duration {
+    match max_update_rate {
+        0 => Duration::from_secs(0),
+        rate => Duration::from_secs(1) / rate
+    }
+}
+
+/// Forwards the latest state of a shared feed to one gRPC client, one message per `min_interval` at most.
+///
+/// Updates arriving faster, or while the client has not read the previous messages yet, replace
+/// the one pending, so the client always gets the latest state and never holds the feed back.
+/// `view` shapes each message sent and may keep what it sent before.
+///
+/// Ends when the client goes away or `cancel_token` is cancelled, dropping `tx` closes the client stream.
+pub async fn conflating_subscriber_task<T, U, V>(
+    current_state: Option<T>,
+    mut update_rx_ch: broadcast::Receiver<T>,
+    tx: mpsc::Sender<Result<U, Status>>,
+    cancel_token: CancellationToken,
+    min_interval: Duration,
+    mut view: V)
+where
+    T: Clone + Send + 'static,
+    U: Send + 'static,
+    V: FnMut(T) -> U + Send + 'static
+{
+
+    let task_name = "--Conflating Subscriber Task--";
+    // A new subscriber gets the current state straight away
+    let mut pending = current_state;
+    let mut next_send = Instant::now();
+    loop{
+        tokio::select! {
+            _ = cancel_token.cancelled() => break,
+            _ = tx.closed() => {
+                log::info!("Info in {:?}:\nClient disconnected", task_name);
+                break;
+            },
+            val = update_rx_ch.recv() => match val {
+                Ok(update) => pending = Some(update),
+                // the next update is newer than the ones missed
+                Err(broadcast::error::RecvError::Lagged(x)) => {
+                    metrics::BROADCAST_LAGGED.with_label_values(&["update_rx_ch"]).inc_by(x);
+                    continue;
+                },
+                Err(broadcast::error::RecvError::Closed) => break
+            },
+            permit = send_slot(&tx, next_send), if pending.is_some() => {
+                let permit = match permit {
+                    Ok(permit) => permit,
+                    Err(_) => break
+                };
+                if let Some(update) = pending.take() {
+                    permit.send(Ok(view(update)));
+                }
+                next_send = Instant::now() + min_interval;
+            }
+        }
+    }
+}
+
+/// Room for one message in `tx`, not before `at`
+async fn send_slot<U>(tx: &mpsc::Sender<U>, at: Instant) -> Result<mpsc::Permit<'_, U>, mpsc::error::SendError<()>> {
+    tokio::time::sleep_until(at).await;
+    tx.reserve().await
+}
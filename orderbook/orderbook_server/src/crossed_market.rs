@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use rust_decimal::Decimal;
+use crate::*;
+use crate::aggregated_order_book::{AggregatedBook, Level, BPS};
+
+/// Best bid of `bid_exchange` above the best ask of `ask_exchange`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CrossedMarket {
+    pub symbol: Symbol,
+    pub bid_exchange: Exchange,
+    pub bid_price: Price,
+    pub ask_exchange: Exchange,
+    pub ask_price: Price,
+    /// Volume bought on `ask_exchange` and sold on `bid_exchange` with a gain once fees are paid
+    pub volume: Volume,
+    /// Fee-adjusted best bid less fee-adjusted best ask, in basis points of the ask
+    pub edge_bps: Decimal,
+    /// False on the last event of a crossing, once the markets uncross
+    pub active: bool,
+    pub since: Timestamp,
+    pub timestamp: Timestamp,
+}
+
+impl From<CrossedMarket> for CrossedMarketEvent {
+    fn from(crossed: CrossedMarket) -> Self {
+        CrossedMarketEvent{
+            symbol: crossed.symbol,
+            bid_exchange: crossed.bid_exchange.to_string(),
+            bid_price: crossed.bid_price.to_string().parse::<f64>().unwrap_or_default(),
+            ask_exchange: crossed.ask_exchange.to_string(),
+            ask_price: crossed.ask_price.to_string().parse::<f64>().unwrap_or_default(),
+            volume: crossed.volume.to_string().parse::<f64>().unwrap_or_default(),
+            edge_bps: crossed.edge_bps.to_string().parse::<f64>().unwrap_or_default(),
+            active: crossed.active,
+            since: crossed.since,
+            timestamp: crossed.timestamp,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Crossing {
+    last: CrossedMarket,
+    reported: bool,
+}
+
+impl Crossing {
+    /// The last event of the crossing ended at `now`, None when it was never reported
+    fn uncrossed(self, now: Timestamp) -> Option<CrossedMarket> {
+        if !self.reported {
+            return None;
+        }
+        let mut uncrossed = self.last;
+        uncrossed.active = false;
+        uncrossed.timestamp = now;
+        log::info!("Market uncrossed:\n{:?}", uncrossed);
+        Some(uncrossed)
+    }
+}
+
+/// Tracks the crossings between every pair of exchanges of the aggregated books.
+///
+/// A crossing is reported once, when it has lasted `persist_ms` with the edge required,
+/// then again when it ends. Both are only noticed on a book update, or when the book of
+/// one of its exchanges is removed.
+#[derive(Debug)]
+pub struct CrossedMarketDetector {
+    config: CrossedMarketConfig,
+    crossings: HashMap<(Symbol, Exchange, Exchange), Crossing>,
+}
+
+impl CrossedMarketDetector {
+    pub fn new(config: CrossedMarketConfig) -> Self {
+        CrossedMarketDetector{
+            config: config,
+            crossings: HashMap::new(),
+        }
+    }
+
+    /// Checks the book of `symbol` after an update at `now`, returns the crossings to report
+    pub fn check(&mut self, symbol: &Symbol, book: &AggregatedBook, now: Timestamp) -> Vec<CrossedMarket> {
+        let mut events = Vec::new();
+        let exchanges = book.exchanges();
+        for bid_exchange in exchanges.iter() {
+            for ask_exchange in exchanges.iter().filter(|exchange| *exchange != bid_exchange) {
+                let key = (symbol.clone(), bid_exchange.clone(), ask_exchange.clone());
+                match self.crossed(symbol, book, bid_exchange, ask_exchange, now) {
+                    Some(mut crossed) => {
+                        let crossing = self.crossings.entry(key).or_insert_with(|| Crossing{
+                            last: crossed.clone(),
+                            reported: false,
+                        });
+                        crossed.since = crossing.last.since;
+                        crossing.last = crossed.clone();
+                        if !crossing.reported && now.saturating_sub(crossed.since) >= self.config.persist_ms {
+                            crossing.reported = true;
+                            log::warn!("Crossed market:\n{:?}", crossed);
+                            events.push(crossed);
+                        }
+                    },
+                    None => {
+                        if let Some(uncrossed) = self.crossings.remove(&key).and_then(|crossing| crossing.uncrossed(now)) {
+                            events.push(uncrossed);
+                        }
+                    }
+                }
+            }
+        }
+        events
+    }
+
+    /// Ends the crossings of `exchange` once its book of `symbol` is removed, returns the ones to report
+    pub fn remove_exchange(&mut self, symbol: &Symbol, exchange: &Exchange, now: Timestamp) -> Vec<CrossedMarket> {
+        let keys: Vec<(Symbol, Exchange, Exchange)> = self.crossings.keys()
+            .filter(|(crossed_symbol, bid_exchange, ask_exchange)| crossed_symbol == symbol && (bid_exchange == exchange || ask_exchange == exchange))
+            .cloned()
+            .collect();
+        keys.iter()
+            .filter_map(|key| self.crossings.remove(key).and_then(|crossing| crossing.uncrossed(now)))
+            .collect()
+    }
+
+    fn crossed(&self, symbol: &Symbol, book: &AggregatedBook, bid_exchange: &Exchange, ask_exchange: &Exchange,
+        now: Timestamp) -> Option<CrossedMarket> {
+
+        let best_bid = book.bids().find(|level| &level.exchange == bid_exchange)?;
+        let best_ask = book.asks().find(|level| &level.exchange == ask_exchange)?;
+        if best_bid.price <= best_ask.price {
+            return None;
+        }
+        let bids = || book.fee_adjusted_bids().filter(move |level| &level.exchange == bid_exchange);
+        let asks = || book.fee_adjusted_asks().filter(move |level| &level.exchange == ask_exchange);
+        let edge_bps = match (bids().next(), asks().next()) {
+            (Some(bid), Some(ask)) if !ask.price.is_zero() => (bid.price - ask.price) / ask.price * Decimal::from(BPS),
+            _ => return None
+        };
+        if let Some(min_edge_bps) = self.config.min_edge_bps {
+            if edge_bps < min_edge_bps {
+                return None;
+            }
+        }
+        Some(CrossedMarket{
+            symbol: symbol.clone(),
+            bid_exchange: bid_exchange.clone(),
+            bid_price: best_bid.price,
+            ask_exchange: ask_exchange.clone(),
+            ask_price: best_ask.price,
+            volume: executable_volume(bids(), asks()),
+            edge_bps: edge_bps,
+            active: true,
+            since: now,
+            timestamp: now,
+        })
+    }
+}
+
+/// Matches the fee-adjusted bids against the fee-adjusted asks while buying at the ask still
+/// sells at a gain, returns the volume matched
+fn executable_volume(mut bids: impl Iterator<Item = Level>, mut asks: impl Iterator<Item = Level>) -> Volume {
+    let mut bid_level = bids.next();
+    let mut ask_level = asks.next();
+    let mut volume = Decimal::ZERO;
+    loop {
+        let (bid, ask) = match (bid_level.as_mut(), ask_level.as_mut()) {
+            (Some(bid), Some(ask)) if bid.price > ask.price => (bid, ask),
+            _ => break
+        };
+        let matched = bid.volume.min(ask.volume);
+        volume += matched;
+        bid.volume -= matched;
+        ask.volume -= matched;
+        if bid.volume.is_zero() {
+            bid_level = bids.next();
+        }
+        if ask.volume.is_zero() {
+            ask_level = asks.next();
+        }
+    }
+    volume
+}
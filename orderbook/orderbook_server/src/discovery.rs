@@ -0,0 +1,43 @@
+use crate::book_feed::BookFeed;
+use crate::status_feed::StatusFeed;
+use crate::auth::Grant;
+use crate::orderbook::{ExchangeInstruments, Instrument};
+
+/// Instruments streamed and granted to the client, by canonical symbol then exchange,
+/// with the state of their feed and the receive time of their last update
+pub fn instruments(feed: &BookFeed, status_feed: &StatusFeed, grant: &Grant) -> Vec<Instrument> {
+    feed.instruments().into_iter()
+        .filter(|(symbol, exchange, _)| grant.allows(symbol, &[&exchange.to_string()]))
+        .map(|(symbol, exchange, native_symbol)| {
+            let state = status_feed.status(&exchange, &symbol).map_or(0, |event| event.state);
+            let last_update_time = feed.with_book(&symbol, |book| book.last_updates().into_iter()
+                    .find(|(book_exchange, _, _)| book_exchange == &exchange)
+                    .map_or(0, |(_, _, received_time)| received_time))
+                .unwrap_or(0);
+            Instrument{
+                exchange: exchange.to_string(),
+                symbol: native_symbol,
+                canonical_symbol: symbol,
+                state: state,
+                last_update_time: last_update_time,
+            }
+        })
+        .collect()
+}
+
+/// The instruments grouped by exchange, in exchange order
+pub fn exchanges(instruments: Vec<Instrument>) -> Vec<ExchangeInstruments> {
+    let mut exchanges: Vec<ExchangeInstruments> = Vec::new();
+    let mut instruments = instruments;
+    instruments.sort_by(|a, b| (&a.exchange, &a.canonical_symbol).cmp(&(&b.exchange, &b.canonical_symbol)));
+    for instrument in instruments {
+        match exchanges.last_mut() {
+            Some(last) if last.exchange == instrument.exchange => last.instruments.push(instrument),
+            _ => exchanges.push(ExchangeInstruments{
+                exchange: instrument.exchange.clone(),
+                instruments: vec![instrument],
+            })
+        }
+    }
+    exchanges
+}
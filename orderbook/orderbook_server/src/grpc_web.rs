@@ -0,0 +1,244 @@
+use std::{
+    convert::Infallible,
+    net::TcpListener,
+    sync::Arc
+};
+use futures::{future::poll_fn, stream, Stream};
+use hyper::{
+    body::{Bytes, HttpBody},
+    header::{HeaderMap, HeaderValue, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN,
+        ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE, CONTENT_TYPE, ORIGIN, VARY},
+    server::{accept::{self, Accept}, Builder},
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode, Version
+};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_util::sync::CancellationToken;
+use tonic::{body::BoxBody, codegen::{Never, Service}, Status};
+use common::{ReloadingTls, tokio_rustls::rustls::ServerConfig};
+use crate::tls::tls_incoming;
+
+pub const GRPC_WEB: &str = "application/grpc-web";
+pub const GRPC_WEB_PROTO: &str = "application/grpc-web+proto";
+pub const GRPC_WEB_TEXT: &str = "application/grpc-web-text";
+pub const GRPC_WEB_TEXT_PROTO: &str = "application/grpc-web-text+proto";
+const GRPC: &str = "application/grpc";
+/// Flag of the last frame of a gRPC-Web response, carrying the trailers
+pub const TRAILERS_FLAG: u8 = 0x80;
+
+/// Headers the browsers may send, asked for in the preflight
+const ALLOWED_HEADERS: &str = "content-type, x-grpc-web, x-user-agent, authorization, grpc-timeout";
+/// Headers the browsers may read, the status of the calls answered without a body
+const EXPOSED_HEADERS: &str = "grpc-status, grpc-message, grpc-status-details-bin";
+/// How long the browsers may cache a preflight, in seconds
+const PREFLIGHT_MAX_AGE: &str = "86400";
+
+/// How the messages are framed in the body, binary or base64 text for the clients without binary support
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Encoding {
+    Binary,
+    Text,
+}
+
+impl Encoding {
+    fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let content_type = headers.get(CONTENT_TYPE)?.to_str().ok()?;
+        match content_type.split(';').next().unwrap_or("").trim() {
+            GRPC_WEB | GRPC_WEB_PROTO => Some(Encoding::Binary),
+            GRPC_WEB_TEXT | GRPC_WEB_TEXT_PROTO => Some(Encoding::Text),
+            _ => None
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Encoding::Binary => GRPC_WEB_PROTO,
+            Encoding::Text => GRPC_WEB_TEXT_PROTO
+        }
+    }
+
+    fn decode(self, body: Bytes) -> Result<Bytes, Status> {
+        match self {
+            Encoding::Binary => Ok(body),
+            Encoding::Text => base64::decode(&body).map(Bytes::from)
+                .map_err(|err| Status::invalid_argument(format!("invalid base64 body: {}", err)))
+        }
+    }
+
+    fn encode(self, frame: Bytes) -> Bytes {
+        match self {
+            Encoding::Binary => frame,
+            // every chunk is padded on its own, the clients decode them one after the other
+            Encoding::Text => Bytes::from(base64::encode(&frame))
+        }
+    }
+}
+
+/// The browser origins allowed to call the service, any when none is configured
+struct Cors {
+    allowed_origins: Vec<String>,
+}
+
+impl Cors {
+    fn allows(&self, origin: &HeaderValue) -> bool {
+        self.allowed_origins.is_empty() || origin.to_str()
+            .is_ok_and(|origin| self.allowed_origins.iter().any(|allowed| allowed == origin))
+    }
+
+    fn add_headers(&self, origin: HeaderValue, headers: &mut HeaderMap) {
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        headers.insert(ACCESS_CONTROL_EXPOSE_HEADERS, HeaderValue::from_static(EXPOSED_HEADERS));
+        headers.insert(VARY, HeaderValue::from_static("origin"));
+    }
+}
+
+/// Serves `service` to the browsers over gRPC-Web on `listener`, HTTP/1.1 with CORS for
+/// `allowed_origins`, until `cancel_token` is cancelled.
+///
+/// Each call is translated into a gRPC call of `service`, its response trailers are sent
+/// as the last frame of the body. Unary and server streaming calls only, as in gRPC-Web.
+pub async fn serve<S>(listener: TcpListener, service: S, allowed_origins: Vec<String>, cancel_token: CancellationToken) -> hyper::Result<()>
+where
+    S: Service<Request<Body>, Response = Response<BoxBody>, Error = Never> + Clone + Send + 'static,
+    S::Future: Send + 'static
+{
+    serve_on(Server::from_tcp(listener)?, service, allowed_origins, cancel_token).await
+}
+
+/// As `serve`, over TLS so the browsers do not send their tokens in cleartext
+pub async fn serve_tls<S>(listener: tokio::net::TcpListener, tls: Arc<ReloadingTls<ServerConfig>>, service: S, allowed_origins: Vec<String>,
+    cancel_token: CancellationToken) -> hyper::Result<()>
+where
+    S: Service<Request<Body>, Response = Response<BoxBody>, Error = Never> + Clone + Send + 'static,
+    S::Future: Send + 'static
+{
+    let incoming = accept::from_stream(tls_incoming(listener, tls, cancel_token.clone()));
+    serve_on(Server::builder(incoming), service, allowed_origins, cancel_token).await
+}
+
+async fn serve_on<I, S>(server: Builder<I>, service: S, allowed_origins: Vec<String>, cancel_token: CancellationToken) -> hyper::Result<()>
+where
+    I: Accept,
+    I::Conn: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    I::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    S: Service<Request<Body>, Response = Response<BoxBody>, Error = Never> + Clone + Send + 'static,
+    S::Future: Send + 'static
+{
+    let cors = Arc::new(Cors{allowed_origins: allowed_origins});
+    let make_service = make_service_fn(move |_| {
+        let (service, cors) = (service.clone(), cors.clone());
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let (service, cors) = (service.clone(), cors.clone());
+                async move { Ok::<_, Infallible>(respond(service, &cors, request).await) }
+            }))
+        }
+    });
+    server
+        .serve(make_service)
+        .with_graceful_shutdown(async move { cancel_token.cancelled().await })
+        .await
+}
+
+async fn respond<S>(service: S, cors: &Cors, request: Request<Body>) -> Response<Body>
+where
+    S: Service<Request<Body>, Response = Response<BoxBody>, Error = Never>
+{
+    // requests without an origin do not come from a browser
+    let origin = request.headers().get(ORIGIN).cloned();
+    if let Some(origin) = &origin {
+        if !cors.allows(origin) {
+            return empty_response(StatusCode::FORBIDDEN);
+        }
+    }
+    let mut response = match (request.method(), Encoding::from_headers(request.headers())) {
+        (&Method::OPTIONS, _) => {
+            let mut response = empty_response(StatusCode::NO_CONTENT);
+            let headers = response.headers_mut();
+            headers.insert(ACCESS_CONTROL_ALLOW_METHODS, HeaderValue::from_static("POST, OPTIONS"));
+            // the same whatever the browser asks for, it refuses the call sending any other
+            headers.insert(ACCESS_CONTROL_ALLOW_HEADERS, HeaderValue::from_static(ALLOWED_HEADERS));
+            headers.insert(ACCESS_CONTROL_MAX_AGE, HeaderValue::from_static(PREFLIGHT_MAX_AGE));
+            response
+        },
+        (&Method::POST, Some(encoding)) => grpc_call(service, encoding, request).await,
+        (&Method::POST, None) => empty_response(StatusCode::UNSUPPORTED_MEDIA_TYPE),
+        _ => empty_response(StatusCode::METHOD_NOT_ALLOWED)
+    };
+    if let Some(origin) = origin {
+        cors.add_headers(origin, response.headers_mut());
+    }
+    response
+}
+
+async fn grpc_call<S>(mut service: S, encoding: Encoding, request: Request<Body>) -> Response<Body>
+where
+    S: Service<Request<Body>, Response = Response<BoxBody>, Error = Never>
+{
+    let (mut parts, body) = request.into_parts();
+    // no client streaming in gRPC-Web, the request is one message
+    let body = hyper::body::to_bytes(body).await
+        .map_err(|err| Status::internal(format!("reading the request failed: {}", err)))
+        .and_then(|body| encoding.decode(body));
+    let response = match body {
+        Ok(body) => {
+            parts.version = Version::HTTP_2;
+            parts.headers.insert(CONTENT_TYPE, HeaderValue::from_static(GRPC));
+            let ready = poll_fn(|cx| service.poll_ready(cx)).await;
+            let call = match ready {
+                Ok(()) => service.call(Request::from_parts(parts, Body::from(body))).await,
+                Err(never) => Err(never)
+            };
+            match call {
+                Ok(response) => response,
+                Err(never) => match never {}
+            }
+        },
+        Err(status) => status.to_http()
+    };
+    let (mut parts, body) = response.into_parts();
+    parts.version = Version::HTTP_11;
+    parts.headers.insert(CONTENT_TYPE, HeaderValue::from_static(encoding.content_type()));
+    Response::from_parts(parts, Body::wrap_stream(frames(body, encoding)))
+}
+
+/// The message frames of a gRPC response body then its trailers in a last frame,
+/// an error of the body ends it with the trailers of its status
+fn frames(body: BoxBody, encoding: Encoding) -> impl Stream<Item = Result<Bytes, Infallible>> {
+    stream::unfold(Some(body), move |body| async move {
+        let mut body = body?;
+        let frame = match body.data().await {
+            Some(Ok(data)) => return Some((Ok(encoding.encode(data)), Some(body))),
+            Some(Err(status)) => trailers_frame(status.to_http().headers()),
+            None => match body.trailers().await {
+                Ok(Some(trailers)) => trailers_frame(&trailers),
+                // the status was in the headers
+                Ok(None) => return None,
+                Err(status) => trailers_frame(status.to_http().headers())
+            }
+        };
+        Some((Ok(encoding.encode(frame)), None))
+    })
+}
+
+/// The trailers as a gRPC-Web frame, one "name:value\r\n" line each
+pub fn trailers_frame(trailers: &HeaderMap) -> Bytes {
+    let mut lines = Vec::new();
+    for (name, value) in trailers.iter().filter(|(name, _)| *name != CONTENT_TYPE) {
+        lines.extend_from_slice(name.as_str().as_bytes());
+        lines.push(b':');
+        lines.extend_from_slice(value.as_bytes());
+        lines.extend_from_slice(b"\r\n");
+    }
+    let mut frame = Vec::with_capacity(5 + lines.len());
+    frame.push(TRAILERS_FLAG);
+    frame.extend_from_slice(&(lines.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&lines);
+    Bytes::from(frame)
+}
+
+fn empty_response(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
@@ -0,0 +1,162 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc
+};
+use tokio::{
+    sync::{broadcast, mpsc, watch},
+    task::JoinSet
+};
+use tokio_util::sync::CancellationToken;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+use common::metrics;
+use crate::status_feed::StatusFeed;
+use crate::orderbook::{FeedStatusEvent, feed_status_event::State};
+
+pub mod proto {
+    tonic::include_proto!("grpc.health.v1");
+}
+
+use proto::{HealthCheckRequest, HealthCheckResponse, health_check_response::ServingStatus};
+use proto::health_server::Health;
+
+/// Name of the whole server in the health protocol
+pub const SERVER: &str = "";
+pub const ORDERBOOK_SERVICE: &str = "orderbook.OrderbookAggregator";
+pub const ADMIN_SERVICE: &str = "orderbook.Admin";
+pub const HEALTH_SERVICE: &str = "grpc.health.v1.Health";
+pub const REFLECTION_SERVICE: &str = "grpc.reflection.v1alpha.ServerReflection";
+
+/// Standard gRPC health service, the orderbook and the server are NOT_SERVING
+/// until at least one exchange book is synced, the admin service is unknown when not served
+#[derive(Clone, Debug)]
+pub struct HealthService {
+    statuses_tx_ch: Arc<watch::Sender<BTreeMap<String, ServingStatus>>>,
+    cancel_token: CancellationToken
+}
+
+impl HealthService {
+    /// Spawns the task following the exchange feeds status, `admin_enabled` when the admin service is served
+    pub fn start(status_feed: &StatusFeed, admin_enabled: bool, cancel_token: CancellationToken, tasks: &mut JoinSet<()>) -> Self {
+        let service = HealthService::new(admin_enabled, cancel_token.clone());
+        let (current_state, status_rx_ch) = status_feed.subscribe();
+        tasks.spawn(service.clone().health_task(current_state, status_rx_ch, cancel_token));
+        service
+    }
+
+    fn new(admin_enabled: bool, cancel_token: CancellationToken) -> Self {
+        let mut statuses = vec![
+            (SERVER.to_string(), ServingStatus::NotServing),
+            (ORDERBOOK_SERVICE.to_string(), ServingStatus::NotServing),
+            (HEALTH_SERVICE.to_string(), ServingStatus::Serving),
+            (REFLECTION_SERVICE.to_string(), ServingStatus::Serving),
+        ];
+        if admin_enabled {
+            statuses.push((ADMIN_SERVICE.to_string(), ServingStatus::Serving));
+        }
+        let (statuses_tx_ch, _) = watch::channel(statuses.into_iter().collect());
+        HealthService{
+            statuses_tx_ch: Arc::new(statuses_tx_ch),
+            cancel_token: cancel_token
+        }
+    }
+
+    /// Status of a service, None when the service is unknown
+    pub fn status(&self, service: &str) -> Option<ServingStatus> {
+        self.statuses_tx_ch.borrow().get(service).copied()
+    }
+
+    pub fn set_status(&self, service: &str, status: ServingStatus) {
+        if self.status(service) == Some(status) {
+            return;
+        }
+        let mut statuses = self.statuses_tx_ch.borrow().clone();
+        statuses.insert(service.to_string(), status);
+        self.statuses_tx_ch.send_replace(statuses);
+    }
+
+    /// The orderbook is served while at least one exchange book is synced
+    fn set_orderbook_status(&self, synced: &BTreeSet<(String, String)>) {
+        let status = if synced.is_empty() { ServingStatus::NotServing } else { ServingStatus::Serving };
+        self.set_status(ORDERBOOK_SERVICE, status);
+        self.set_status(SERVER, status);
+    }
+
+    async fn health_task(self, current_state: Vec<FeedStatusEvent>,
+        mut status_rx_ch: broadcast::Receiver<FeedStatusEvent>, cancel_token: CancellationToken) {
+        let task_name = "--Health Task--";
+        log::info!("{:?} Init", task_name);
+        let mut synced = BTreeSet::new();
+        for event in current_state {
+            track_synced(&mut synced, event);
+        }
+        self.set_orderbook_status(&synced);
+        loop {
+            let val = tokio::select! {
+                _ = cancel_token.cancelled() => break,
+                val = status_rx_ch.recv() => val
+            };
+            match val {
+                Ok(event) => track_synced(&mut synced, event),
+                Err(broadcast::error::RecvError::Lagged(x)) => {
+                    log::warn!("Warning in {:?}:\nstatus_rx_ch lagged:\n{:?}\n", task_name, x);
+                    metrics::BROADCAST_LAGGED.with_label_values(&["status_rx_ch"]).inc_by(x);
+                    continue;
+                },
+                Err(broadcast::error::RecvError::Closed) => break
+            }
+            self.set_orderbook_status(&synced);
+        }
+        // the server is going down
+        self.set_orderbook_status(&BTreeSet::new());
+        log::info!("{:?} End", task_name);
+    }
+}
+
+/// Keeps the exchange books currently synced
+fn track_synced(synced: &mut BTreeSet<(String, String)>, event: FeedStatusEvent) {
+    let key = (event.exchange, event.symbol);
+    if event.state == State::Synced as i32 {
+        synced.insert(key);
+    } else {
+        synced.remove(&key);
+    }
+}
+
+#[tonic::async_trait]
+impl Health for HealthService {
+    async fn check(&self, request: Request<HealthCheckRequest>) -> Result<Response<HealthCheckResponse>, Status> {
+        let service = request.into_inner().service;
+        let status = self.status(&service)
+            .ok_or_else(|| Status::not_found(format!("unknown service {}", service)))?;
+        Ok(Response::new(HealthCheckResponse{status: status as i32}))
+    }
+
+    type WatchStream = ReceiverStream<Result<HealthCheckResponse, Status>>;
+
+    /// Sends the current status of the service, then every change, SERVICE_UNKNOWN while it is unknown
+    async fn watch(&self, request: Request<HealthCheckRequest>) -> Result<Response<Self::WatchStream>, Status> {
+        let service = request.into_inner().service;
+        let mut statuses_rx_ch = self.statuses_tx_ch.subscribe();
+        let cancel_token = self.cancel_token.child_token();
+        let (tx, rx) = mpsc::channel(4);
+        tokio::spawn(async move {
+            let mut last_status = None;
+            loop {
+                let status = statuses_rx_ch.borrow_and_update().get(&service).copied().unwrap_or(ServingStatus::ServiceUnknown);
+                if last_status != Some(status) {
+                    if tx.send(Ok(HealthCheckResponse{status: status as i32})).await.is_err() {
+                        break;
+                    }
+                    last_status = Some(status);
+                }
+                tokio::select! {
+                    _ = cancel_token.cancelled() => break,
+                    _ = tx.closed() => break,
+                    changed = statuses_rx_ch.changed() => if changed.is_err() { break }
+                }
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
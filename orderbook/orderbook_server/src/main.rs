@@ -7,33 +7,78 @@
       unused_qualifications,
       warnings
   )]
+// fields are named in full, `field: field`, across the crates
+#![allow(clippy::redundant_field_names)]
+// tonic::Status is the error of every call
+#![allow(clippy::result_large_err)]
 
 mod orderbook;
 mod orderbook_service;
 mod aggregated_order_book;
+mod book_feed;
+mod status_feed;
+mod synthetic_book;
+mod crossed_market;
+mod quote_cost;
+mod analytics;
+mod book_deltas;
+mod health;
+mod reflection;
+mod tls;
+mod auth;
+mod admin;
+mod discovery;
+mod metrics_server;
+mod grpc_web;
 
 #[cfg(test)]
 mod tests;
 
 
 use anyhow::Result;
-use std::io::{ Error, ErrorKind};
-use tokio::sync::{broadcast, mpsc};
+use rust_decimal::{Decimal, prelude::FromPrimitive};
+use std::{
+    str::FromStr,
+    collections::HashMap,
+    sync::Arc,
+    io::{ Error, ErrorKind}
+};
+use tokio::{
+    net::TcpListener,
+    sync::{broadcast, mpsc},
+    task::JoinSet
+};
+use tokio_util::sync::CancellationToken;
+use futures::FutureExt;
 use tokio_stream::wrappers::ReceiverStream;
 use lazy_static::lazy_static;
 use common::*;
-use gateway_in::exchanges_services::{binance::*, bitstamp::*, ExchangeInit};
-use crate::aggregated_order_book::AggregatedBook;
+use crate::aggregated_order_book::{AggregatedBook, ConsolidatedLevel};
+use crate::book_feed::{BookFeed, BookFeedSettings, SummaryFilter, SummaryViews, subscriber_task, conflating_subscriber_task, update_interval};
+use crate::status_feed::StatusFeed;
+use crate::synthetic_book::{SyntheticBook, SyntheticLevel};
+use crate::quote_cost::{OrderSize, QuoteCost};
+use crate::analytics::{BookAnalytics, BookStats};
+use crate::book_deltas::{DeltaEncoder, DELTA_REFRESH_INTERVAL};
+use crate::health::{HealthService, proto::health_server::HealthServer};
+use crate::tls::tls_incoming;
+use crate::auth::{Authenticator, Grant};
+use crate::admin::{exchange_pipelines, AdminService, Subscriptions, SHUTDOWN_TIMEOUT};
+use crate::reflection::{ReflectionService, FILE_DESCRIPTOR_SET, proto::server_reflection_server::ServerReflectionServer};
 use tonic::{transport::Server, Request, Response, Status};
 use orderbook::orderbook_aggregator_server::{OrderbookAggregator, OrderbookAggregatorServer};
-use orderbook::{Summary, Level ,Empty};
+use orderbook::admin_server::AdminServer;
+use orderbook::{Summary, Level ,Empty, BookSummaryRequest, BookRequest, BookDelta, AggregationMode, FeedStatusEvent, CrossedMarketEvent, ExchangeUpdate,
+    QuoteCostRequest, QuoteCostResponse, Side,
+    ExchangeList, InstrumentList};
+use orderbook::quote_cost_request::Size;
 
 const CONFIG_PATH: &str = "../config.json"; 
 const LOG_CONFIG_PATH: &str = "log_config.yaml";
 
 use std::sync::Once;
 static INIT: Once = Once::new();
-pub fn setup_log() -> () { 
+pub fn setup_log() { 
     INIT.call_once(|| {
         log4rs::init_file(LOG_CONFIG_PATH, Default::default()).unwrap();
     });
@@ -49,102 +94,443 @@ lazy_static! {
     };   
 }
 
-fn init( mut service: (impl ExchangeInit + 'static + Send), output_stream_tx_ch: broadcast::Sender<SnapshotData>)  {
-    tokio::spawn(async move {service.stream_init_task(output_stream_tx_ch).await});
+/// Resolves on SIGINT or SIGTERM and cancels `cancel_token`
+async fn shutdown_signal(cancel_token: CancellationToken) {
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {signal.recv().await;},
+            Err(err) => {
+                log::error!("\n{:?}", err);
+                std::future::pending::<()>().await
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {},
+        _ = terminate => {},
+        _ = cancel_token.cancelled() => {}
+    }
+    log::warn!("Shutting down");
+    cancel_token.cancel();
+}
+
+fn level_response(level: &aggregated_order_book::Level) -> Result<Level> {
+    Ok(Level{
+        exchange: level.exchange.to_string(),
+        price: level.price.to_string().parse::<f64>()?,
+        amount: level.volume.to_string().parse::<f64>()?,
+        raw_price: level.raw_price.to_string().parse::<f64>()?,
+        price_decimal: level.price.to_string(),
+        amount_decimal: level.volume.to_string(),
+        raw_price_decimal: level.raw_price.to_string(),
+    })
+}
+
+fn consolidated_level_response(level: &ConsolidatedLevel) -> Result<orderbook::ConsolidatedLevel> {
+    Ok(orderbook::ConsolidatedLevel{
+        price: level.price.to_string().parse::<f64>()?,
+        amount: level.volume.to_string().parse::<f64>()?,
+        exchanges: level.exchanges.iter().map(level_response).collect::<Result<Vec<Level>>>()?,
+        price_decimal: level.price.to_string(),
+        amount_decimal: level.volume.to_string(),
+    })
+}
+
+fn decimal_response(value: Option<Decimal>) -> Result<Option<f64>> {
+    Ok(value.map(|value| value.to_string().parse::<f64>()).transpose()?)
+}
+
+fn book_stats_response(stats: &BookStats) -> Result<orderbook::BookStats> {
+    Ok(orderbook::BookStats{
+        exchange: stats.exchange.as_ref().map(|exchange| exchange.to_string()).unwrap_or_default(),
+        mid: decimal_response(stats.mid)?,
+        microprice: decimal_response(stats.microprice)?,
+        imbalance: decimal_response(stats.imbalance)?,
+        depth: stats.depth.iter().map(|depth| Ok(orderbook::DepthWithin{
+            bps: depth.bps.to_string().parse::<f64>()?,
+            bid_amount: depth.bid_volume.to_string().parse::<f64>()?,
+            ask_amount: depth.ask_volume.to_string().parse::<f64>()?,
+        })).collect::<Result<Vec<_>>>()?,
+        weighted_spread_bps: decimal_response(stats.weighted_spread_bps)?,
+    })
 }
 
-fn set_response_stream(agrregate_book_result: &mut AggregatedBook) -> Result<Summary> {
+fn set_response_stream(symbol: &str, agrregate_book_result: &AggregatedBook, depth: usize, analytics: &BookAnalytics) -> Result<SummaryViews> {
     
-    let asks = agrregate_book_result.get_top_asks(20);
-    let bids = agrregate_book_result.get_top_bids(20);
-    let spot_ask = asks.get(0).ok_or(anyhow::Error::new(Error::from(ErrorKind::NotFound)))?; 
-    let spot_bid = bids.get(0).ok_or(anyhow::Error::new(Error::from(ErrorKind::NotFound)))?; 
+    let asks = agrregate_book_result.get_top_asks(depth);
+    let bids = agrregate_book_result.get_top_bids(depth);
+    let spot_ask = asks.first().ok_or(anyhow::Error::new(Error::from(ErrorKind::NotFound)))?; 
+    let spot_bid = bids.first().ok_or(anyhow::Error::new(Error::from(ErrorKind::NotFound)))?; 
     let spread = spot_ask.price - spot_bid.price;
     log::trace!("\n{:?} = {:?} - {:?}", spread, spot_ask.price, spot_bid.price);
-    let mut level_asks = Vec::new();
-    let mut level_bids = Vec::new();
-    for ask in asks.iter().rev() {
-        level_asks.push(Level{
-            exchange: ask.exchange.to_string(),
-            price: ask.price.to_string().parse::<f64>()?,
-            amount: ask.volume.to_string().parse::<f64>()?,
-        })
-    }
-    for bid in bids.iter().rev() {
-        level_bids.push(Level{
-            exchange: bid.exchange.to_string(),
-            price: bid.price.to_string().parse::<f64>()?,
-            amount: bid.volume.to_string().parse::<f64>()?,
-        })
-    }
-    let summary = Summary {
+    let level_asks = asks.iter().rev().map(level_response).collect::<Result<Vec<Level>>>()?;
+    let level_bids = bids.iter().rev().map(level_response).collect::<Result<Vec<Level>>>()?;
+    let by_venue = Summary {
         spread: spread.to_string().parse::<f64>()?,
+        spread_decimal: spread.to_string(),
+        sequence: 0,
+        publish_time: 0,
+        exchange_updates: Vec::new(),
         asks: level_asks,
         bids: level_bids,
+        symbol: symbol.to_string(),
+        consolidated_asks: Vec::new(),
+        consolidated_bids: Vec::new(),
+        synthetic: false,
+        legs: Vec::new(),
+        stats: None,
+        venue_stats: Vec::new(),
     };
-    Ok(summary)
+    let by_venue = if analytics.is_enabled() {
+        Summary {
+            stats: Some(book_stats_response(&analytics.stats(agrregate_book_result))?),
+            venue_stats: analytics.venue_stats(agrregate_book_result).iter()
+                .map(book_stats_response).collect::<Result<Vec<_>>>()?,
+            ..by_venue
+        }
+    } else {
+        by_venue
+    };
+
+    let consolidated_asks = agrregate_book_result.get_top_consolidated_asks(depth).iter().rev()
+        .map(consolidated_level_response).collect::<Result<Vec<_>>>()?;
+    let consolidated_bids = agrregate_book_result.get_top_consolidated_bids(depth).iter().rev()
+        .map(consolidated_level_response).collect::<Result<Vec<_>>>()?;
+    let consolidated = Summary {
+        asks: Vec::new(),
+        bids: Vec::new(),
+        consolidated_asks: consolidated_asks,
+        consolidated_bids: consolidated_bids,
+        ..by_venue.clone()
+    };
+
+    // the same levels ranked on their cost once the taker fee is paid
+    let fee_adjusted_asks = agrregate_book_result.get_top_fee_adjusted_asks(depth);
+    let fee_adjusted_bids = agrregate_book_result.get_top_fee_adjusted_bids(depth);
+    let spot_ask = fee_adjusted_asks.first().ok_or(anyhow::Error::new(Error::from(ErrorKind::NotFound)))?; 
+    let spot_bid = fee_adjusted_bids.first().ok_or(anyhow::Error::new(Error::from(ErrorKind::NotFound)))?; 
+    let spread = spot_ask.price - spot_bid.price;
+    let fee_adjusted = Summary {
+        spread: spread.to_string().parse::<f64>()?,
+        spread_decimal: spread.to_string(),
+        asks: fee_adjusted_asks.iter().rev().map(level_response).collect::<Result<Vec<Level>>>()?,
+        bids: fee_adjusted_bids.iter().rev().map(level_response).collect::<Result<Vec<Level>>>()?,
+        ..by_venue.clone()
+    };
+
+    Ok(SummaryViews{
+        by_venue: by_venue,
+        consolidated: consolidated,
+        fee_adjusted: fee_adjusted,
+        filtered: HashMap::new(),
+    })
 }
 
-#[derive(Default)]
-pub struct OrderbookService {}
+fn synthetic_level_response(level: &SyntheticLevel) -> Result<Level> {
+    Ok(Level{
+        exchange: format!("{}/{}", level.base_exchange, level.quote_exchange),
+        price: level.price.to_string().parse::<f64>()?,
+        amount: level.volume.to_string().parse::<f64>()?,
+        raw_price: level.raw_price.to_string().parse::<f64>()?,
+        price_decimal: level.price.to_string(),
+        amount_decimal: level.volume.to_string(),
+        raw_price_decimal: level.raw_price.to_string(),
+    })
+}
+
+/// Merges the levels of equal price, the ladder sorted best first
+fn consolidate_levels(levels: &[SyntheticLevel]) -> Result<Vec<orderbook::ConsolidatedLevel>> {
+    let mut consolidated: Vec<(Price, Volume, Vec<Level>)> = Vec::new();
+    for level in levels {
+        match consolidated.last_mut() {
+            Some((price, volume, exchanges)) if *price == level.price => {
+                *volume += level.volume;
+                exchanges.push(synthetic_level_response(level)?);
+            },
+            _ => consolidated.push((level.price, level.volume, vec![synthetic_level_response(level)?]))
+        }
+    }
+    consolidated.into_iter()
+        .map(|(price, volume, exchanges)| Ok(orderbook::ConsolidatedLevel{
+            price: price.to_string().parse::<f64>()?,
+            amount: volume.to_string().parse::<f64>()?,
+            exchanges: exchanges,
+            price_decimal: price.to_string(),
+            amount_decimal: volume.to_string(),
+        }))
+        .collect()
+}
+
+/// Summaries of a synthetic instrument from the books of its two legs
+fn synthetic_response_stream(synthetic: &SyntheticBook, base: &AggregatedBook, quote: &AggregatedBook, depth: usize) -> Result<SummaryViews> {
+
+    let asks = synthetic.get_top_asks(base, quote, depth);
+    let bids = synthetic.get_top_bids(base, quote, depth);
+    let spot_ask = asks.first().ok_or(anyhow::Error::new(Error::from(ErrorKind::NotFound)))?; 
+    let spot_bid = bids.first().ok_or(anyhow::Error::new(Error::from(ErrorKind::NotFound)))?; 
+    let spread = spot_ask.price - spot_bid.price;
+    let by_venue = Summary {
+        spread: spread.to_string().parse::<f64>()?,
+        spread_decimal: spread.to_string(),
+        sequence: 0,
+        publish_time: 0,
+        exchange_updates: Vec::new(),
+        asks: asks.iter().rev().map(synthetic_level_response).collect::<Result<Vec<Level>>>()?,
+        bids: bids.iter().rev().map(synthetic_level_response).collect::<Result<Vec<Level>>>()?,
+        symbol: synthetic.symbol.clone(),
+        consolidated_asks: Vec::new(),
+        consolidated_bids: Vec::new(),
+        synthetic: true,
+        legs: vec![synthetic.base_leg.clone(), synthetic.quote_leg.clone()],
+        stats: None,
+        venue_stats: Vec::new(),
+    };
+
+    let consolidated = Summary {
+        asks: Vec::new(),
+        bids: Vec::new(),
+        consolidated_asks: consolidate_levels(&asks)?.into_iter().rev().collect(),
+        consolidated_bids: consolidate_levels(&bids)?.into_iter().rev().collect(),
+        ..by_venue.clone()
+    };
+
+    let fee_adjusted_asks = synthetic.get_top_fee_adjusted_asks(base, quote, depth);
+    let fee_adjusted_bids = synthetic.get_top_fee_adjusted_bids(base, quote, depth);
+    let spot_ask = fee_adjusted_asks.first().ok_or(anyhow::Error::new(Error::from(ErrorKind::NotFound)))?; 
+    let spot_bid = fee_adjusted_bids.first().ok_or(anyhow::Error::new(Error::from(ErrorKind::NotFound)))?; 
+    let spread = spot_ask.price - spot_bid.price;
+    let fee_adjusted = Summary {
+        spread: spread.to_string().parse::<f64>()?,
+        spread_decimal: spread.to_string(),
+        asks: fee_adjusted_asks.iter().rev().map(synthetic_level_response).collect::<Result<Vec<Level>>>()?,
+        bids: fee_adjusted_bids.iter().rev().map(synthetic_level_response).collect::<Result<Vec<Level>>>()?,
+        ..by_venue.clone()
+    };
+
+    Ok(SummaryViews{
+        by_venue: by_venue,
+        consolidated: consolidated,
+        fee_adjusted: fee_adjusted,
+        filtered: HashMap::new(),
+    })
+}
+
+fn quote_cost_response(symbol: &str, side: Side, quote_cost: &QuoteCost) -> Result<QuoteCostResponse> {
+    Ok(QuoteCostResponse{
+        symbol: symbol.to_string(),
+        side: side as i32,
+        amount: quote_cost.volume.to_string().parse::<f64>()?,
+        notional: quote_cost.notional.to_string().parse::<f64>()?,
+        vwap: quote_cost.vwap.to_string().parse::<f64>()?,
+        worst_price: quote_cost.worst_price.to_string().parse::<f64>()?,
+        mid: quote_cost.mid.to_string().parse::<f64>()?,
+        slippage_bps: quote_cost.slippage_bps.to_string().parse::<f64>()?,
+        fills: quote_cost.fills.iter().map(|fill| Ok(orderbook::Fill{
+            exchange: fill.exchange.to_string(),
+            amount: fill.volume.to_string().parse::<f64>()?,
+            notional: fill.notional.to_string().parse::<f64>()?,
+        })).collect::<Result<Vec<orderbook::Fill>>>()?,
+        complete: quote_cost.complete,
+    })
+}
+
+#[derive(Clone)]
+pub struct OrderbookService {
+    feed: BookFeed,
+    status_feed: StatusFeed,
+    auth: Authenticator,
+    cancel_token: CancellationToken
+}
+
+impl OrderbookService {
+    pub fn new(feed: BookFeed, status_feed: StatusFeed, auth: Authenticator, cancel_token: CancellationToken) -> Self {
+        OrderbookService{
+            feed: feed,
+            status_feed: status_feed,
+            auth: auth,
+            cancel_token: cancel_token
+        }
+    }
+
+    /// Validates the depth and the exchanges of a book request against the config and the client grant,
+    /// the default depth when 0 and the granted exchanges when none is asked for
+    fn depth_and_exchanges(&self, grant: &Grant, depth: u32, exchanges: &[String]) -> Result<(usize, Vec<Exchange>), Status> {
+        let depth_config = self.feed.depth_config();
+        let granted_depth = grant.max_depth().unwrap_or(depth_config.max_depth);
+        let depth = match depth as usize {
+            0 => depth_config.default_depth.min(depth_config.max_depth).min(granted_depth),
+            depth if depth > depth_config.max_depth => return Err(Status::invalid_argument(format!("depth {} above the maximum of {}", depth, depth_config.max_depth))),
+            depth if depth > granted_depth => return Err(Status::permission_denied(format!("depth {} above the {} granted to {}", depth, granted_depth, grant.client))),
+            depth => depth
+        };
+        let exchanges = exchanges.iter()
+            .map(|exchange| Exchange::from_str(exchange)
+                .map_err(|_| Status::invalid_argument(format!("unknown exchange {}", exchange))))
+            .collect::<Result<Vec<Exchange>, Status>>()?;
+        Ok((depth, grant.exchanges(exchanges)?))
+    }
+}
 
 #[tonic::async_trait]
 impl OrderbookAggregator for OrderbookService {
 // Specify the output of rpc call
     type BookSummaryStream = ReceiverStream<Result<Summary, Status>>;
 // implementation for rpc call
-    async fn book_summary(&self, _: Request<Empty>) -> Result<Response<Self::BookSummaryStream>, Status> {
-        // let task_name = "--book_summary Task--";
-
-        let binance_service = BinanceService::new(CONFIG.binance.clone());
-        let bitstamp_service = BitstampService::new(CONFIG.bitstamp.clone());
+    async fn book_summary(&self, request: Request<BookSummaryRequest>) -> Result<Response<Self::BookSummaryStream>, Status> {
+        let grant = self.auth.grant(request.metadata())?;
+        let request = request.into_inner();
+        let symbol = request.symbol.clone();
+        if symbol.is_empty() {
+            return Err(Status::invalid_argument("symbol is required"));
+        }
+        grant.check_symbol(&symbol)?;
+        let mode = AggregationMode::from_i32(request.mode)
+            .ok_or_else(|| Status::invalid_argument(format!("unknown aggregation mode {}", request.mode)))?;
+        let (depth, exchanges) = self.depth_and_exchanges(&grant, request.depth, &request.exchanges)?;
+        let filter = SummaryFilter::new(depth, exchanges);
+        let ((last_summary, summary_rx_ch), filter_subscription) = self.feed.subscribe_filtered(&symbol, &filter)
+            .ok_or_else(|| Status::not_found(format!("symbol {} is not streamed", symbol)))?;
+        let permit = self.auth.open_stream(&grant)?;
 
         let (tx, rx) = mpsc::channel(4);
-        let (binance_output_tx_ch, mut binance_output_rx_ch) =  broadcast::channel(10);
-        let (bitstamp_output_tx_ch, mut bitstamp_output_rx_ch) =  broadcast::channel(10);
-
-        init(binance_service, binance_output_tx_ch);
-        init(bitstamp_service, bitstamp_output_tx_ch);
-
-
-        tokio::spawn(async move {
-            let mut agrregate_book_result = AggregatedBook::new();
-            loop{   
-                
-                tokio::select! {
-
-                    val = bitstamp_output_rx_ch.recv() => {
-                        match val {
-                            Ok(snap_shot)=> {
-                                agrregate_book_result.update_book(snap_shot);
-                                match set_response_stream(&mut agrregate_book_result){
-                                    Ok(response) => {let _ = tx.send(Ok(response)).await;},
-                                    Err(err) => log::error!("\nError in Bitstamp  :\n {:?}", err)
-                                };
-                            },
-                            Err(err)=> {log::error!("\nError in Bitstamp  :\n {:?}", err); return}        
-                        };
-                    }
-                    val = binance_output_rx_ch.recv() => {                 
-                        match val {
-                            Ok(snap_shot)=> {
-                                agrregate_book_result.update_book(snap_shot);
-                                match set_response_stream(&mut agrregate_book_result){
-                                    Ok(response) =>{let _ = tx.send(Ok(response)).await;},
-                                    Err(err) => log::error!("\nError in Binance  :\n {:?}", err)
-                                };
-                            },
-                            Err(err)=> {log::error!("\nError in Binance  :\n {:?}", err); return}        
-                        };
-       
-                    }
-
-                }
-            }
-        });
+        // numbered per stream, the summaries skipped by the filter or conflated leave no gap
+        let mut sequence = 0;
+        tokio::spawn(conflating_subscriber_task(last_summary, summary_rx_ch, tx, self.cancel_token.child_token(),
+            update_interval(request.max_update_rate), move |summary| {
+                let _permit = (&permit, &filter_subscription);
+                sequence += 1;
+                Summary { sequence: sequence, ..filter.view(&summary, mode) }
+            }));
 // returning our reciever so that tonic can listen on reciever and send the response to client
         Ok(Response::new(ReceiverStream::new(rx)))
     }
+
+    type BookDeltasStream = ReceiverStream<Result<BookDelta, Status>>;
+
+    async fn book_deltas(&self, request: Request<BookSummaryRequest>) -> Result<Response<Self::BookDeltasStream>, Status> {
+        let grant = self.auth.grant(request.metadata())?;
+        let request = request.into_inner();
+        if request.symbol.is_empty() {
+            return Err(Status::invalid_argument("symbol is required"));
+        }
+        grant.check_symbol(&request.symbol)?;
+        let mode = match AggregationMode::from_i32(request.mode) {
+            Some(AggregationMode::Consolidated) => return Err(Status::invalid_argument("deltas are kept per exchange level, not in CONSOLIDATED mode")),
+            Some(mode) => mode,
+            None => return Err(Status::invalid_argument(format!("unknown aggregation mode {}", request.mode)))
+        };
+        let (depth, exchanges) = self.depth_and_exchanges(&grant, request.depth, &request.exchanges)?;
+        let filter = SummaryFilter::new(depth, exchanges);
+        let ((last_summary, summary_rx_ch), filter_subscription) = self.feed.subscribe_filtered(&request.symbol, &filter)
+            .ok_or_else(|| Status::not_found(format!("symbol {} is not streamed", request.symbol)))?;
+        let permit = self.auth.open_stream(&grant)?;
+
+        let (tx, rx) = mpsc::channel(4);
+        let mut encoder = DeltaEncoder::new(DELTA_REFRESH_INTERVAL);
+        // the changes are taken from the last book sent, the conflated ones are folded in
+        tokio::spawn(conflating_subscriber_task(last_summary, summary_rx_ch, tx, self.cancel_token.child_token(),
+            update_interval(request.max_update_rate), move |summary| {
+                let _permit = (&permit, &filter_subscription);
+                encoder.encode(filter.view(&summary, mode))
+            }));
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    type FeedStatusStream = ReceiverStream<Result<FeedStatusEvent, Status>>;
+
+    async fn feed_status(&self, request: Request<Empty>) -> Result<Response<Self::FeedStatusStream>, Status> {
+        let grant = self.auth.grant(request.metadata())?;
+        let permit = self.auth.open_stream(&grant)?;
+        let (tx, rx) = mpsc::channel(16);
+        let (last_status, status_rx_ch) = self.status_feed.subscribe();
+        tokio::spawn(subscriber_task(last_status, status_rx_ch, tx, self.cancel_token.child_token(), move |status| {
+            let _permit = &permit;
+            if grant.allows(&status.symbol, &[&status.exchange]) { Some(status) } else { None }
+        }));
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn quote_cost(&self, request: Request<QuoteCostRequest>) -> Result<Response<QuoteCostResponse>, Status> {
+        let grant = self.auth.grant(request.metadata())?;
+        let request = request.into_inner();
+        grant.check_symbol(&request.symbol)?;
+        let exchanges = grant.exchanges(Vec::new())?;
+        let side = Side::from_i32(request.side)
+            .ok_or_else(|| Status::invalid_argument(format!("unknown side {}", request.side)))?;
+        let size = match request.size {
+            Some(Size::BaseAmount(amount)) if amount > 0.0 => Decimal::from_f64(amount).map(OrderSize::Base),
+            Some(Size::QuoteAmount(amount)) if amount > 0.0 => Decimal::from_f64(amount).map(OrderSize::Quote),
+            _ => None
+        }.ok_or_else(|| Status::invalid_argument("a positive base_amount or quote_amount is required"))?;
+        let internal_side = match side {
+            Side::Buy => quote_cost::Side::Buy,
+            Side::Sell => quote_cost::Side::Sell
+        };
+
+        // only the granted exchanges fill the order
+        let quote_cost = self.feed.with_book(&request.symbol, |book| if exchanges.is_empty() {
+                book.quote_cost(internal_side, size)
+            } else {
+                book.only(&exchanges).quote_cost(internal_side, size)
+            })
+            .ok_or_else(|| Status::not_found(format!("no book for symbol {}", request.symbol)))?
+            .ok_or_else(|| Status::failed_precondition(format!("book of symbol {} is one sided", request.symbol)))?;
+        let response = quote_cost_response(&canonical_symbol(&request.symbol), side, &quote_cost)
+            .map_err(|err| Status::internal(err.to_string()))?;
+        Ok(Response::new(response))
+    }
+
+    async fn get_book(&self, request: Request<BookRequest>) -> Result<Response<Summary>, Status> {
+        let grant = self.auth.grant(request.metadata())?;
+        let request = request.into_inner();
+        if request.symbol.is_empty() {
+            return Err(Status::invalid_argument("symbol is required"));
+        }
+        grant.check_symbol(&request.symbol)?;
+        let mode = AggregationMode::from_i32(request.mode)
+            .ok_or_else(|| Status::invalid_argument(format!("unknown aggregation mode {}", request.mode)))?;
+        let (depth, exchanges) = self.depth_and_exchanges(&grant, request.depth, &request.exchanges)?;
+
+        let summary = match self.feed.snapshot(&request.symbol, depth, &exchanges) {
+            Some(summary) => summary
+                .map_err(|_| Status::failed_precondition(format!("book of symbol {} is one sided", request.symbol)))?
+                .view(mode),
+            // synthetic instruments have no book of their own, their last summary is used
+            None => self.feed.last_summary(&request.symbol)
+                .map(|summary| SummaryFilter::new(depth, exchanges).apply(summary.view(mode)))
+                .ok_or_else(|| Status::not_found(format!("no book for symbol {}", request.symbol)))?
+        };
+        Ok(Response::new(summary))
+    }
+
+    async fn list_exchanges(&self, request: Request<Empty>) -> Result<Response<ExchangeList>, Status> {
+        let grant = self.auth.grant(request.metadata())?;
+        let instruments = discovery::instruments(&self.feed, &self.status_feed, &grant);
+        Ok(Response::new(ExchangeList{exchanges: discovery::exchanges(instruments)}))
+    }
+
+    async fn list_instruments(&self, request: Request<Empty>) -> Result<Response<InstrumentList>, Status> {
+        let grant = self.auth.grant(request.metadata())?;
+        Ok(Response::new(InstrumentList{instruments: discovery::instruments(&self.feed, &self.status_feed, &grant)}))
+    }
+
+    type CrossedMarketsStream = ReceiverStream<Result<CrossedMarketEvent, Status>>;
+
+    async fn crossed_markets(&self, request: Request<Empty>) -> Result<Response<Self::CrossedMarketsStream>, Status> {
+        let grant = self.auth.grant(request.metadata())?;
+        let permit = self.auth.open_stream(&grant)?;
+        let (tx, rx) = mpsc::channel(16);
+        let (active_crossings, crossed_rx_ch) = self.feed.subscribe_crossed();
+        tokio::spawn(subscriber_task(active_crossings, crossed_rx_ch, tx, self.cancel_token.child_token(), move |event| {
+            let _permit = &permit;
+            if grant.allows(&event.symbol, &[&event.bid_exchange, &event.ask_exchange]) { Some(event) } else { None }
+        }));
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
 }
 
 
@@ -154,14 +540,109 @@ async fn main() -> Result<()>
     // setup_log();
     let addr = CONFIG.grpc_server.parse()?;
 
+    let cancel_token = CancellationToken::new();
+    let mut tasks = JoinSet::new();
+
+    // starting the exchange feeds once, shared by every subscriber
+    let (snapshot_tx_ch, snapshot_rx_ch) =  broadcast::channel(10);
+    let (status_tx_ch, status_rx_ch) =  broadcast::channel(32);
+    let synthetics: Vec<SyntheticBook> = CONFIG.synthetics.iter().map(SyntheticBook::new).collect();
+    for synthetic in synthetics.iter() {
+        for leg in [&synthetic.base_leg, &synthetic.quote_leg].iter() {
+            if !CONFIG.symbols().contains(leg) {
+                log::warn!("Synthetic {} leg {} is not streamed by any exchange", synthetic.symbol, leg);
+            }
+        }
+    }
+    let settings = BookFeedSettings{
+        symbols: CONFIG.symbols(),
+        fees: CONFIG.fees.clone(),
+        synthetics: synthetics,
+        crossed_market: CONFIG.crossed_market.clone(),
+        analytics: CONFIG.analytics.clone(),
+        depth: CONFIG.depth.clone(),
+    };
+    let feed = BookFeed::start(settings, snapshot_rx_ch, cancel_token.clone(), &mut tasks);
+    let status_feed = StatusFeed::start(status_rx_ch, cancel_token.clone(), &mut tasks);
+    let subscriptions = Subscriptions::new(exchange_pipelines(CONFIG.binance.clone(), CONFIG.bitstamp.clone()), feed.clone(), snapshot_tx_ch, status_tx_ch, cancel_token.clone());
+    for symbol in CONFIG.binance.symbols.iter() {
+        subscriptions.add(&Exchange::Binance, symbol).await;
+    }
+    for symbol in CONFIG.bitstamp.symbols.iter() {
+        subscriptions.add(&Exchange::Bitstamp, symbol).await;
+    }
+    let reflection_service = ReflectionService::new(FILE_DESCRIPTOR_SET, cancel_token.clone())?;
+
     // creating a service
-    let orderbook_service = OrderbookService::default();
+    let auth = Authenticator::new(&CONFIG.auth)?;
+    let health_service = HealthService::start(&status_feed, auth.admin_enabled(), cancel_token.clone(), &mut tasks);
+    if let Some(metrics_addr) = &CONFIG.metrics_server {
+        let listener = std::net::TcpListener::bind(metrics_addr)?;
+        println!("Metrics served on http://{}{}", metrics_addr, metrics_server::METRICS_PATH);
+        let (feed, cancel_token) = (feed.clone(), cancel_token.clone());
+        tasks.spawn(async move {
+            if let Err(err) = metrics_server::serve(listener, feed, cancel_token).await {
+                log::error!("Error in metrics server:\n{}", err);
+            }
+        });
+    }
+    let orderbook_service = OrderbookService::new(feed, status_feed, auth.clone(), cancel_token.clone());
+    let tls = match &CONFIG.tls.server {
+        Some(files) => Some(Arc::new(ReloadingTls::server(files)?)),
+        None => None
+    };
+    if let Some(grpc_web) = &CONFIG.grpc_web {
+        let service = OrderbookAggregatorServer::with_interceptor(orderbook_service.clone(), auth.interceptor());
+        let (allowed_origins, cancel_token) = (grpc_web.allowed_origins.clone(), cancel_token.clone());
+        // the browsers send their tokens, over TLS as the gRPC clients whenever it is configured
+        let served = match &tls {
+            Some(tls) => {
+                let listener = TcpListener::bind(&grpc_web.address).await?;
+                println!("gRPC-Web served on https://{}", grpc_web.address);
+                grpc_web::serve_tls(listener, tls.clone(), service, allowed_origins, cancel_token).boxed()
+            },
+            None => {
+                let listener = std::net::TcpListener::bind(&grpc_web.address)?;
+                println!("gRPC-Web served on http://{}", grpc_web.address);
+                grpc_web::serve(listener, service, allowed_origins, cancel_token).boxed()
+            }
+        };
+        tasks.spawn(async move {
+            if let Err(err) = served.await {
+                log::error!("Error in gRPC-Web server:\n{}", err);
+            }
+        });
+    }
     println!("Server listening on {}", addr);
+    // anyone reaching the port could stop the feeds without admin clients
+    let admin_service = auth.admin_enabled()
+        .then(|| AdminServer::with_interceptor(AdminService::new(subscriptions.clone()), auth.admin_interceptor()));
+    if admin_service.is_none() {
+        log::info!("Admin service not served, no client is granted admin");
+    }
     // adding our service to our server.
-    Server::builder()
-        .add_service(OrderbookAggregatorServer::new(orderbook_service))
-        .serve(addr)
-        .await?;
+    let router = Server::builder()
+        .add_service(OrderbookAggregatorServer::with_interceptor(orderbook_service, auth.interceptor()))
+        .add_optional_service(admin_service)
+        .add_service(HealthServer::new(health_service))
+        .add_service(ServerReflectionServer::new(reflection_service));
+    let result = match tls {
+        None => router.serve_with_shutdown(addr, shutdown_signal(cancel_token.clone())).await,
+        Some(tls) => {
+            let listener = TcpListener::bind(addr).await?;
+            router.serve_with_incoming_shutdown(tls_incoming(listener, tls, cancel_token.clone()), shutdown_signal(cancel_token.clone())).await
+        }
+    };
+
+    // the server may also have stopped on error, either way the feeds are stopped
+    cancel_token.cancel();
+    subscriptions.join().await;
+    let join_all = async { while tasks.join_next().await.is_some() {} };
+    if tokio::time::timeout(SHUTDOWN_TIMEOUT, join_all).await.is_err() {
+        log::warn!("Exchange tasks did not stop in {:?}, aborting them", SHUTDOWN_TIMEOUT);
+        tasks.shutdown().await;
+    }
+    result?;
     Ok(())
 }
 
@@ -0,0 +1,60 @@
+use std::{
+    convert::Infallible,
+    net::TcpListener
+};
+use hyper::{
+    header::{HeaderValue, CONTENT_TYPE},
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, Server, StatusCode
+};
+use tokio_util::sync::CancellationToken;
+use common::*;
+use crate::book_feed::BookFeed;
+
+/// Path Prometheus scrapes
+pub const METRICS_PATH: &str = "/metrics";
+
+/// Serves the metrics on `listener` until `cancel_token` is cancelled
+pub async fn serve(listener: TcpListener, feed: BookFeed, cancel_token: CancellationToken) -> hyper::Result<()> {
+    let make_service = make_service_fn(move |_| {
+        let feed = feed.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let response = respond(&feed, &request);
+                async move { Ok::<_, Infallible>(response) }
+            }))
+        }
+    });
+    Server::from_tcp(listener)?
+        .serve(make_service)
+        .with_graceful_shutdown(async move { cancel_token.cancelled().await })
+        .await
+}
+
+fn respond(feed: &BookFeed, request: &Request<Body>) -> Response<Body> {
+    if request.method() != Method::GET || request.uri().path() != METRICS_PATH {
+        let mut response = Response::new(Body::empty());
+        *response.status_mut() = StatusCode::NOT_FOUND;
+        return response;
+    }
+    set_feed_age(feed, now_millis());
+    let mut response = Response::new(Body::from(metrics::encode()));
+    response.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static(metrics::CONTENT_TYPE));
+    response
+}
+
+/// Sets the age of every exchange book streamed from the receive time of its last update,
+/// the books not updated yet and the removed ones have none
+pub fn set_feed_age(feed: &BookFeed, now: Timestamp) {
+    metrics::FEED_AGE_SECONDS.reset();
+    for (symbol, exchange, _) in feed.instruments() {
+        let received_time = feed.with_book(&symbol, |book| book.last_updates().into_iter()
+                .find(|(book_exchange, _, _)| book_exchange == &exchange)
+                .map(|(_, _, received_time)| received_time))
+            .flatten();
+        if let Some(received_time) = received_time {
+            metrics::FEED_AGE_SECONDS.with_label_values(&[&exchange.to_string(), &symbol])
+                .set(now.saturating_sub(received_time) as f64 / 1000.0);
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::BTreeMap;
+use rust_decimal::Decimal;
+use crate::*;
+use crate::aggregated_order_book::{AggregatedBook, BPS};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    /// Walks the asks
+    Buy,
+    /// Walks the bids
+    Sell
+}
+
+/// Size of an order, in the base currency or as a notional in the quote currency
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OrderSize {
+    Base(Volume),
+    Quote(Decimal)
+}
+
+/// What one exchange fills of the order
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fill {
+    pub exchange: Exchange,
+    pub volume: Volume,
+    pub notional: Decimal,
+}
+
+/// Cost of filling an order against the aggregated book as it is now
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QuoteCost {
+    pub side: Side,
+    /// Base volume filled, less than asked when the book is too thin
+    pub volume: Volume,
+    /// Quote notional paid or received
+    pub notional: Decimal,
+    pub vwap: Price,
+    /// Price of the last level reached
+    pub worst_price: Price,
+    pub mid: Price,
+    /// How much worse than the mid the vwap is, in basis points of the mid
+    pub slippage_bps: Decimal,
+    /// Per-exchange breakdown, ordered by exchange
+    pub fills: Vec<Fill>,
+    /// Whether the whole size was filled
+    pub complete: bool,
+}
+
+impl AggregatedBook {
+    /// Walks the consolidated ladder of `side` until `size` is filled.
+    ///
+    /// `None` when either side of the book is empty, no mid to measure against.
+    pub fn quote_cost(&self, side: Side, size: OrderSize) -> Option<QuoteCost> {
+        let best_bid = self.bids().next()?;
+        let best_ask = self.asks().next()?;
+        let mid = (best_bid.price + best_ask.price) / Decimal::from(2);
+        if mid <= Decimal::ZERO {
+            return None;
+        }
+        let levels: Box<dyn Iterator<Item = _>> = match side {
+            Side::Buy => Box::new(self.asks()),
+            Side::Sell => Box::new(self.bids())
+        };
+
+        let mut volume = Decimal::ZERO;
+        let mut notional = Decimal::ZERO;
+        let mut worst_price = Decimal::ZERO;
+        let mut fills: BTreeMap<Exchange, Fill> = BTreeMap::new();
+        let mut complete = false;
+        for level in levels {
+            if level.price <= Decimal::ZERO {
+                continue;
+            }
+            let left = match size {
+                OrderSize::Base(size) => size - volume,
+                OrderSize::Quote(size) => (size - notional) / level.price
+            };
+            if left <= Decimal::ZERO {
+                complete = true;
+                break;
+            }
+            // the last level reached fills what is left
+            complete = level.volume >= left;
+            let level_volume = level.volume.min(left);
+            let level_notional = level_volume * level.price;
+            volume += level_volume;
+            notional += level_notional;
+            worst_price = level.price;
+            let fill = fills.entry(level.exchange.clone()).or_insert_with(|| Fill{
+                exchange: level.exchange.clone(),
+                volume: Decimal::ZERO,
+                notional: Decimal::ZERO,
+            });
+            fill.volume += level_volume;
+            fill.notional += level_notional;
+            if complete {
+                break;
+            }
+        }
+
+        let vwap = if volume.is_zero() { Decimal::ZERO } else { notional / volume };
+        let slippage_bps = match side {
+            Side::Buy => (vwap - mid) / mid * Decimal::from(BPS),
+            Side::Sell => (mid - vwap) / mid * Decimal::from(BPS)
+        };
+        Some(QuoteCost{
+            side: side,
+            volume: volume,
+            notional: notional,
+            vwap: vwap,
+            worst_price: worst_price,
+            mid: mid,
+            slippage_bps: if volume.is_zero() { Decimal::ZERO } else { slippage_bps },
+            fills: fills.into_values().collect(),
+            complete: complete,
+        })
+    }
+}
@@ -0,0 +1,163 @@
+use std::{
+    collections::BTreeMap,
+    sync::Arc
+};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status, Streaming};
+use prost::Message;
+use prost_types::{DescriptorProto, FileDescriptorProto, FileDescriptorSet};
+
+// the oneof variants are named by the standard proto
+#[allow(clippy::enum_variant_names)]
+pub mod proto {
+    tonic::include_proto!("grpc.reflection.v1alpha");
+}
+
+use proto::{ServerReflectionRequest, ServerReflectionResponse, FileDescriptorResponse, ListServiceResponse, ServiceResponse, ErrorResponse};
+use proto::server_reflection_request::MessageRequest;
+use proto::server_reflection_response::MessageResponse;
+use proto::server_reflection_server::ServerReflection;
+
+/// Descriptors of every proto compiled by build.rs, dependencies included
+pub const FILE_DESCRIPTOR_SET: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/orderbook_descriptor.bin"));
+
+/// gRPC server reflection, lets tools like grpcurl list the services and fetch the protos
+#[derive(Clone, Debug)]
+pub struct ReflectionService {
+    files: Arc<BTreeMap<String, FileDescriptorProto>>,
+    /// Fully qualified symbol to the name of the file defining it
+    symbols: Arc<BTreeMap<String, String>>,
+    services: Arc<Vec<String>>,
+    cancel_token: CancellationToken
+}
+
+impl ReflectionService {
+    pub fn new(file_descriptor_set: &[u8], cancel_token: CancellationToken) -> Result<Self, prost::DecodeError> {
+        let file_descriptor_set = FileDescriptorSet::decode(file_descriptor_set)?;
+        let mut files = BTreeMap::new();
+        let mut symbols = BTreeMap::new();
+        let mut services = Vec::new();
+        for file in file_descriptor_set.file {
+            let file_name = file.name().to_string();
+            let prefix = match file.package() {
+                "" => String::new(),
+                package => format!("{}.", package)
+            };
+            for service in file.service.iter() {
+                let service_name = format!("{}{}", prefix, service.name());
+                for method in service.method.iter() {
+                    symbols.insert(format!("{}.{}", service_name, method.name()), file_name.clone());
+                }
+                symbols.insert(service_name.clone(), file_name.clone());
+                services.push(service_name);
+            }
+            for message in file.message_type.iter() {
+                message_symbols(&prefix, message, &file_name, &mut symbols);
+            }
+            for enumeration in file.enum_type.iter() {
+                symbols.insert(format!("{}{}", prefix, enumeration.name()), file_name.clone());
+            }
+            files.insert(file_name, file);
+        }
+        Ok(ReflectionService{
+            files: Arc::new(files),
+            symbols: Arc::new(symbols),
+            services: Arc::new(services),
+            cancel_token: cancel_token
+        })
+    }
+
+    /// Answers one reflection request
+    pub fn respond(&self, request: ServerReflectionRequest) -> ServerReflectionResponse {
+        let message_response = match &request.message_request {
+            Some(MessageRequest::ListServices(_)) => MessageResponse::ListServicesResponse(ListServiceResponse{
+                service: self.services.iter().map(|name| ServiceResponse{name: name.clone()}).collect()
+            }),
+            Some(MessageRequest::FileByFilename(file_name)) => self.file_response(file_name)
+                .unwrap_or_else(|| error_response(tonic::Code::NotFound, format!("unknown file {}", file_name))),
+            Some(MessageRequest::FileContainingSymbol(symbol)) => self.symbols.get(symbol.trim_start_matches('.'))
+                .and_then(|file_name| self.file_response(file_name))
+                .unwrap_or_else(|| error_response(tonic::Code::NotFound, format!("unknown symbol {}", symbol))),
+            Some(MessageRequest::FileContainingExtension(_)) | Some(MessageRequest::AllExtensionNumbersOfType(_)) =>
+                error_response(tonic::Code::Unimplemented, "extensions are not used".to_string()),
+            None => error_response(tonic::Code::InvalidArgument, "empty request".to_string()),
+        };
+        ServerReflectionResponse{
+            valid_host: request.host.clone(),
+            original_request: Some(request),
+            message_response: Some(message_response)
+        }
+    }
+
+    /// The file followed by all its dependencies, each serialized once
+    fn file_response(&self, file_name: &str) -> Option<MessageResponse> {
+        let mut names = vec![file_name.to_string()];
+        let mut index = 0;
+        while index < names.len() {
+            for dependency in self.files.get(&names[index])?.dependency.iter() {
+                if !names.contains(dependency) {
+                    names.push(dependency.clone());
+                }
+            }
+            index += 1;
+        }
+        let mut file_descriptor_proto = Vec::new();
+        for name in names {
+            let mut buf = Vec::new();
+            self.files.get(&name)?.encode(&mut buf).ok()?;
+            file_descriptor_proto.push(buf);
+        }
+        Some(MessageResponse::FileDescriptorResponse(FileDescriptorResponse{file_descriptor_proto: file_descriptor_proto}))
+    }
+}
+
+/// Registers a message with its nested messages and enums
+fn message_symbols(prefix: &str, message: &DescriptorProto, file_name: &str, symbols: &mut BTreeMap<String, String>) {
+    let name = format!("{}{}", prefix, message.name());
+    let nested_prefix = format!("{}.", name);
+    for nested in message.nested_type.iter() {
+        message_symbols(&nested_prefix, nested, file_name, symbols);
+    }
+    for enumeration in message.enum_type.iter() {
+        symbols.insert(format!("{}{}", nested_prefix, enumeration.name()), file_name.to_string());
+    }
+    symbols.insert(name, file_name.to_string());
+}
+
+fn error_response(code: tonic::Code, message: String) -> MessageResponse {
+    MessageResponse::ErrorResponse(ErrorResponse{error_code: code as i32, error_message: message})
+}
+
+#[tonic::async_trait]
+impl ServerReflection for ReflectionService {
+    type ServerReflectionInfoStream = ReceiverStream<Result<ServerReflectionResponse, Status>>;
+
+    async fn server_reflection_info(&self, request: Request<Streaming<ServerReflectionRequest>>) -> Result<Response<Self::ServerReflectionInfoStream>, Status> {
+        let mut request_stream = request.into_inner();
+        let service = self.clone();
+        let cancel_token = self.cancel_token.child_token();
+        let (tx, rx) = mpsc::channel(4);
+        tokio::spawn(async move {
+            loop {
+                let val = tokio::select! {
+                    _ = cancel_token.cancelled() => break,
+                    val = request_stream.message() => val
+                };
+                let request = match val {
+                    Ok(Some(request)) => request,
+                    Ok(None) => break,
+                    Err(status) => {
+                        let _ = tx.send(Err(status)).await;
+                        break;
+                    }
+                };
+                if tx.send(Ok(service.respond(request))).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+}
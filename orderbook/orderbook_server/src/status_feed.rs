@@ -0,0 +1,104 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock}
+};
+use tokio::{
+    sync::broadcast,
+    task::JoinSet
+};
+use tokio_util::sync::CancellationToken;
+use crate::*;
+use crate::orderbook::{FeedStatusEvent, feed_status_event::State};
+
+/// Capacity of the status broadcast
+const STATUS_CHANNEL_CAPACITY: usize = 64;
+
+impl From<FeedStatus> for FeedStatusEvent {
+    fn from(status: FeedStatus) -> Self {
+        let state = match status.state {
+            FeedState::Connecting => State::Connecting,
+            FeedState::Subscribed => State::Subscribed,
+            FeedState::Synced => State::Synced,
+            FeedState::Resyncing => State::Resyncing,
+            FeedState::Stale => State::Stale,
+            FeedState::Disconnected => State::Disconnected,
+        };
+        FeedStatusEvent {
+            exchange: status.exchange.to_string(),
+            symbol: status.symbol,
+            state: state as i32,
+            reason: status.reason.unwrap_or_default(),
+            timestamp: status.timestamp,
+        }
+    }
+}
+
+/// Health of every exchange feed, keeps the latest status per exchange and symbol
+/// and fans the changes out to the gRPC `FeedStatus` subscribers
+#[derive(Clone, Debug)]
+pub struct StatusFeed {
+    status_tx_ch: broadcast::Sender<FeedStatusEvent>,
+    last_status: Arc<RwLock<BTreeMap<(Exchange, Symbol), FeedStatusEvent>>>,
+}
+
+impl StatusFeed {
+    /// Spawns the task reading the statuses published by the exchange pipelines
+    pub fn start(status_rx_ch: broadcast::Receiver<FeedStatus>, cancel_token: CancellationToken, tasks: &mut JoinSet<()>) -> Self {
+        let (status_tx_ch, _) = broadcast::channel(STATUS_CHANNEL_CAPACITY);
+        let feed = StatusFeed {
+            status_tx_ch: status_tx_ch,
+            last_status: Arc::new(RwLock::new(BTreeMap::new())),
+        };
+        tasks.spawn(feed.clone().status_task(status_rx_ch, cancel_token));
+        feed
+    }
+
+    /// Returns the latest status of every feed and a receiver for the following changes
+    pub fn subscribe(&self) -> (Vec<FeedStatusEvent>, broadcast::Receiver<FeedStatusEvent>) {
+        let last_status = self.last_status.read().unwrap_or_else(|err| err.into_inner());
+        (last_status.values().cloned().collect(), self.status_tx_ch.subscribe())
+    }
+
+    /// Latest status of the feed of `symbol` on `exchange`, the symbol native or canonical
+    pub fn status(&self, exchange: &Exchange, symbol: &str) -> Option<FeedStatusEvent> {
+        let symbol = canonical_symbol(symbol);
+        let last_status = self.last_status.read().unwrap_or_else(|err| err.into_inner());
+        last_status.iter()
+            .find(|((status_exchange, status_symbol), _)| status_exchange == exchange && canonical_symbol(status_symbol) == symbol)
+            .map(|(_, event)| event.clone())
+    }
+
+    fn publish(&self, status: FeedStatus) {
+        let key = (status.exchange.clone(), status.symbol.clone());
+        let event = FeedStatusEvent::from(status);
+        let mut last_status = self.last_status.write().unwrap_or_else(|err| err.into_inner());
+        last_status.insert(key, event.clone());
+        let _ = self.status_tx_ch.send(event);
+    }
+
+    async fn status_task(self, mut status_rx_ch: broadcast::Receiver<FeedStatus>, cancel_token: CancellationToken) {
+        let task_name = "--Status Task--";
+        log::info!("{:?} Init", task_name);
+        loop {
+            let val = tokio::select! {
+                _ = cancel_token.cancelled() => break,
+                val = status_rx_ch.recv() => val
+            };
+            match val {
+                Ok(status) => {
+                    if status.state == FeedState::Disconnected || status.state == FeedState::Stale {
+                        log::warn!("Warning in {:?}:\n{} {} {}: {:?}", task_name, status.exchange, status.symbol, status.state, status.reason);
+                    }
+                    self.publish(status)
+                },
+                Err(broadcast::error::RecvError::Lagged(x)) => {
+                    log::warn!("Warning in {:?}:\nstatus_rx_ch lagged:\n{:?}\n", task_name, x);
+                    metrics::BROADCAST_LAGGED.with_label_values(&["status_rx_ch"]).inc_by(x);
+                    continue;
+                },
+                Err(broadcast::error::RecvError::Closed) => break
+            };
+        }
+        log::info!("{:?} End", task_name);
+    }
+}
@@ -0,0 +1,98 @@
+use rust_decimal::Decimal;
+use crate::*;
+use crate::aggregated_order_book::{AggregatedBook, Level};
+
+/// Level of a synthetic book, trading through one level of each leg
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntheticLevel {
+    pub base_exchange: Exchange,
+    pub quote_exchange: Exchange,
+    pub price:  Price,
+    /// Volume in the base currency, the most both leg levels can fill
+    pub volume: Volume,
+    /// Price implied by the quoted leg prices, differs from `price` when fees are included
+    pub raw_price: Price,
+}
+
+/// Virtual instrument implied by two legs quoted in the same currency,
+/// e.g. ETHBTC from the ETHUSD base leg and the BTCUSD quote leg.
+///
+/// Buying the synthetic buys the base leg and sells the quote leg, selling it does the opposite.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SyntheticBook {
+    pub symbol: Symbol,
+    pub base_leg: Symbol,
+    pub quote_leg: Symbol,
+}
+
+impl SyntheticBook {
+    pub fn new(config: &SyntheticConfig) -> Self {
+        SyntheticBook{
+            symbol: canonical_symbol(&config.symbol),
+            base_leg: canonical_symbol(&config.base_leg),
+            quote_leg: canonical_symbol(&config.quote_leg),
+        }
+    }
+
+    pub fn is_leg(&self, symbol: &str) -> bool {
+        self.base_leg == symbol || self.quote_leg == symbol
+    }
+
+    /// Best `top_num` synthetic asks, the base leg asks against the quote leg bids
+    pub fn get_top_asks(&self, base: &AggregatedBook, quote: &AggregatedBook, top_num: usize) -> Vec<SyntheticLevel> {
+        implied_ladder(base.asks(), quote.bids(), top_num)
+    }
+
+    /// Best `top_num` synthetic bids, the base leg bids against the quote leg asks
+    pub fn get_top_bids(&self, base: &AggregatedBook, quote: &AggregatedBook, top_num: usize) -> Vec<SyntheticLevel> {
+        implied_ladder(base.bids(), quote.asks(), top_num)
+    }
+
+    /// Best `top_num` synthetic asks once the taker fees of both legs are paid
+    pub fn get_top_fee_adjusted_asks(&self, base: &AggregatedBook, quote: &AggregatedBook, top_num: usize) -> Vec<SyntheticLevel> {
+        implied_ladder(base.fee_adjusted_asks(), quote.fee_adjusted_bids(), top_num)
+    }
+
+    /// Best `top_num` synthetic bids once the taker fees of both legs are paid
+    pub fn get_top_fee_adjusted_bids(&self, base: &AggregatedBook, quote: &AggregatedBook, top_num: usize) -> Vec<SyntheticLevel> {
+        implied_ladder(base.fee_adjusted_bids(), quote.fee_adjusted_asks(), top_num)
+    }
+}
+
+/// Walks both legs best level first, each step trades the notional, in the shared currency,
+/// of the thinner of the two current levels and moves past the level it empties
+fn implied_ladder(mut base: impl Iterator<Item = Level>, mut quote: impl Iterator<Item = Level>, top_num: usize) -> Vec<SyntheticLevel> {
+    let mut top_levels = Vec::new();
+    let mut base_level = base.next();
+    let mut quote_level = quote.next();
+    while top_levels.len() < top_num {
+        let (base_current, quote_current) = match (base_level.as_mut(), quote_level.as_mut()) {
+            (Some(base_current), Some(quote_current)) => (base_current, quote_current),
+            _ => break
+        };
+        if base_current.price <= Decimal::ZERO || quote_current.price <= Decimal::ZERO {
+            break;
+        }
+        let base_notional = base_current.price * base_current.volume;
+        let quote_notional = quote_current.price * quote_current.volume;
+        let notional = base_notional.min(quote_notional);
+        top_levels.push(SyntheticLevel{
+            base_exchange: base_current.exchange.clone(),
+            quote_exchange: quote_current.exchange.clone(),
+            price: base_current.price / quote_current.price,
+            volume: notional / base_current.price,
+            raw_price: base_current.raw_price / quote_current.raw_price,
+        });
+
+        if base_notional <= quote_notional {
+            quote_current.volume -= notional / quote_current.price;
+            base_level = base.next();
+        } else {
+            base_current.volume -= notional / base_current.price;
+        }
+        if quote_notional <= base_notional {
+            quote_level = quote.next();
+        }
+    }
+    top_levels
+}
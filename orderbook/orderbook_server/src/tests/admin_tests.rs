@@ -0,0 +1,326 @@
+use std::{
+    convert::Infallible,
+    str::FromStr,
+    collections::BTreeMap,
+    net::TcpListener,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration
+};
+use crate::*;
+use crate::admin::{exchange_pipelines, AdminService, PipelineFactory, Subscriptions};
+use crate::auth::{Authenticator, AUTHORIZATION};
+use crate::book_feed::{BookFeed, BookFeedSettings};
+use crate::orderbook::{admin_server::Admin, SubscriptionRequest};
+use gateway_in::error::GatewayError;
+use gateway_in::exchanges_services::ExchangeInit;
+use futures_util::StreamExt;
+use hyper::{service::{make_service_fn, service_fn}, Body, Response, Server, StatusCode};
+use tokio_tungstenite::tungstenite::handshake::server::{Request as WsRequest, Response as WsResponse};
+use rust_decimal::Decimal;
+use tonic::metadata::MetadataValue;
+use pretty_assertions::assert_eq;
+use tokio::time::timeout;
+
+/// Sends one snapshot then waits to be cancelled, as a synced exchange with no update
+struct FakePipeline {
+    exchange: Exchange,
+    symbol: Symbol,
+    starts: Arc<AtomicUsize>,
+}
+
+#[tonic::async_trait]
+impl ExchangeInit for FakePipeline {
+    async fn stream_init_task(&mut self, output_stream_tx_ch: broadcast::Sender<SnapshotData>,
+        _status_tx_ch: broadcast::Sender<FeedStatus>, cancel_token: CancellationToken) -> Result<(), GatewayError> {
+        self.starts.fetch_add(1, Ordering::SeqCst);
+        let (bid, ask) = match self.exchange {
+            Exchange::Binance => ("7.0", "8.0"),
+            Exchange::Bitstamp => ("7.5", "7.75"),
+        };
+        let mut bid_to_update: BTreeMap<Price, Volume> =  BTreeMap::new();
+        let mut ask_to_update: BTreeMap<Price, Volume> =  BTreeMap::new();
+        bid_to_update.insert(Decimal::from_str(bid).unwrap(), Decimal::from_str("1").unwrap());
+        ask_to_update.insert(Decimal::from_str(ask).unwrap(), Decimal::from_str("1").unwrap());
+        let _ = output_stream_tx_ch.send(SnapshotData {
+            exchange: self.exchange.clone(),
+            symbol: self.symbol.clone(),
+            timestamp: 1833980193,
+            bid_to_update: bid_to_update,
+            ask_to_update: ask_to_update
+        });
+        cancel_token.cancelled().await;
+        Ok(())
+    }
+}
+
+/// Stops for good as soon as it is started
+struct FailingPipeline {
+    exchange: Exchange,
+    symbol: Symbol,
+}
+
+#[tonic::async_trait]
+impl ExchangeInit for FailingPipeline {
+    async fn stream_init_task(&mut self, _output_stream_tx_ch: broadcast::Sender<SnapshotData>,
+        _status_tx_ch: broadcast::Sender<FeedStatus>, _cancel_token: CancellationToken) -> Result<(), GatewayError> {
+        Err(GatewayError::Config { exchange: self.exchange.clone(), symbol: self.symbol.clone(), what: "websocket url".to_string() })
+    }
+}
+
+fn subscriptions(cancel_token: CancellationToken, tasks: &mut JoinSet<()>) -> (Subscriptions, BookFeed, Arc<AtomicUsize>) {
+    let (snapshot_tx_ch, snapshot_rx_ch) =  broadcast::channel(10);
+    let (status_tx_ch, _) =  broadcast::channel(10);
+    let feed = BookFeed::start(BookFeedSettings::default(), snapshot_rx_ch, cancel_token.clone(), tasks);
+    let starts = Arc::new(AtomicUsize::new(0));
+    let factory_starts = starts.clone();
+    let factory: PipelineFactory = Arc::new(move |exchange: &Exchange, symbol: &str| -> Box<dyn ExchangeInit + Send> {
+        Box::new(FakePipeline{exchange: exchange.clone(), symbol: canonical_symbol(symbol), starts: factory_starts.clone()})
+    });
+    let subscriptions = Subscriptions::new(factory, feed.clone(), snapshot_tx_ch, status_tx_ch, cancel_token);
+    (subscriptions, feed, starts)
+}
+
+fn request(exchange: &str, symbol: &str) -> Request<SubscriptionRequest> {
+    Request::new(SubscriptionRequest{exchange: exchange.to_string(), symbol: symbol.to_string()})
+}
+
+fn exchanges(summary: &SummaryViews) -> Vec<String> {
+    let mut exchanges: Vec<String> = summary.by_venue.bids.iter().map(|level| level.exchange.clone()).collect();
+    exchanges.sort();
+    exchanges.dedup();
+    exchanges
+}
+
+#[tokio::test]
+async fn test_add_and_remove_subscriptions() {
+
+    let mut tasks = JoinSet::new();
+    let (subscriptions, feed, _) = subscriptions(CancellationToken::new(), &mut tasks);
+    assert!(feed.subscribe("ETHBTC").is_none());
+
+    let binance = subscriptions.add(&Exchange::Binance, "ethbtc").await.unwrap();
+    assert_eq!((binance.exchange.as_str(), binance.symbol.as_str()), ("Binance", "ETHBTC"));
+    assert!(binance.started_time > 0);
+    let (_, mut summary_rx_ch) = feed.subscribe("ETHBTC").unwrap();
+    let summary = timeout(Duration::from_secs(1), summary_rx_ch.recv()).await.unwrap().unwrap();
+    assert_eq!(exchanges(&summary), vec!["Binance"]);
+
+    subscriptions.add(&Exchange::Bitstamp, "ETHBTC").await.unwrap();
+    let summary = timeout(Duration::from_secs(1), summary_rx_ch.recv()).await.unwrap().unwrap();
+    assert_eq!(exchanges(&summary), vec!["Binance", "Bitstamp"]);
+    assert!(subscriptions.add(&Exchange::Binance, "ETHBTC").await.is_none());
+    assert_eq!(subscriptions.list().await.len(), 2);
+
+    // the other exchange keeps streaming
+    assert!(subscriptions.remove(&Exchange::Bitstamp, "ETHBTC").await);
+    let summary = timeout(Duration::from_secs(1), summary_rx_ch.recv()).await.unwrap().unwrap();
+    assert_eq!(exchanges(&summary), vec!["Binance"]);
+    assert!(!subscriptions.remove(&Exchange::Bitstamp, "ETHBTC").await);
+    assert_eq!(subscriptions.list().await.iter().map(|subscription| subscription.exchange.as_str()).collect::<Vec<&str>>(), vec!["Binance"]);
+
+    // the streams of the symbol end with its last exchange
+    assert!(subscriptions.remove(&Exchange::Binance, "ETHBTC").await);
+    assert!(feed.subscribe("ETHBTC").is_none());
+    assert!(matches!(timeout(Duration::from_secs(1), summary_rx_ch.recv()).await.unwrap(), Err(broadcast::error::RecvError::Closed)));
+    assert!(subscriptions.list().await.is_empty());
+}
+
+#[tokio::test]
+async fn test_admin_service() {
+
+    let mut tasks = JoinSet::new();
+    let cancel_token = CancellationToken::new();
+    let (subscriptions, feed, starts) = subscriptions(cancel_token.clone(), &mut tasks);
+    let service = AdminService::new(subscriptions.clone());
+
+    assert_eq!(service.add_subscription(request("Kraken", "ETHBTC")).await.unwrap_err().code(), tonic::Code::InvalidArgument);
+    assert_eq!(service.add_subscription(request("Binance", "")).await.unwrap_err().code(), tonic::Code::InvalidArgument);
+    assert_eq!(service.remove_subscription(request("Binance", "ETHBTC")).await.unwrap_err().code(), tonic::Code::NotFound);
+    assert_eq!(service.resync_book(request("Binance", "ETHBTC")).await.unwrap_err().code(), tonic::Code::NotFound);
+
+    let added = service.add_subscription(request("Binance", "ETHBTC")).await.unwrap().into_inner();
+    assert_eq!(service.add_subscription(request("binance", "ethbtc")).await.unwrap_err().code(), tonic::Code::AlreadyExists);
+    let (_, mut summary_rx_ch) = feed.subscribe("ETHBTC").unwrap();
+    timeout(Duration::from_secs(1), summary_rx_ch.recv()).await.unwrap().unwrap();
+
+    // the book is dropped then rebuilt from the snapshot of the restarted pipeline
+    let resynced = service.resync_book(request("Binance", "ETHBTC")).await.unwrap().into_inner();
+    assert!(resynced.started_time >= added.started_time);
+    let summary = timeout(Duration::from_secs(1), summary_rx_ch.recv()).await.unwrap().unwrap();
+    assert_eq!(exchanges(&summary), vec!["Binance"]);
+    assert_eq!(starts.load(Ordering::SeqCst), 2);
+
+    let list = service.list_subscriptions(Request::new(Empty{})).await.unwrap().into_inner();
+    assert_eq!(list.subscriptions, vec![resynced]);
+
+    // every pipeline stops with the server
+    cancel_token.cancel();
+    timeout(Duration::from_secs(1), subscriptions.join()).await.unwrap();
+    assert!(subscriptions.list().await.is_empty());
+}
+
+#[tokio::test]
+async fn test_failed_pipeline_is_dropped() {
+
+    let mut tasks = JoinSet::new();
+    let cancel_token = CancellationToken::new();
+    let (snapshot_tx_ch, snapshot_rx_ch) =  broadcast::channel(10);
+    let (status_tx_ch, mut status_rx_ch) =  broadcast::channel(10);
+    let feed = BookFeed::start(BookFeedSettings::default(), snapshot_rx_ch, cancel_token.clone(), &mut tasks);
+    let factory: PipelineFactory = Arc::new(|exchange: &Exchange, symbol: &str| -> Box<dyn ExchangeInit + Send> {
+        Box::new(FailingPipeline{exchange: exchange.clone(), symbol: canonical_symbol(symbol)})
+    });
+    let subscriptions = Subscriptions::new(factory, feed.clone(), snapshot_tx_ch, status_tx_ch, cancel_token.clone());
+
+    subscriptions.add(&Exchange::Binance, "ETHBTC").await.unwrap();
+    let status = timeout(Duration::from_secs(1), status_rx_ch.recv()).await.unwrap().unwrap();
+    assert_eq!((status.exchange, status.symbol.as_str(), status.state), (Exchange::Binance, "ETHBTC", FeedState::Disconnected));
+    assert_eq!(status.reason.unwrap(), "Binance ETHBTC: missing configuration: websocket url");
+
+    // neither the subscription nor its book are left behind
+    timeout(Duration::from_secs(1), async {
+        while !subscriptions.list().await.is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }).await.unwrap();
+    assert!(feed.subscribe("ETHBTC").is_none());
+    cancel_token.cancel();
+}
+
+/// Answers the snapshots of ETHBTC on the Binance and Bitstamp paths, anything else is a bad request
+fn snapshot_server(listener: TcpListener, cancel_token: CancellationToken) -> tokio::task::JoinHandle<hyper::Result<()>> {
+    let make_service = make_service_fn(|_| async {
+        Ok::<_, Infallible>(service_fn(|request: hyper::Request<Body>| async move {
+            let body = match (request.uri().path(), request.uri().query()) {
+                ("/api/v3/depth", Some("symbol=ETHBTC&limit=10")) =>
+                    r#"{"lastUpdateId": 160, "bids": [["0.0024", "10"]], "asks": [["0.0026", "100"]]}"#,
+                ("/api/v2/order_book/ethbtc", None) =>
+                    r#"{"microtimestamp": "1833980193054545", "timestamp": "1833980193", "bids": [["0.0024", "10"]], "asks": [["0.0026", "100"]]}"#,
+                _ => {
+                    let mut response = Response::new(Body::empty());
+                    *response.status_mut() = StatusCode::BAD_REQUEST;
+                    return Ok::<_, Infallible>(response);
+                }
+            };
+            Ok::<_, Infallible>(Response::new(Body::from(body)))
+        }))
+    });
+    let server = Server::from_tcp(listener).unwrap()
+        .serve(make_service)
+        .with_graceful_shutdown(async move { cancel_token.cancelled().await });
+    tokio::spawn(server)
+}
+
+/// Accepts the exchange websockets, the path of each connection and the first message it sends are
+/// sent on `events_tx_ch`
+async fn websocket_server(listener: tokio::net::TcpListener, events_tx_ch: mpsc::UnboundedSender<String>) {
+    loop {
+        let (stream, _) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(_) => return
+        };
+        let events_tx_ch = events_tx_ch.clone();
+        tokio::spawn(async move {
+            let path_tx_ch = events_tx_ch.clone();
+            let mut websocket = tokio_tungstenite::accept_hdr_async(stream, move |request: &WsRequest, response: WsResponse| {
+                let _ = path_tx_ch.send(request.uri().path().to_string());
+                Ok(response)
+            }).await.unwrap();
+            if let Some(Ok(message)) = websocket.next().await {
+                let _ = events_tx_ch.send(message.to_string());
+            }
+            // kept open until the pipeline closes it
+            while websocket.next().await.is_some() {}
+        });
+    }
+}
+
+#[tokio::test]
+async fn test_exchange_pipelines_stream_any_symbol() {
+
+    let mut tasks = JoinSet::new();
+    let cancel_token = CancellationToken::new();
+    let http_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let http_address = http_listener.local_addr().unwrap();
+    snapshot_server(http_listener, cancel_token.clone());
+    let ws_listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let ws_address = ws_listener.local_addr().unwrap();
+    let (events_tx_ch, mut events_rx_ch) = mpsc::unbounded_channel();
+    let ws_server = tokio::spawn(websocket_server(ws_listener, events_tx_ch));
+
+    // no symbol configured, every one is added through the Admin service
+    let binance: BinanceConfig = serde_json::from_str(&format!(r#"{{
+        "websocket_base_url": "ws://{}/stream",
+        "websocket_rate_ms": 100,
+        "symbols": [],
+        "snapshot_depth": 10,
+        "snapshot_base_url": "http://{}/api/v3/depth"
+    }}"#, ws_address, http_address)).unwrap();
+    let bitstamp: BitstampConfig = serde_json::from_str(&format!(r#"{{
+        "websocket_base_url": "ws://{}",
+        "symbols": [],
+        "snapshot_base_url": "http://{}/api/v2/order_book"
+    }}"#, ws_address, http_address)).unwrap();
+    let (snapshot_tx_ch, snapshot_rx_ch) =  broadcast::channel(10);
+    let (status_tx_ch, _) =  broadcast::channel(10);
+    let feed = BookFeed::start(BookFeedSettings::default(), snapshot_rx_ch, cancel_token.clone(), &mut tasks);
+    let subscriptions = Subscriptions::new(exchange_pipelines(binance, bitstamp), feed, snapshot_tx_ch, status_tx_ch, cancel_token.clone());
+    let service = AdminService::new(subscriptions.clone());
+
+    // each exchange is asked for the symbol in its own case
+    let binance = service.add_subscription(request("Binance", "ethbtc")).await.unwrap().into_inner();
+    let bitstamp = service.add_subscription(request("Bitstamp", "ETHBTC")).await.unwrap().into_inner();
+    assert_eq!((binance.symbol.as_str(), bitstamp.symbol.as_str()), ("ETHBTC", "ETHBTC"));
+    let mut events = Vec::new();
+    while events.len() < 3 {
+        events.push(timeout(Duration::from_secs(1), events_rx_ch.recv()).await.unwrap().unwrap());
+    }
+    events.sort();
+    assert_eq!(events, vec![
+        "/".to_string(),
+        "/ws/ethbtc@depth@100ms".to_string(),
+        "{\"event\": \"bts:subscribe\", \"data\": { \"channel\": \"order_book_ethbtc\" } }".to_string()
+    ]);
+
+    // a symbol the exchange does not list is refused and not streamed
+    assert_eq!(service.add_subscription(request("Binance", "XRPBTC")).await.unwrap_err().code(), tonic::Code::InvalidArgument);
+    assert_eq!(service.add_subscription(request("Bitstamp", "xrpbtc")).await.unwrap_err().code(), tonic::Code::InvalidArgument);
+    assert_eq!(subscriptions.list().await.len(), 2);
+
+    cancel_token.cancel();
+    timeout(Duration::from_secs(5), subscriptions.join()).await.unwrap();
+    ws_server.abort();
+}
+
+#[test]
+fn test_admin_grant() {
+
+    let mut clients = BTreeMap::new();
+    clients.insert("webserver".to_string(), ClientGrant::default());
+    clients.insert("ops".to_string(), ClientGrant{admin: true, ..Default::default()});
+    let mut tokens = BTreeMap::new();
+    tokens.insert("webserver-token".to_string(), "webserver".to_string());
+    tokens.insert("ops-token".to_string(), "ops".to_string());
+    let auth = Authenticator::new(&AuthConfig{clients: clients, tokens: tokens, ..Default::default()}).unwrap();
+    let interceptor = auth.admin_interceptor();
+
+    let request = |token: &str| {
+        let mut request = Request::new(());
+        request.metadata_mut().insert(AUTHORIZATION, MetadataValue::from_str(&format!("Bearer {}", token)).unwrap());
+        request
+    };
+    assert_eq!(interceptor(request("webserver-token")).unwrap_err().code(), tonic::Code::PermissionDenied);
+    assert_eq!(interceptor(Request::new(())).unwrap_err().code(), tonic::Code::Unauthenticated);
+    assert!(interceptor(request("ops-token")).is_ok());
+
+    assert!(auth.admin_enabled());
+
+    // closed when auth is disabled or nobody is granted admin
+    assert!(!Authenticator::default().admin_enabled());
+    assert_eq!(Authenticator::default().admin_interceptor()(Request::new(())).unwrap_err().code(), tonic::Code::PermissionDenied);
+    let mut clients = BTreeMap::new();
+    clients.insert("webserver".to_string(), ClientGrant::default());
+    let mut tokens = BTreeMap::new();
+    tokens.insert("webserver-token".to_string(), "webserver".to_string());
+    assert!(!Authenticator::new(&AuthConfig{clients: clients, tokens: tokens, ..Default::default()}).unwrap().admin_enabled());
+}
@@ -0,0 +1,83 @@
+use std::str::FromStr;
+use crate::*;
+use crate::aggregated_order_book::AggregatedBook;
+use crate::analytics::{BookAnalytics, BookStats, DepthWithin};
+use rust_decimal::Decimal;
+use super::book;
+use pretty_assertions::assert_eq;
+
+fn aggregated_book() -> AggregatedBook {
+    let mut agrregate_book = AggregatedBook::new();
+    agrregate_book.update_book(book(Exchange::Binance, &[("99", "1"), ("98", "2")], &[("101", "1"), ("102", "2")]));
+    agrregate_book.update_book(book(Exchange::Bitstamp, &[("99", "3"), ("97", "1")], &[("103", "1")]));
+    agrregate_book
+}
+
+fn all_metrics() -> AnalyticsConfig {
+    AnalyticsConfig {
+        metrics: vec![Metric::Mid, Metric::Microprice, Metric::Imbalance, Metric::Depth, Metric::WeightedSpread],
+        imbalance_levels: 1,
+        depth_bps: vec![Decimal::from_str("100").unwrap(), Decimal::from_str("200").unwrap()],
+        weighted_spread_volume: Decimal::from_str("2").unwrap(),
+    }
+}
+
+#[test]
+fn test_aggregated_book_stats() {
+
+    let stats = BookAnalytics::new(all_metrics()).stats(&aggregated_book());
+
+    assert_eq!(stats, BookStats {
+        exchange: None,
+        mid: Some(Decimal::from_str("100").unwrap()),
+        // 4 bid at 99 against 1 ask at 101
+        microprice: Some(Decimal::from_str("100.6").unwrap()),
+        imbalance: Some(Decimal::from_str("0.6").unwrap()),
+        depth: vec![
+            DepthWithin {
+                bps: Decimal::from_str("100").unwrap(),
+                bid_volume: Decimal::from_str("4").unwrap(),
+                ask_volume: Decimal::from_str("1").unwrap(),
+            },
+            DepthWithin {
+                bps: Decimal::from_str("200").unwrap(),
+                bid_volume: Decimal::from_str("6").unwrap(),
+                ask_volume: Decimal::from_str("3").unwrap(),
+            },
+        ],
+        // buys 2 at 101.5 and sells 2 at 99
+        weighted_spread_bps: Some(Decimal::from_str("250").unwrap()),
+    });
+}
+
+#[test]
+fn test_venue_stats() {
+
+    let venue_stats = BookAnalytics::new(all_metrics()).venue_stats(&aggregated_book());
+
+    assert_eq!(venue_stats.len(), 2);
+    assert_eq!(venue_stats[0].exchange, Some(Exchange::Binance));
+    assert_eq!(venue_stats[0].mid, Some(Decimal::from_str("100").unwrap()));
+    assert_eq!(venue_stats[0].imbalance, Some(Decimal::ZERO));
+    assert_eq!(venue_stats[0].weighted_spread_bps, Some(Decimal::from_str("300").unwrap()));
+    assert_eq!(venue_stats[1].exchange, Some(Exchange::Bitstamp));
+    assert_eq!(venue_stats[1].mid, Some(Decimal::from_str("101").unwrap()));
+    // a single ask, too thin to sell 2
+    assert_eq!(venue_stats[1].weighted_spread_bps, None);
+}
+
+#[test]
+fn test_only_configured_metrics() {
+
+    let analytics = BookAnalytics::new(AnalyticsConfig {
+        metrics: vec![Metric::Mid],
+        ..all_metrics()
+    });
+    let stats = analytics.stats(&aggregated_book());
+    assert_eq!(stats, BookStats {
+        mid: Some(Decimal::from_str("100").unwrap()),
+        ..Default::default()
+    });
+
+    assert!(!BookAnalytics::default().is_enabled());
+}
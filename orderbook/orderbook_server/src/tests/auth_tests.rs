@@ -0,0 +1,146 @@
+use std::{collections::BTreeMap, time::Duration};
+use crate::*;
+use crate::auth::{Authenticator, AUTHORIZATION};
+use crate::book_feed::{BookFeed, BookFeedSettings};
+use ring::hmac;
+use tonic::metadata::{MetadataMap, MetadataValue};
+use pretty_assertions::assert_eq;
+
+const JWT_SECRET: &str = "jwt-secret";
+
+fn auth_config() -> AuthConfig {
+    let mut clients = BTreeMap::new();
+    clients.insert("webserver".to_string(), ClientGrant::default());
+    clients.insert("desk".to_string(), ClientGrant{
+        symbols: vec!["ethbtc".to_string()],
+        exchanges: vec!["Binance".to_string()],
+        max_depth: Some(5),
+        max_streams: Some(1),
+        admin: false,
+    });
+    let mut tokens = BTreeMap::new();
+    tokens.insert("webserver-token".to_string(), "webserver".to_string());
+    tokens.insert("desk-token".to_string(), "desk".to_string());
+    tokens.insert("unknown-token".to_string(), "unknown".to_string());
+    AuthConfig{
+        clients: clients,
+        tokens: tokens,
+        jwt_secret: Some(JWT_SECRET.to_string()),
+        client_token: None,
+    }
+}
+
+fn metadata(token: &str) -> MetadataMap {
+    let mut metadata = MetadataMap::new();
+    metadata.insert(AUTHORIZATION, MetadataValue::from_str(&format!("Bearer {}", token)).unwrap());
+    metadata
+}
+
+fn jwt(header: &str, claims: &str, secret: &str) -> String {
+    let encode = |part: &str| base64::encode_config(part, base64::URL_SAFE_NO_PAD);
+    let signed = format!("{}.{}", encode(header), encode(claims));
+    let signature = hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes()), signed.as_bytes());
+    format!("{}.{}", signed, base64::encode_config(signature.as_ref(), base64::URL_SAFE_NO_PAD))
+}
+
+#[test]
+fn test_static_tokens() {
+
+    let auth = Authenticator::new(&auth_config()).unwrap();
+
+    assert_eq!(auth.grant(&MetadataMap::new()).unwrap_err().code(), tonic::Code::Unauthenticated);
+    assert_eq!(auth.grant(&metadata("wrong-token")).unwrap_err().code(), tonic::Code::Unauthenticated);
+    assert_eq!(auth.grant(&metadata("unknown-token")).unwrap_err().code(), tonic::Code::PermissionDenied);
+
+    let webserver = auth.grant(&metadata("webserver-token")).unwrap();
+    assert_eq!(webserver.client, "webserver");
+    assert!(webserver.check_symbol("BTCUSD").is_ok());
+    assert_eq!(webserver.exchanges(Vec::new()).unwrap(), Vec::<Exchange>::new());
+
+    let desk = auth.grant(&metadata("desk-token")).unwrap();
+    assert!(desk.check_symbol("ETHBTC").is_ok());
+    assert_eq!(desk.check_symbol("BTCUSD").unwrap_err().code(), tonic::Code::PermissionDenied);
+    assert_eq!(desk.exchanges(Vec::new()).unwrap(), vec![Exchange::Binance]);
+    assert_eq!(desk.exchanges(vec![Exchange::Bitstamp]).unwrap_err().code(), tonic::Code::PermissionDenied);
+    assert!(desk.allows("ETHBTC", &["Binance"]));
+    assert!(!desk.allows("ETHBTC", &["Binance", "Bitstamp"]));
+
+    let interceptor = auth.interceptor();
+    assert_eq!(interceptor(Request::new(())).unwrap_err().code(), tonic::Code::Unauthenticated);
+
+    // everything is granted when no token is configured
+    assert!(Authenticator::default().grant(&MetadataMap::new()).is_ok());
+    let mut config = auth_config();
+    config.clients.get_mut("desk").unwrap().exchanges = vec!["Kraken".to_string()];
+    assert!(Authenticator::new(&config).is_err());
+}
+
+#[test]
+fn test_jwt_tokens() {
+
+    let auth = Authenticator::new(&auth_config()).unwrap();
+    let header = r#"{"alg":"HS256","typ":"JWT"}"#;
+    let expiry = now_millis() / 1000 + 60;
+
+    let token = jwt(header, &format!(r#"{{"sub":"desk","exp":{}}}"#, expiry), JWT_SECRET);
+    assert_eq!(auth.grant(&metadata(&token)).unwrap().client, "desk");
+    let token = jwt(header, r#"{"sub":"webserver"}"#, JWT_SECRET);
+    assert_eq!(auth.grant(&metadata(&token)).unwrap().client, "webserver");
+
+    let expired = jwt(header, r#"{"sub":"desk","exp":1}"#, JWT_SECRET);
+    assert_eq!(auth.grant(&metadata(&expired)).unwrap_err().code(), tonic::Code::Unauthenticated);
+    let other_secret = jwt(header, r#"{"sub":"desk"}"#, "other-secret");
+    assert_eq!(auth.grant(&metadata(&other_secret)).unwrap_err().code(), tonic::Code::Unauthenticated);
+    let unsigned = jwt(r#"{"alg":"none"}"#, r#"{"sub":"desk"}"#, JWT_SECRET);
+    assert_eq!(auth.grant(&metadata(&unsigned)).unwrap_err().code(), tonic::Code::Unauthenticated);
+    let no_grant = jwt(header, r#"{"sub":"intruder"}"#, JWT_SECRET);
+    assert_eq!(auth.grant(&metadata(&no_grant)).unwrap_err().code(), tonic::Code::PermissionDenied);
+}
+
+#[tokio::test]
+async fn test_service_enforces_grants() {
+
+    let (_snapshot_tx_ch, snapshot_rx_ch) =  broadcast::channel(10);
+    let (_status_tx_ch, status_rx_ch) =  broadcast::channel(10);
+    let mut tasks = JoinSet::new();
+    let cancel_token = CancellationToken::new();
+    let settings = BookFeedSettings {
+        symbols: vec!["ETHBTC".to_string(), "BTCUSD".to_string()],
+        ..Default::default()
+    };
+    let feed = BookFeed::start(settings, snapshot_rx_ch, cancel_token.clone(), &mut tasks);
+    let status_feed = StatusFeed::start(status_rx_ch, cancel_token.clone(), &mut tasks);
+    let auth = Authenticator::new(&auth_config()).unwrap();
+    let service = OrderbookService::new(feed, status_feed, auth, cancel_token.clone());
+    let request = |symbol: &str, depth: u32, exchanges: Vec<String>| {
+        let mut request = Request::new(BookSummaryRequest {
+            symbol: symbol.to_string(),
+            mode: AggregationMode::ByVenue as i32,
+            depth: depth,
+            exchanges: exchanges,
+            max_update_rate: 0,
+        });
+        *request.metadata_mut() = metadata("desk-token");
+        request
+    };
+
+    let denied = |result: Result<Response<ReceiverStream<Result<Summary, Status>>>, Status>| result.unwrap_err().code() == tonic::Code::PermissionDenied;
+    assert!(denied(service.book_summary(request("BTCUSD", 0, Vec::new())).await));
+    assert!(denied(service.book_summary(request("ETHBTC", 6, Vec::new())).await));
+    assert!(denied(service.book_summary(request("ETHBTC", 0, vec!["Bitstamp".to_string()])).await));
+
+    // one stream at a time
+    let stream = service.book_summary(request("ETHBTC", 5, Vec::new())).await.unwrap();
+    assert!(denied(service.book_summary(request("ETHBTC", 0, Vec::new())).await));
+    drop(stream);
+    let mut reopened = false;
+    for _ in 0..100 {
+        if service.book_summary(request("ETHBTC", 0, Vec::new())).await.is_ok() {
+            reopened = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert!(reopened);
+    cancel_token.cancel();
+}
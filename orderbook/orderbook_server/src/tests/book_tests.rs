@@ -3,7 +3,7 @@ use std::{
     collections::BTreeMap
 };
 use crate::*;
-use crate::aggregated_order_book::{AggregatedBook, Level};
+use crate::aggregated_order_book::{AggregatedBook, AggregatedBookSet, ConsolidatedLevel, Level};
 use rust_decimal::Decimal;
 use pretty_assertions::assert_eq;
 
@@ -122,4 +122,182 @@ fn  test_get_top_bids_aggregate_book() {
     
     assert_eq!(bids_expected, agrregate_book_top_bids_result);
   
-}
\ No newline at end of file
+}
+#[test]
+fn test_book_set_keeps_symbols_apart() {
+
+    let mut asks_ethbtc: BTreeMap<Price, Volume> =  BTreeMap::new();
+    let mut asks_ltcbtc: BTreeMap<Price, Volume> =  BTreeMap::new();
+    asks_ethbtc.insert(Decimal::from_str("0.07").unwrap(), Decimal::from_str("1").unwrap());
+    asks_ltcbtc.insert(Decimal::from_str("0.003").unwrap(), Decimal::from_str("2").unwrap());
+
+    let update_ethbtc = SnapshotData {
+        exchange: Exchange::Bitstamp,
+        symbol: "ethbtc".to_string(),
+        timestamp: 1833980193,
+        bid_to_update: BTreeMap::new(),
+        ask_to_update: asks_ethbtc
+    };
+    let update_ltcbtc = SnapshotData {
+        exchange: Exchange::Bitstamp,
+        symbol: "ltcbtc".to_string(),
+        timestamp: 1833980193,
+        bid_to_update: BTreeMap::new(),
+        ask_to_update: asks_ltcbtc
+    };
+
+    let mut agrregate_books = AggregatedBookSet::new(FeesConfig::default());
+    let bitstamp = Exchange::Bitstamp;
+    assert_eq!(agrregate_books.update_book(update_ethbtc).get_top_asks(20),
+        vec![Level::new(bitstamp.clone(), Decimal::from_str("0.07").unwrap(), Decimal::from_str("1").unwrap())]);
+    assert_eq!(agrregate_books.update_book(update_ltcbtc).get_top_asks(20),
+        vec![Level::new(bitstamp.clone(), Decimal::from_str("0.003").unwrap(), Decimal::from_str("2").unwrap())]);
+}
+
+#[test]
+fn test_get_top_levels_past_book_depth() {
+
+    let mut asks_from_exchange_1: BTreeMap<Price, Volume> =  BTreeMap::new();
+    let mut bids_from_exchange_2: BTreeMap<Price, Volume> =  BTreeMap::new();
+    asks_from_exchange_1.insert(Decimal::from_str("9.0").unwrap(), Decimal::from_str("2").unwrap());
+    bids_from_exchange_2.insert(Decimal::from_str("7.0").unwrap(), Decimal::from_str("5").unwrap());
+    bids_from_exchange_2.insert(Decimal::from_str("6.0").unwrap(), Decimal::from_str("5").unwrap());
+
+    let mut agrregate_book_result = AggregatedBook::new();
+    agrregate_book_result.update_book(SnapshotData {
+        exchange: Exchange::Binance,
+        symbol: "BNBBTC".to_string(),
+        timestamp: 1833980193,
+        bid_to_update: BTreeMap::new(),
+        ask_to_update: asks_from_exchange_1
+    });
+    agrregate_book_result.update_book(SnapshotData {
+        exchange: Exchange::Bitstamp,
+        symbol: "BNBBTC".to_string(),
+        timestamp: 1833980193,
+        bid_to_update: bids_from_exchange_2,
+        ask_to_update: BTreeMap::new()
+    });
+
+    let bitstamp = Exchange::Bitstamp;
+    let binance = Exchange::Binance;
+    assert_eq!(agrregate_book_result.get_top_asks(20),
+        vec![Level::new(binance.clone(), Decimal::from_str("9.0").unwrap(), Decimal::from_str("2").unwrap())]);
+    assert_eq!(agrregate_book_result.get_top_bids(20), vec![
+        Level::new(bitstamp.clone(), Decimal::from_str("7.0").unwrap(), Decimal::from_str("5").unwrap()),
+        Level::new(bitstamp.clone(), Decimal::from_str("6.0").unwrap(), Decimal::from_str("5").unwrap())]);
+    assert_eq!(agrregate_book_result.get_top_bids(0), Vec::new());
+}
+
+#[test]
+fn test_get_top_consolidated_asks_aggregate_book() {
+
+    let mut asks_from_exchange_1: BTreeMap<Price, Volume> =  BTreeMap::new();
+    let mut asks_from_exchange_2: BTreeMap<Price, Volume> =  BTreeMap::new();
+
+    asks_from_exchange_1.insert(Decimal::from_str("10.0").unwrap(), Decimal::from_str("3").unwrap());
+    asks_from_exchange_1.insert(Decimal::from_str("9.0").unwrap(), Decimal::from_str("2").unwrap());
+    asks_from_exchange_1.insert(Decimal::from_str("8.0").unwrap(), Decimal::from_str("5").unwrap());
+
+    asks_from_exchange_2.insert(Decimal::from_str("11.0").unwrap(), Decimal::from_str("3").unwrap());
+    asks_from_exchange_2.insert(Decimal::from_str("8.0").unwrap(), Decimal::from_str("4").unwrap());
+    asks_from_exchange_2.insert(Decimal::from_str("7.0").unwrap(), Decimal::from_str("6").unwrap());
+
+    let mut agrregate_book_result = AggregatedBook::new();
+    agrregate_book_result.update_book(SnapshotData {
+        exchange: Exchange::Binance,
+        symbol: "BNBBTC".to_string(),
+        timestamp: 1833980193,
+        bid_to_update: BTreeMap::new(),
+        ask_to_update: asks_from_exchange_1
+    });
+    agrregate_book_result.update_book(SnapshotData {
+        exchange: Exchange::Bitstamp,
+        symbol: "BNBBTC".to_string(),
+        timestamp: 1833980193,
+        bid_to_update: BTreeMap::new(),
+        ask_to_update: asks_from_exchange_2
+    });
+
+    let bitstamp = Exchange::Bitstamp;
+    let binance = Exchange::Binance;
+    let asks_expected = vec![
+        ConsolidatedLevel {
+            price: Decimal::from_str("7.0").unwrap(),
+            volume: Decimal::from_str("6").unwrap(),
+            exchanges: vec![Level::new(bitstamp.clone(), Decimal::from_str("7.0").unwrap(), Decimal::from_str("6").unwrap())]
+        },
+        ConsolidatedLevel {
+            price: Decimal::from_str("8.0").unwrap(),
+            volume: Decimal::from_str("9").unwrap(),
+            exchanges: vec![
+                Level::new(binance.clone(), Decimal::from_str("8.0").unwrap(), Decimal::from_str("5").unwrap()),
+                Level::new(bitstamp.clone(), Decimal::from_str("8.0").unwrap(), Decimal::from_str("4").unwrap())]
+        },
+        ConsolidatedLevel {
+            price: Decimal::from_str("9.0").unwrap(),
+            volume: Decimal::from_str("2").unwrap(),
+            exchanges: vec![Level::new(binance.clone(), Decimal::from_str("9.0").unwrap(), Decimal::from_str("2").unwrap())]
+        },
+    ];
+
+    assert_eq!(asks_expected, agrregate_book_result.get_top_consolidated_asks(3));
+}
+
+#[test]
+fn test_get_top_fee_adjusted_asks_aggregate_book() {
+
+    let mut asks_from_exchange_1: BTreeMap<Price, Volume> =  BTreeMap::new();
+    let mut asks_from_exchange_2: BTreeMap<Price, Volume> =  BTreeMap::new();
+
+    asks_from_exchange_1.insert(Decimal::from_str("100.0").unwrap(), Decimal::from_str("1").unwrap());
+    asks_from_exchange_1.insert(Decimal::from_str("100.2").unwrap(), Decimal::from_str("1").unwrap());
+    asks_from_exchange_2.insert(Decimal::from_str("99.9").unwrap(), Decimal::from_str("2").unwrap());
+
+    let mut fees = FeesConfig::default();
+    fees.binance.taker_bps = Decimal::from_str("10").unwrap();
+    fees.bitstamp.taker_bps = Decimal::from_str("50").unwrap();
+
+    let mut agrregate_books = AggregatedBookSet::new(fees);
+    agrregate_books.update_book(SnapshotData {
+        exchange: Exchange::Binance,
+        symbol: "BNBBTC".to_string(),
+        timestamp: 1833980193,
+        bid_to_update: BTreeMap::new(),
+        ask_to_update: asks_from_exchange_1
+    });
+    let agrregate_book_result = agrregate_books.update_book(SnapshotData {
+        exchange: Exchange::Bitstamp,
+        symbol: "BNBBTC".to_string(),
+        timestamp: 1833980193,
+        bid_to_update: BTreeMap::new(),
+        ask_to_update: asks_from_exchange_2
+    });
+
+    // Bitstamp quotes the best raw price but costs more once its fee is paid
+    let bitstamp = Exchange::Bitstamp;
+    let binance = Exchange::Binance;
+    let asks_expected = vec![
+        Level {
+            exchange: binance.clone(),
+            price: Decimal::from_str("100.1").unwrap(),
+            volume: Decimal::from_str("1").unwrap(),
+            raw_price: Decimal::from_str("100.0").unwrap()
+        },
+        Level {
+            exchange: binance.clone(),
+            price: Decimal::from_str("100.3002").unwrap(),
+            volume: Decimal::from_str("1").unwrap(),
+            raw_price: Decimal::from_str("100.2").unwrap()
+        },
+        Level {
+            exchange: bitstamp.clone(),
+            price: Decimal::from_str("100.3995").unwrap(),
+            volume: Decimal::from_str("2").unwrap(),
+            raw_price: Decimal::from_str("99.9").unwrap()
+        },
+    ];
+
+    assert_eq!(asks_expected, agrregate_book_result.get_top_fee_adjusted_asks(3));
+    assert_eq!(agrregate_book_result.get_top_asks(1)[0].exchange, bitstamp);
+}
@@ -0,0 +1,128 @@
+use std::str::FromStr;
+use crate::*;
+use crate::aggregated_order_book::AggregatedBook;
+use crate::book_feed::{BookFeed, BookFeedSettings};
+use crate::crossed_market::CrossedMarketDetector;
+use rust_decimal::Decimal;
+use super::book;
+use pretty_assertions::assert_eq;
+
+#[test]
+fn test_crossed_market_reported_after_persisting() {
+
+    let symbol = "ETHBTC".to_string();
+    let mut agrregate_book = AggregatedBook::new();
+    agrregate_book.update_book(book(Exchange::Binance, &[("101", "1"), ("100.5", "2")], &[("102", "1")]));
+    agrregate_book.update_book(book(Exchange::Bitstamp, &[("99", "1")], &[("100", "0.5"), ("100.8", "3"), ("102", "1")]));
+    agrregate_book.set_taker_fee(Exchange::Binance, Decimal::from_str("10").unwrap());
+
+    let mut detector = CrossedMarketDetector::new(CrossedMarketConfig {
+        min_edge_bps: Some(Decimal::from_str("50").unwrap()),
+        persist_ms: 100,
+    });
+    assert_eq!(detector.check(&symbol, &agrregate_book, 1_000), Vec::new());
+    let events = detector.check(&symbol, &agrregate_book, 1_100);
+    assert_eq!(events.len(), 1);
+
+    let crossed = &events[0];
+    assert_eq!(crossed.bid_exchange, Exchange::Binance);
+    assert_eq!(crossed.ask_exchange, Exchange::Bitstamp);
+    assert_eq!(crossed.bid_price, Decimal::from_str("101").unwrap());
+    assert_eq!(crossed.ask_price, Decimal::from_str("100").unwrap());
+    // 100.899 = 101 less 10 bps, 100.8 still sells at a gain against it, 100.5 does not
+    assert_eq!(crossed.edge_bps, Decimal::from_str("89.9").unwrap());
+    assert_eq!(crossed.volume, Decimal::from_str("1").unwrap());
+    assert_eq!(crossed.since, 1_000);
+    assert!(crossed.active);
+
+    // reported once while it lasts, then when it ends
+    assert_eq!(detector.check(&symbol, &agrregate_book, 1_200), Vec::new());
+    agrregate_book.update_book(book(Exchange::Binance, &[("99.5", "1")], &[("102", "1")]));
+    let events = detector.check(&symbol, &agrregate_book, 1_300);
+    assert_eq!(events.len(), 1);
+    assert!(!events[0].active);
+    assert_eq!(events[0].timestamp, 1_300);
+}
+
+#[test]
+fn test_crossed_market_ends_with_exchange() {
+
+    let symbol = "ETHBTC".to_string();
+    let mut agrregate_book = AggregatedBook::new();
+    agrregate_book.update_book(book(Exchange::Binance, &[("101", "1")], &[("102", "1")]));
+    agrregate_book.update_book(book(Exchange::Bitstamp, &[("99", "1")], &[("100", "1")]));
+    let mut detector = CrossedMarketDetector::new(CrossedMarketConfig::default());
+    assert_eq!(detector.check(&symbol, &agrregate_book, 1_000).len(), 1);
+
+    assert_eq!(detector.remove_exchange(&"LTCBTC".to_string(), &Exchange::Bitstamp, 1_100), Vec::new());
+    let events = detector.remove_exchange(&symbol, &Exchange::Bitstamp, 1_100);
+    assert_eq!(events.len(), 1);
+    assert_eq!((&events[0].bid_exchange, &events[0].ask_exchange), (&Exchange::Binance, &Exchange::Bitstamp));
+    assert!(!events[0].active);
+    assert_eq!(events[0].timestamp, 1_100);
+
+    // reported again as a new crossing once the exchange is back
+    assert_eq!(detector.remove_exchange(&symbol, &Exchange::Bitstamp, 1_200), Vec::new());
+    let events = detector.check(&symbol, &agrregate_book, 1_300);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].since, 1_300);
+}
+
+#[test]
+fn test_crossed_market_below_min_edge() {
+
+    let symbol = "ETHBTC".to_string();
+    let mut agrregate_book = AggregatedBook::new();
+    agrregate_book.update_book(book(Exchange::Binance, &[("100.1", "1")], &[("102", "1")]));
+    agrregate_book.update_book(book(Exchange::Bitstamp, &[("99", "1")], &[("100", "1")]));
+    agrregate_book.set_taker_fee(Exchange::Bitstamp, Decimal::from_str("50").unwrap());
+
+    // crossed on the quotes, the fees eat the edge
+    let mut detector = CrossedMarketDetector::new(CrossedMarketConfig {
+        min_edge_bps: Some(Decimal::ZERO),
+        persist_ms: 0,
+    });
+    assert_eq!(detector.check(&symbol, &agrregate_book, 1_000), Vec::new());
+
+    // without a minimum edge every crossing is reported, with nothing to execute
+    let mut detector = CrossedMarketDetector::new(CrossedMarketConfig::default());
+    let events = detector.check(&symbol, &agrregate_book, 1_000);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].edge_bps < Decimal::ZERO);
+    assert_eq!(events[0].volume, Decimal::ZERO);
+}
+
+#[tokio::test]
+async fn test_crossed_market_streamed() {
+
+    let (snapshot_tx_ch, snapshot_rx_ch) =  broadcast::channel(10);
+    let mut tasks = JoinSet::new();
+    let settings = BookFeedSettings {
+        symbols: vec!["ETHBTC".to_string()],
+        ..Default::default()
+    };
+    let feed = BookFeed::start(settings, snapshot_rx_ch, CancellationToken::new(), &mut tasks);
+    let (active_crossings, mut crossed_rx_ch) = feed.subscribe_crossed();
+    assert_eq!(active_crossings, Vec::new());
+
+    snapshot_tx_ch.send(book(Exchange::Binance, &[("101", "1")], &[("102", "1")])).unwrap();
+    snapshot_tx_ch.send(book(Exchange::Bitstamp, &[("99", "1")], &[("100", "2")])).unwrap();
+
+    let event = crossed_rx_ch.recv().await.unwrap();
+    assert_eq!(event.bid_exchange, "Binance");
+    assert_eq!(event.ask_exchange, "Bitstamp");
+    assert_eq!(event.volume, 1.0);
+    assert!(event.active);
+
+    // a late subscriber gets the crossings still active
+    let (active_crossings, _) = feed.subscribe_crossed();
+    assert_eq!(active_crossings, vec![event]);
+
+    // removing an exchange ends its crossings
+    feed.remove_book(&Exchange::Bitstamp, "ETHBTC");
+    let event = crossed_rx_ch.recv().await.unwrap();
+    assert_eq!((event.bid_exchange.as_str(), event.ask_exchange.as_str()), ("Binance", "Bitstamp"));
+    assert!(!event.active);
+    let (active_crossings, _) = feed.subscribe_crossed();
+    assert_eq!(active_crossings, Vec::new());
+}
@@ -0,0 +1,71 @@
+use std::time::Duration;
+use crate::*;
+use crate::aggregated_order_book::AggregatedBook;
+use crate::analytics::BookAnalytics;
+use crate::book_deltas::DeltaEncoder;
+use orderbook::{LevelChange, level_change::Action};
+use super::book;
+use pretty_assertions::assert_eq;
+
+fn summary(agrregate_book: &AggregatedBook) -> Summary {
+    set_response_stream("ETHBTC", agrregate_book, 20, &BookAnalytics::default()).unwrap()
+        .view(AggregationMode::ByVenue)
+}
+
+fn actions(changes: &[LevelChange]) -> Vec<(Action, String, String, String)> {
+    changes.iter()
+        .map(|change| {
+            let level = change.level.as_ref().unwrap();
+            (Action::from_i32(change.action).unwrap(), level.exchange.clone(), level.price_decimal.clone(), level.amount_decimal.clone())
+        })
+        .collect()
+}
+
+#[test]
+fn test_delta_encoder_sends_changes_after_snapshot() {
+
+    let mut agrregate_book = AggregatedBook::new();
+    agrregate_book.update_book(book(Exchange::Binance, &[("7", "1"), ("6", "1")], &[("8", "1")]));
+    agrregate_book.update_book(book(Exchange::Bitstamp, &[("7", "2")], &[("9", "1")]));
+    let mut encoder = DeltaEncoder::new(Duration::from_secs(60));
+
+    let first = encoder.encode(summary(&agrregate_book));
+    assert_eq!(first.sequence, 1);
+    assert!(first.snapshot);
+    assert_eq!(first.bids.len(), 3);
+    assert_eq!(first.asks.len(), 2);
+    assert!(first.bids.iter().chain(first.asks.iter()).all(|change| change.action == Action::Insert as i32));
+
+    // Binance drops 6, changes 8 and adds 7.5, Bitstamp is left alone
+    agrregate_book.update_book(book(Exchange::Binance, &[("7", "1")], &[("7.5", "1"), ("8", "3")]));
+    let second = encoder.encode(summary(&agrregate_book));
+    assert_eq!(second.sequence, 2);
+    assert!(!second.snapshot);
+    assert_eq!(actions(&second.bids), vec![
+        (Action::Delete, "Binance".to_string(), "6".to_string(), "1".to_string()),
+    ]);
+    assert_eq!(actions(&second.asks), vec![
+        (Action::Insert, "Binance".to_string(), "7.5".to_string(), "1".to_string()),
+        (Action::Update, "Binance".to_string(), "8".to_string(), "3".to_string()),
+    ]);
+
+    let unchanged = encoder.encode(summary(&agrregate_book));
+    assert_eq!(unchanged.sequence, 3);
+    assert!(unchanged.bids.is_empty() && unchanged.asks.is_empty());
+}
+
+#[test]
+fn test_delta_encoder_refreshes() {
+
+    let mut agrregate_book = AggregatedBook::new();
+    agrregate_book.update_book(book(Exchange::Binance, &[("7", "1")], &[("8", "1")]));
+    let mut encoder = DeltaEncoder::new(Duration::from_secs(0));
+
+    encoder.encode(summary(&agrregate_book));
+    let refresh = encoder.encode(summary(&agrregate_book));
+    assert!(refresh.snapshot);
+    assert_eq!(refresh.sequence, 2);
+    assert_eq!(actions(&refresh.asks), vec![
+        (Action::Insert, "Binance".to_string(), "8".to_string(), "1".to_string()),
+    ]);
+}
@@ -0,0 +1,82 @@
+use std::{
+    collections::BTreeMap,
+    time::Duration
+};
+use crate::*;
+use crate::auth::{Authenticator, AUTHORIZATION};
+use crate::book_feed::{BookFeed, BookFeedSettings};
+use crate::status_feed::StatusFeed;
+use crate::orderbook::{feed_status_event::State, Instrument};
+use tonic::metadata::MetadataValue;
+use super::snapshot;
+use pretty_assertions::assert_eq;
+use tokio::time::timeout;
+
+fn instrument(exchange: &str, symbol: &str, state: State) -> Instrument {
+    Instrument{
+        exchange: exchange.to_string(),
+        symbol: symbol.to_string(),
+        canonical_symbol: canonical_symbol(symbol),
+        state: state as i32,
+        last_update_time: 0,
+    }
+}
+
+#[tokio::test]
+async fn test_list_exchanges_and_instruments() {
+
+    let (snapshot_tx_ch, snapshot_rx_ch) =  broadcast::channel(10);
+    let (status_tx_ch, status_rx_ch) =  broadcast::channel(10);
+    let mut tasks = JoinSet::new();
+    let cancel_token = CancellationToken::new();
+    let feed = BookFeed::start(BookFeedSettings::default(), snapshot_rx_ch, cancel_token.clone(), &mut tasks);
+    let status_feed = StatusFeed::start(status_rx_ch, cancel_token.clone(), &mut tasks);
+    feed.add_book(&Exchange::Bitstamp, "ethbtc");
+    feed.add_book(&Exchange::Binance, "ETHBTC");
+    feed.add_book(&Exchange::Binance, "LTCBTC");
+
+    let mut clients = BTreeMap::new();
+    clients.insert("desk".to_string(), ClientGrant{exchanges: vec!["Binance".to_string()], ..Default::default()});
+    let mut tokens = BTreeMap::new();
+    tokens.insert("desk-token".to_string(), "desk".to_string());
+    let auth = Authenticator::new(&AuthConfig{clients: clients, tokens: tokens, ..Default::default()}).unwrap();
+    let service = OrderbookService::new(feed.clone(), status_feed.clone(), Authenticator::default(), cancel_token.clone());
+    let desk_service = OrderbookService::new(feed.clone(), status_feed.clone(), auth, cancel_token.clone());
+
+    let (_, mut summary_rx_ch) = feed.subscribe("ETHBTC").unwrap();
+    let (_, mut event_rx_ch) = status_feed.subscribe();
+    snapshot_tx_ch.send(snapshot(Exchange::Binance, "ETHBTC")).unwrap();
+    status_tx_ch.send(FeedStatus::new(Exchange::Binance, "ETHBTC".to_string(), FeedState::Synced, None)).unwrap();
+    status_tx_ch.send(FeedStatus::new(Exchange::Bitstamp, "ETHBTC".to_string(), FeedState::Connecting, None)).unwrap();
+    timeout(Duration::from_secs(1), summary_rx_ch.recv()).await.unwrap().unwrap();
+    timeout(Duration::from_secs(1), event_rx_ch.recv()).await.unwrap().unwrap();
+    timeout(Duration::from_secs(1), event_rx_ch.recv()).await.unwrap().unwrap();
+
+    // by canonical symbol then exchange, native symbols kept
+    let mut instruments = service.list_instruments(Request::new(Empty{})).await.unwrap().into_inner().instruments;
+    assert!(instruments[0].last_update_time > 0);
+    instruments[0].last_update_time = 0;
+    assert_eq!(instruments, vec![
+        instrument("Binance", "ETHBTC", State::Synced),
+        instrument("Bitstamp", "ethbtc", State::Connecting),
+        instrument("Binance", "LTCBTC", State::Unknown),
+    ]);
+
+    let exchanges = service.list_exchanges(Request::new(Empty{})).await.unwrap().into_inner().exchanges;
+    assert_eq!(exchanges.iter().map(|exchange| (exchange.exchange.as_str(), exchange.instruments.len())).collect::<Vec<(&str, usize)>>(),
+        vec![("Binance", 2), ("Bitstamp", 1)]);
+    assert_eq!(exchanges[0].instruments.iter().map(|instrument| instrument.canonical_symbol.as_str()).collect::<Vec<&str>>(), vec!["ETHBTC", "LTCBTC"]);
+
+    // only what the client is granted
+    let mut request = Request::new(Empty{});
+    request.metadata_mut().insert(AUTHORIZATION, MetadataValue::from_str("Bearer desk-token").unwrap());
+    let exchanges = desk_service.list_exchanges(request).await.unwrap().into_inner().exchanges;
+    assert_eq!(exchanges.iter().map(|exchange| exchange.exchange.as_str()).collect::<Vec<&str>>(), vec!["Binance"]);
+    assert_eq!(desk_service.list_instruments(Request::new(Empty{})).await.unwrap_err().code(), tonic::Code::Unauthenticated);
+
+    // a removed book is no longer listed
+    feed.remove_book(&Exchange::Bitstamp, "ETHBTC");
+    let instruments = service.list_instruments(Request::new(Empty{})).await.unwrap().into_inner().instruments;
+    assert_eq!(instruments.len(), 2);
+    cancel_token.cancel();
+}
@@ -0,0 +1,383 @@
+use std::{
+    str::FromStr,
+    time::Duration
+};
+use crate::*;
+use crate::book_feed::{BookFeed, BookFeedSettings, SummaryFilter, subscriber_task, conflating_subscriber_task, update_interval};
+use crate::status_feed::StatusFeed;
+use crate::auth::Authenticator;
+use crate::aggregated_order_book::AggregatedBook;
+use rust_decimal::Decimal;
+use futures::StreamExt;
+use super::book;
+use pretty_assertions::assert_eq;
+use tokio::time::timeout;
+
+fn ethbtc_settings() -> BookFeedSettings {
+    BookFeedSettings {
+        symbols: vec!["ETHBTC".to_string()],
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_subscribers_share_one_feed() {
+
+    let (snapshot_tx_ch, snapshot_rx_ch) =  broadcast::channel(10);
+    let mut tasks = JoinSet::new();
+    let feed = BookFeed::start(ethbtc_settings(), snapshot_rx_ch, CancellationToken::new(), &mut tasks);
+
+    let (last_summary, mut first_rx_ch) = feed.subscribe("ETHBTC").unwrap();
+    assert_eq!(last_summary, None);
+
+    snapshot_tx_ch.send(book(Exchange::Binance, &[("7.0", "1")], &[("8.0", "1")])).unwrap();
+    let first_summary = first_rx_ch.recv().await.unwrap();
+    assert_eq!(first_summary.by_venue.spread, 1.0);
+
+    // A late subscriber gets the current state without waiting for an update
+    let (last_summary, mut second_rx_ch) = feed.subscribe("ETHBTC").unwrap();
+    assert_eq!(last_summary, Some(first_summary.clone()));
+
+    snapshot_tx_ch.send(book(Exchange::Bitstamp, &[("7.5", "1")], &[("7.75", "1")])).unwrap();
+    let summary = first_rx_ch.recv().await.unwrap();
+    assert_eq!(summary.by_venue.spread, 0.25);
+    assert_eq!(second_rx_ch.recv().await.unwrap(), summary);
+
+    // numbered per symbol, with the last update of each exchange
+    assert_eq!(first_summary.by_venue.sequence, 1);
+    assert_eq!(summary.by_venue.sequence, 2);
+    assert_eq!(summary.fee_adjusted.sequence, 2);
+    assert!(summary.by_venue.publish_time >= first_summary.by_venue.publish_time);
+    let updates = &summary.by_venue.exchange_updates;
+    assert_eq!(updates.iter().map(|update| update.exchange.as_str()).collect::<Vec<&str>>(), vec!["Binance", "Bitstamp"]);
+    assert!(updates.iter().all(|update| update.sequence == 1833980193 && update.symbol == "ETHBTC" && update.received_time > 0));
+}
+
+#[tokio::test]
+async fn test_cancel_leaves_no_tasks() {
+
+    let (_snapshot_tx_ch, snapshot_rx_ch) =  broadcast::channel(10);
+    let cancel_token = CancellationToken::new();
+    let mut tasks = JoinSet::new();
+    let feed = BookFeed::start(ethbtc_settings(), snapshot_rx_ch, cancel_token.clone(), &mut tasks);
+
+    let (tx, mut rx) = mpsc::channel(4);
+    let (last_summary, summary_rx_ch) = feed.subscribe("ETHBTC").unwrap();
+    tasks.spawn(subscriber_task(last_summary.into_iter().collect(), summary_rx_ch, tx, cancel_token.child_token(), Some));
+    assert_eq!(tasks.len(), 2);
+
+    cancel_token.cancel();
+    let join_all = async { while tasks.join_next().await.is_some() {} };
+    timeout(Duration::from_secs(1), join_all).await.unwrap();
+    assert!(tasks.is_empty());
+    // the client stream is closed once its task is gone
+    assert!(rx.recv().await.is_none());
+}
+
+#[tokio::test]
+async fn test_subscriber_task_ends_when_client_disconnects() {
+
+    let (_snapshot_tx_ch, snapshot_rx_ch) =  broadcast::channel(10);
+    let mut tasks = JoinSet::new();
+    let feed = BookFeed::start(ethbtc_settings(), snapshot_rx_ch, CancellationToken::new(), &mut tasks);
+
+    let (tx, rx) = mpsc::channel(4);
+    let (last_summary, summary_rx_ch) = feed.subscribe("ETHBTC").unwrap();
+    let handle = tokio::spawn(subscriber_task(last_summary.into_iter().collect(), summary_rx_ch, tx, CancellationToken::new(), Some));
+
+    drop(rx);
+    timeout(Duration::from_secs(1), handle).await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn test_conflating_subscriber_sends_the_latest_state() {
+
+    let (update_tx_ch, update_rx_ch) =  broadcast::channel(16);
+    let (tx, mut rx) = mpsc::channel(1);
+    let cancel_token = CancellationToken::new();
+    tokio::spawn(conflating_subscriber_task(Some(0u64), update_rx_ch, tx, cancel_token.clone(), update_interval(0), |update| update));
+
+    // the client reads nothing meanwhile, the feed is not held back and the updates replace each other
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    for update in 1..=10 {
+        update_tx_ch.send(update).unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(rx.recv().await.unwrap().unwrap(), 0);
+    assert_eq!(timeout(Duration::from_secs(1), rx.recv()).await.unwrap().unwrap().unwrap(), 10);
+
+    update_tx_ch.send(11).unwrap();
+    assert_eq!(timeout(Duration::from_secs(1), rx.recv()).await.unwrap().unwrap().unwrap(), 11);
+    cancel_token.cancel();
+}
+
+#[tokio::test]
+async fn test_conflating_subscriber_throttles() {
+
+    assert_eq!(update_interval(10), Duration::from_millis(100));
+    assert_eq!(update_interval(0), Duration::from_secs(0));
+
+    let (update_tx_ch, update_rx_ch) =  broadcast::channel(16);
+    let (tx, mut rx) = mpsc::channel(16);
+    let cancel_token = CancellationToken::new();
+    tokio::spawn(conflating_subscriber_task(None, update_rx_ch, tx, cancel_token.clone(), update_interval(10), |update| update));
+
+    update_tx_ch.send(1u64).unwrap();
+    assert_eq!(timeout(Duration::from_secs(1), rx.recv()).await.unwrap().unwrap().unwrap(), 1);
+    let first_sent = std::time::Instant::now();
+    update_tx_ch.send(2).unwrap();
+    update_tx_ch.send(3).unwrap();
+
+    // at most 10 per second, the intermediate state is dropped
+    assert!(timeout(Duration::from_millis(50), rx.recv()).await.is_err());
+    assert_eq!(timeout(Duration::from_secs(1), rx.recv()).await.unwrap().unwrap().unwrap(), 3);
+    assert!(first_sent.elapsed() >= Duration::from_millis(90));
+    cancel_token.cancel();
+}
+
+#[tokio::test]
+async fn test_subscribe_unknown_symbol() {
+
+    let (_snapshot_tx_ch, snapshot_rx_ch) =  broadcast::channel(10);
+    let mut tasks = JoinSet::new();
+    let feed = BookFeed::start(ethbtc_settings(), snapshot_rx_ch, CancellationToken::new(), &mut tasks);
+
+    assert!(feed.subscribe("ethbtc").is_some());
+    assert!(feed.subscribe("LTCBTC").is_none());
+}
+
+#[test]
+fn test_summary_views_per_mode() {
+
+    let mut agrregate_book = AggregatedBook::new();
+    agrregate_book.update_book(book(Exchange::Binance, &[("7.0", "1")], &[("8.0", "1")]));
+    agrregate_book.update_book(book(Exchange::Bitstamp, &[("7.0", "1")], &[("8.0", "1")]));
+    agrregate_book.set_taker_fee(Exchange::Bitstamp, Decimal::from_str("50").unwrap());
+    let summary = set_response_stream("ETHBTC", &agrregate_book, 20, &BookAnalytics::default()).unwrap();
+
+    let by_venue = summary.view(AggregationMode::ByVenue);
+    assert_eq!(by_venue.asks.len(), 2);
+    assert_eq!(by_venue.spread, 1.0);
+    assert!(by_venue.consolidated_bids.is_empty() && by_venue.consolidated_asks.is_empty());
+
+    let consolidated = summary.view(AggregationMode::Consolidated);
+    assert_eq!(consolidated.consolidated_asks.len(), 1);
+    assert_eq!(consolidated.consolidated_asks[0].amount, 2.0);
+    assert_eq!(consolidated.consolidated_asks[0].exchanges.len(), 2);
+    assert!(consolidated.bids.is_empty() && consolidated.asks.is_empty());
+    assert_eq!(consolidated.spread, by_venue.spread);
+
+    // Binance is charged nothing so it ranks first on both sides, the Bitstamp levels pay 50 bps
+    let fee_adjusted = summary.view(AggregationMode::FeeAdjusted);
+    assert_eq!(fee_adjusted.spread, 1.0);
+    assert_eq!(fee_adjusted.asks.last().unwrap().exchange, "Binance");
+    assert_eq!(fee_adjusted.asks[0].price, 8.04);
+    assert_eq!(fee_adjusted.asks[0].raw_price, 8.0);
+    assert_eq!(fee_adjusted.bids[0].price, 6.965);
+    assert_eq!(fee_adjusted.bids[0].raw_price, 7.0);
+}
+
+#[test]
+fn test_summary_filter_depth_and_exchanges() {
+
+    let mut agrregate_book = AggregatedBook::new();
+    agrregate_book.update_book(book(Exchange::Binance, &[("6.0", "1")], &[("9.0", "1")]));
+    agrregate_book.update_book(book(Exchange::Bitstamp, &[("7.0", "1")], &[("8.0", "1")]));
+    let summary = set_response_stream("ETHBTC", &agrregate_book, 20, &BookAnalytics::default()).unwrap();
+
+    let top = SummaryFilter::new(1, Vec::new()).apply(summary.view(AggregationMode::ByVenue));
+    assert_eq!(top.asks.len(), 1);
+    assert_eq!(top.asks[0].price, 8.0);
+    assert_eq!(top.bids.len(), 1);
+    assert_eq!(top.bids[0].price, 7.0);
+    assert_eq!(top.spread, 1.0);
+
+    let binance = SummaryFilter::new(20, vec![Exchange::Binance]);
+    let by_venue = binance.apply(summary.view(AggregationMode::ByVenue));
+    assert_eq!(by_venue.asks.len(), 1);
+    assert_eq!(by_venue.asks[0].exchange, "Binance");
+    assert_eq!(by_venue.spread, 3.0);
+
+    let consolidated = binance.apply(summary.view(AggregationMode::Consolidated));
+    assert_eq!(consolidated.consolidated_asks.len(), 1);
+    assert_eq!(consolidated.consolidated_asks[0].price, 9.0);
+    assert_eq!(consolidated.spread, 3.0);
+}
+
+#[tokio::test]
+async fn test_book_summary_validates_depth_and_exchanges() {
+
+    let (_snapshot_tx_ch, snapshot_rx_ch) =  broadcast::channel(10);
+    let (_status_tx_ch, status_rx_ch) =  broadcast::channel(10);
+    let mut tasks = JoinSet::new();
+    let cancel_token = CancellationToken::new();
+    let feed = BookFeed::start(ethbtc_settings(), snapshot_rx_ch, cancel_token.clone(), &mut tasks);
+    let status_feed = StatusFeed::start(status_rx_ch, cancel_token.clone(), &mut tasks);
+    let service = OrderbookService::new(feed, status_feed, Authenticator::default(), cancel_token.clone());
+    let request = |depth: u32, exchanges: Vec<String>| Request::new(BookSummaryRequest {
+        symbol: "ETHBTC".to_string(),
+        mode: AggregationMode::ByVenue as i32,
+        depth: depth,
+        exchanges: exchanges,
+        max_update_rate: 0,
+    });
+
+    let max_depth = DepthConfig::default().max_depth as u32;
+    assert!(service.book_summary(request(max_depth, vec!["binance".to_string()])).await.is_ok());
+    let status = service.book_summary(request(max_depth + 1, Vec::new())).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    let status = service.book_summary(request(0, vec!["Kraken".to_string()])).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    cancel_token.cancel();
+}
+
+#[tokio::test]
+async fn test_book_summary_filtered_from_exchange_book() {
+
+    let (snapshot_tx_ch, snapshot_rx_ch) =  broadcast::channel(10);
+    let (_status_tx_ch, status_rx_ch) =  broadcast::channel(10);
+    let mut tasks = JoinSet::new();
+    let cancel_token = CancellationToken::new();
+    let analytics = AnalyticsConfig{metrics: vec![Metric::Mid], ..Default::default()};
+    // the shared summaries hold the best level of each side only, quoted by Bitstamp
+    let settings = BookFeedSettings {
+        analytics: analytics.clone(),
+        depth: DepthConfig{default_depth: 1, max_depth: 1},
+        ..ethbtc_settings()
+    };
+    let feed = BookFeed::start(settings, snapshot_rx_ch, cancel_token.clone(), &mut tasks);
+    let status_feed = StatusFeed::start(status_rx_ch, cancel_token.clone(), &mut tasks);
+    let (_, mut summary_rx_ch) = feed.subscribe("ETHBTC").unwrap();
+    snapshot_tx_ch.send(book(Exchange::Binance, &[("6.0", "1")], &[("10.0", "1")])).unwrap();
+    timeout(Duration::from_secs(1), summary_rx_ch.recv()).await.unwrap().unwrap();
+    snapshot_tx_ch.send(book(Exchange::Bitstamp, &[("7.0", "1")], &[("8.0", "1")])).unwrap();
+    let shared = timeout(Duration::from_secs(1), summary_rx_ch.recv()).await.unwrap().unwrap();
+    assert_eq!(shared.by_venue.bids[0].exchange, "Bitstamp");
+
+    let service = OrderbookService::new(feed, status_feed, Authenticator::default(), cancel_token.clone());
+    let mut stream = service.book_summary(Request::new(BookSummaryRequest {
+        symbol: "ETHBTC".to_string(),
+        mode: AggregationMode::ByVenue as i32,
+        depth: 1,
+        exchanges: vec!["Binance".to_string()],
+        max_update_rate: 0,
+    })).await.unwrap().into_inner();
+    let summary = timeout(Duration::from_secs(1), stream.next()).await.unwrap().unwrap().unwrap();
+
+    // the Binance levels below the Bitstamp ones, with the stats of the Binance book
+    assert_eq!((summary.bids.len(), summary.asks.len()), (1, 1));
+    assert_eq!((summary.bids[0].exchange.as_str(), summary.bids[0].price, summary.asks[0].price), ("Binance", 6.0, 10.0));
+    assert_eq!(summary.spread, 4.0);
+    // numbered by the stream, not by the summaries published before it
+    assert_eq!((summary.sequence, shared.by_venue.sequence), (1, 2));
+    let mut binance_book = AggregatedBook::new();
+    binance_book.update_book(book(Exchange::Binance, &[("6.0", "1")], &[("10.0", "1")]));
+    let binance = set_response_stream("ETHBTC", &binance_book, 1, &BookAnalytics::new(analytics)).unwrap();
+    assert_eq!(summary.stats, binance.by_venue.stats);
+    assert_ne!(summary.stats, shared.by_venue.stats);
+
+    snapshot_tx_ch.send(book(Exchange::Binance, &[("6.5", "1")], &[("10.0", "1")])).unwrap();
+    let summary = timeout(Duration::from_secs(1), stream.next()).await.unwrap().unwrap().unwrap();
+    assert_eq!(summary.sequence, 2);
+    cancel_token.cancel();
+}
+
+#[tokio::test]
+async fn test_filtered_summaries_built_once_per_publish() {
+
+    let (snapshot_tx_ch, snapshot_rx_ch) =  broadcast::channel(10);
+    let mut tasks = JoinSet::new();
+    let cancel_token = CancellationToken::new();
+    let feed = BookFeed::start(ethbtc_settings(), snapshot_rx_ch, cancel_token.clone(), &mut tasks);
+    let binance = SummaryFilter::new(1, vec![Exchange::Binance]);
+    let (_, mut shared_rx_ch) = feed.subscribe("ETHBTC").unwrap();
+    snapshot_tx_ch.send(book(Exchange::Binance, &[("6.0", "1")], &[("10.0", "1")])).unwrap();
+    let published = timeout(Duration::from_secs(1), shared_rx_ch.recv()).await.unwrap().unwrap();
+    assert!(published.filtered.is_empty());
+
+    // the current summary gets the view of the filter, the following ones carry it
+    let ((last_summary, mut summary_rx_ch), subscription) = feed.subscribe_filtered("ETHBTC", &binance).unwrap();
+    let last_summary = last_summary.unwrap();
+    assert_eq!(last_summary.filtered[&binance].by_venue.sequence, published.by_venue.sequence);
+    let other = feed.subscribe_filtered("ETHBTC", &binance).unwrap().1;
+    snapshot_tx_ch.send(book(Exchange::Bitstamp, &[("7.0", "1")], &[("8.0", "1")])).unwrap();
+    let summary = timeout(Duration::from_secs(1), summary_rx_ch.recv()).await.unwrap().unwrap();
+    assert_eq!(summary.filtered.len(), 1);
+    let view = binance.view(&summary, AggregationMode::ByVenue);
+    assert_eq!((view.bids[0].exchange.as_str(), view.spread), ("Binance", 4.0));
+    assert_eq!(view.exchange_updates.len(), 1);
+    assert_eq!(view.sequence, summary.by_venue.sequence);
+
+    // built while one subscriber keeps it
+    drop(subscription);
+    snapshot_tx_ch.send(book(Exchange::Bitstamp, &[("7.0", "1")], &[("8.5", "1")])).unwrap();
+    let summary = timeout(Duration::from_secs(1), summary_rx_ch.recv()).await.unwrap().unwrap();
+    assert_eq!(summary.filtered.len(), 1);
+    drop(other);
+    snapshot_tx_ch.send(book(Exchange::Bitstamp, &[("7.0", "1")], &[("9.0", "1")])).unwrap();
+    let summary = timeout(Duration::from_secs(1), summary_rx_ch.recv()).await.unwrap().unwrap();
+    assert!(summary.filtered.is_empty());
+    cancel_token.cancel();
+}
+
+#[test]
+fn test_summary_exact_decimals() {
+
+    let mut agrregate_book = AggregatedBook::new();
+    agrregate_book.update_book(book(Exchange::Binance, &[("0.00002", "1")], &[("0.000031", "1")]));
+    agrregate_book.update_book(book(Exchange::Bitstamp, &[("0.000019", "1")], &[("0.000031", "1")]));
+    let summary = set_response_stream("SHIBBTC", &agrregate_book, 20, &BookAnalytics::default()).unwrap();
+
+    let by_venue = summary.view(AggregationMode::ByVenue);
+    assert_eq!(by_venue.spread_decimal, "0.000011");
+    assert_eq!(by_venue.bids.last().unwrap().price_decimal, "0.00002");
+    assert_eq!(by_venue.bids.last().unwrap().amount_decimal, "1");
+
+    let consolidated = summary.view(AggregationMode::Consolidated);
+    assert_eq!(consolidated.consolidated_asks[0].price_decimal, "0.000031");
+    assert_eq!(consolidated.consolidated_asks[0].amount_decimal, "2");
+
+    // the spread of the exchanges kept
+    let bitstamp = SummaryFilter::new(20, vec![Exchange::Bitstamp]).apply(by_venue);
+    assert_eq!(bitstamp.spread_decimal, "0.000012");
+}
+
+#[tokio::test]
+async fn test_get_book_from_shared_book() {
+
+    let (snapshot_tx_ch, snapshot_rx_ch) =  broadcast::channel(10);
+    let (_status_tx_ch, status_rx_ch) =  broadcast::channel(10);
+    let mut tasks = JoinSet::new();
+    let cancel_token = CancellationToken::new();
+    let feed = BookFeed::start(ethbtc_settings(), snapshot_rx_ch, cancel_token.clone(), &mut tasks);
+    let status_feed = StatusFeed::start(status_rx_ch, cancel_token.clone(), &mut tasks);
+    let service = OrderbookService::new(feed.clone(), status_feed, Authenticator::default(), cancel_token.clone());
+    let request = |exchanges: Vec<String>| Request::new(BookRequest {
+        symbol: "ethbtc".to_string(),
+        mode: AggregationMode::ByVenue as i32,
+        depth: 1,
+        exchanges: exchanges,
+    });
+
+    let status = service.get_book(request(Vec::new())).await.unwrap_err();
+    assert_eq!(status.code(), tonic::Code::NotFound);
+
+    let (_, mut summary_rx_ch) = feed.subscribe("ETHBTC").unwrap();
+    snapshot_tx_ch.send(book(Exchange::Binance, &[("6.0", "1")], &[("9.0", "1")])).unwrap();
+    snapshot_tx_ch.send(book(Exchange::Bitstamp, &[("7.0", "1")], &[("8.0", "1")])).unwrap();
+    summary_rx_ch.recv().await.unwrap();
+    let published = summary_rx_ch.recv().await.unwrap();
+
+    let summary = service.get_book(request(Vec::new())).await.unwrap().into_inner();
+    assert_eq!(summary.symbol, "ETHBTC");
+    assert_eq!(summary.asks.len(), 1);
+    assert_eq!(summary.spread, 1.0);
+    assert_eq!(summary.sequence, published.by_venue.sequence);
+    assert_eq!(summary.exchange_updates.len(), 2);
+
+    let binance = service.get_book(request(vec!["Binance".to_string()])).await.unwrap().into_inner();
+    assert_eq!(binance.spread, 3.0);
+    assert_eq!(binance.asks[0].exchange, "Binance");
+    assert_eq!(binance.exchange_updates.len(), 1);
+    cancel_token.cancel();
+}
@@ -0,0 +1,215 @@
+use std::{
+    collections::BTreeMap,
+    net::TcpListener,
+    time::Duration
+};
+use crate::*;
+use crate::auth::Authenticator;
+use crate::book_feed::{BookFeed, BookFeedSettings};
+use crate::status_feed::StatusFeed;
+use crate::grpc_web::{GRPC_WEB, GRPC_WEB_PROTO, GRPC_WEB_TEXT, GRPC_WEB_TEXT_PROTO, TRAILERS_FLAG};
+use crate::orderbook::InstrumentList;
+use prost::Message;
+use super::snapshot;
+use pretty_assertions::assert_eq;
+use tokio::time::timeout;
+
+const ORIGIN: &str = "http://127.0.0.1:8080";
+
+/// The OrderbookAggregator streaming ETHBTC from Binance once synced
+async fn orderbook_service(auth: Authenticator, cancel_token: CancellationToken, tasks: &mut JoinSet<()>) -> OrderbookAggregatorServer<OrderbookService> {
+    let (snapshot_tx_ch, snapshot_rx_ch) =  broadcast::channel(10);
+    let (_, status_rx_ch) =  broadcast::channel(10);
+    let feed = BookFeed::start(BookFeedSettings::default(), snapshot_rx_ch, cancel_token.clone(), tasks);
+    let status_feed = StatusFeed::start(status_rx_ch, cancel_token.clone(), tasks);
+    feed.add_book(&Exchange::Binance, "ETHBTC");
+    let (_, mut summary_rx_ch) = feed.subscribe("ETHBTC").unwrap();
+    snapshot_tx_ch.send(snapshot(Exchange::Binance, "ETHBTC")).unwrap();
+    timeout(Duration::from_secs(1), summary_rx_ch.recv()).await.unwrap().unwrap();
+
+    OrderbookAggregatorServer::with_interceptor(
+        OrderbookService::new(feed, status_feed, auth.clone(), cancel_token.clone()), auth.interceptor())
+}
+
+/// Starts the gRPC-Web server in front of the OrderbookAggregator
+async fn grpc_web_server(auth: Authenticator, cancel_token: CancellationToken, tasks: &mut JoinSet<()>) -> String {
+    let service = orderbook_service(auth, cancel_token.clone(), tasks).await;
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    tasks.spawn(async move {
+        grpc_web::serve(listener, service, vec![ORIGIN.to_string()], cancel_token).await.unwrap();
+    });
+    url
+}
+
+/// A message in a gRPC frame
+fn frame(message: &impl Message) -> Vec<u8> {
+    let mut frame = vec![0];
+    frame.extend_from_slice(&(message.encoded_len() as u32).to_be_bytes());
+    message.encode(&mut frame).unwrap();
+    frame
+}
+
+/// The frames of a gRPC-Web body, flag then payload
+fn split_frames(mut body: &[u8]) -> Vec<(u8, Vec<u8>)> {
+    let mut frames = Vec::new();
+    while body.len() >= 5 {
+        let len = u32::from_be_bytes([body[1], body[2], body[3], body[4]]) as usize;
+        frames.push((body[0], body[5..5 + len].to_vec()));
+        body = &body[5 + len..];
+    }
+    frames
+}
+
+/// A body of padded base64 chunks, decoded four characters at a time
+fn decode_text(body: &[u8]) -> Vec<u8> {
+    body.chunks(4).flat_map(|quantum| base64::decode(quantum).unwrap()).collect()
+}
+
+fn call(client: &reqwest::Client, url: &str, method: &str, content_type: &str, body: Vec<u8>) -> reqwest::RequestBuilder {
+    client.post(format!("{}/orderbook.OrderbookAggregator/{}", url, method))
+        .header("content-type", content_type)
+        .header("x-grpc-web", "1")
+        .header("origin", ORIGIN)
+        .body(body)
+}
+
+#[tokio::test]
+async fn test_grpc_web_unary_calls() {
+
+    let mut tasks = JoinSet::new();
+    let cancel_token = CancellationToken::new();
+    let url = grpc_web_server(Authenticator::default(), cancel_token.clone(), &mut tasks).await;
+    let client = reqwest::Client::new();
+
+    let response = call(&client, &url, "ListInstruments", GRPC_WEB_PROTO, frame(&Empty{})).send().await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], GRPC_WEB_PROTO);
+    assert_eq!(response.headers()["access-control-allow-origin"], ORIGIN);
+    let frames = split_frames(&response.bytes().await.unwrap());
+    assert_eq!(frames.len(), 2);
+    let instruments = InstrumentList::decode(&frames[0].1[..]).unwrap().instruments;
+    assert_eq!(instruments.iter().map(|instrument| instrument.canonical_symbol.as_str()).collect::<Vec<&str>>(), vec!["ETHBTC"]);
+    assert_eq!(frames[1], (TRAILERS_FLAG, b"grpc-status:0\r\n".to_vec()));
+
+    // base64 text, each way
+    let request = BookRequest{symbol: "ETHBTC".to_string(), mode: 0, depth: 0, exchanges: Vec::new()};
+    let response = call(&client, &url, "GetBook", GRPC_WEB_TEXT, base64::encode(frame(&request)).into_bytes()).send().await.unwrap();
+    assert_eq!(response.headers()["content-type"], GRPC_WEB_TEXT_PROTO);
+    let frames = split_frames(&decode_text(&response.bytes().await.unwrap()));
+    let summary = Summary::decode(&frames[0].1[..]).unwrap();
+    assert_eq!((summary.symbol.as_str(), summary.bids.len(), summary.asks.len()), ("ETHBTC", 1, 1));
+    assert_eq!(frames[1], (TRAILERS_FLAG, b"grpc-status:0\r\n".to_vec()));
+
+    // a failed call answers its status in the headers
+    let request = BookRequest{symbol: "LTCBTC".to_string(), mode: 0, depth: 0, exchanges: Vec::new()};
+    let response = call(&client, &url, "GetBook", GRPC_WEB, frame(&request)).send().await.unwrap();
+    assert_eq!(response.headers()["grpc-status"], "5");
+    assert!(response.bytes().await.unwrap().is_empty());
+    cancel_token.cancel();
+}
+
+#[tokio::test]
+async fn test_grpc_web_over_tls() {
+
+    let mut tasks = JoinSet::new();
+    let cancel_token = CancellationToken::new();
+    let service = orderbook_service(Authenticator::default(), cancel_token.clone(), &mut tasks).await;
+    let tls_file = |name: &str| format!("{}/../common/test_data/tls/{}", env!("CARGO_MANIFEST_DIR"), name);
+    let tls = Arc::new(ReloadingTls::server(&ServerTlsFiles{
+        cert_path: tls_file("server.pem"),
+        key_path: tls_file("server.key"),
+        client_ca_path: None,
+    }).unwrap());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let serve_token = cancel_token.clone();
+    tasks.spawn(async move {
+        grpc_web::serve_tls(listener, tls, service, vec![ORIGIN.to_string()], serve_token).await.unwrap();
+    });
+
+    let ca = reqwest::Certificate::from_pem(&std::fs::read(tls_file("ca.pem")).unwrap()).unwrap();
+    let client = reqwest::Client::builder().add_root_certificate(ca).resolve("localhost", addr).build().unwrap();
+    let url = format!("https://localhost:{}", addr.port());
+    let response = call(&client, &url, "ListInstruments", GRPC_WEB_PROTO, frame(&Empty{})).send().await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let frames = split_frames(&response.bytes().await.unwrap());
+    assert_eq!(frames[1], (TRAILERS_FLAG, b"grpc-status:0\r\n".to_vec()));
+
+    // no cleartext on the TLS port
+    assert!(call(&reqwest::Client::new(), &format!("http://{}", addr), "ListInstruments", GRPC_WEB_PROTO, frame(&Empty{})).send().await.is_err());
+    cancel_token.cancel();
+}
+
+#[tokio::test]
+async fn test_grpc_web_server_streaming() {
+
+    let mut tasks = JoinSet::new();
+    let cancel_token = CancellationToken::new();
+    let url = grpc_web_server(Authenticator::default(), cancel_token.clone(), &mut tasks).await;
+    let client = reqwest::Client::new();
+
+    let request = BookSummaryRequest{symbol: "ETHBTC".to_string(), mode: 0, depth: 1, exchanges: Vec::new(), max_update_rate: 0};
+    let mut response = call(&client, &url, "BookSummary", GRPC_WEB_PROTO, frame(&request)).send().await.unwrap();
+    let mut body = Vec::new();
+    while split_frames(&body).is_empty() {
+        let chunk = timeout(Duration::from_secs(1), response.chunk()).await.unwrap().unwrap().unwrap();
+        body.extend_from_slice(&chunk);
+    }
+    let frames = split_frames(&body);
+    assert_eq!(frames[0].0, 0);
+    assert_eq!(Summary::decode(&frames[0].1[..]).unwrap().symbol, "ETHBTC");
+
+    // the stream ends with the server, trailers last
+    cancel_token.cancel();
+    while let Some(chunk) = timeout(Duration::from_secs(1), response.chunk()).await.unwrap().unwrap() {
+        body.extend_from_slice(&chunk);
+    }
+    let frames = split_frames(&body);
+    assert_eq!(frames.last().unwrap(), &(TRAILERS_FLAG, b"grpc-status:0\r\n".to_vec()));
+}
+
+#[tokio::test]
+async fn test_grpc_web_cors_and_auth() {
+
+    let mut tasks = JoinSet::new();
+    let cancel_token = CancellationToken::new();
+    let mut clients = BTreeMap::new();
+    clients.insert("web".to_string(), ClientGrant::default());
+    let mut tokens = BTreeMap::new();
+    tokens.insert("web-token".to_string(), "web".to_string());
+    let auth = Authenticator::new(&AuthConfig{clients: clients, tokens: tokens, ..Default::default()}).unwrap();
+    let url = grpc_web_server(auth, cancel_token.clone(), &mut tasks).await;
+    let client = reqwest::Client::new();
+    let method_url = format!("{}/orderbook.OrderbookAggregator/ListInstruments", url);
+
+    let preflight = client.request(reqwest::Method::OPTIONS, &method_url)
+        .header("origin", ORIGIN)
+        .header("access-control-request-method", "POST")
+        .header("access-control-request-headers", "content-type,x-grpc-web,authorization,x-custom")
+        .send().await.unwrap();
+    assert_eq!(preflight.status(), reqwest::StatusCode::NO_CONTENT);
+    assert_eq!(preflight.headers()["access-control-allow-origin"], ORIGIN);
+    assert_eq!(preflight.headers()["access-control-allow-methods"], "POST, OPTIONS");
+    // only the headers allowed, not the ones asked for
+    assert_eq!(preflight.headers()["access-control-allow-headers"], "content-type, x-grpc-web, x-user-agent, authorization, grpc-timeout");
+
+    let preflight = client.request(reqwest::Method::OPTIONS, &method_url)
+        .header("origin", "http://elsewhere.example")
+        .send().await.unwrap();
+    assert_eq!(preflight.status(), reqwest::StatusCode::FORBIDDEN);
+
+    // the bearer token is checked as on the gRPC port
+    let response = call(&client, &url, "ListInstruments", GRPC_WEB_PROTO, frame(&Empty{})).send().await.unwrap();
+    assert_eq!(response.headers()["grpc-status"], "16");
+    assert_eq!(response.headers()["access-control-expose-headers"], "grpc-status, grpc-message, grpc-status-details-bin");
+    let response = call(&client, &url, "ListInstruments", GRPC_WEB_PROTO, frame(&Empty{}))
+        .header("authorization", "Bearer web-token")
+        .send().await.unwrap();
+    let frames = split_frames(&response.bytes().await.unwrap());
+    assert_eq!(frames.last().unwrap(), &(TRAILERS_FLAG, b"grpc-status:0\r\n".to_vec()));
+
+    let response = client.post(&method_url).header("content-type", "application/json").send().await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    cancel_token.cancel();
+}
@@ -0,0 +1,55 @@
+use crate::*;
+use crate::health::{HealthService, ADMIN_SERVICE, ORDERBOOK_SERVICE, REFLECTION_SERVICE, SERVER};
+use crate::health::proto::{HealthCheckRequest, HealthCheckResponse, health_check_response::ServingStatus, health_server::Health};
+use tokio_stream::StreamExt;
+use pretty_assertions::assert_eq;
+
+fn request(service: &str) -> Request<HealthCheckRequest> {
+    Request::new(HealthCheckRequest{service: service.to_string()})
+}
+
+#[tokio::test]
+async fn test_health_serving_once_a_book_is_synced() {
+
+    let (status_tx_ch, status_rx_ch) =  broadcast::channel(10);
+    let mut tasks = JoinSet::new();
+    let cancel_token = CancellationToken::new();
+    let status_feed = StatusFeed::start(status_rx_ch, cancel_token.clone(), &mut tasks);
+    let health = HealthService::start(&status_feed, false, cancel_token.clone(), &mut tasks);
+
+    let status = |response: Response<HealthCheckResponse>| response.into_inner().status;
+    assert_eq!(status(health.check(request(SERVER)).await.unwrap()), ServingStatus::NotServing as i32);
+    assert_eq!(status(health.check(request(ORDERBOOK_SERVICE)).await.unwrap()), ServingStatus::NotServing as i32);
+    assert_eq!(status(health.check(request(REFLECTION_SERVICE)).await.unwrap()), ServingStatus::Serving as i32);
+    assert_eq!(health.check(request("orderbook.Unknown")).await.unwrap_err().code(), tonic::Code::NotFound);
+    // not served without admin clients
+    assert_eq!(health.check(request(ADMIN_SERVICE)).await.unwrap_err().code(), tonic::Code::NotFound);
+
+    let mut watch_stream = health.watch(request(ORDERBOOK_SERVICE)).await.unwrap().into_inner();
+    assert_eq!(watch_stream.next().await.unwrap().unwrap().status, ServingStatus::NotServing as i32);
+
+    status_tx_ch.send(FeedStatus::new(Exchange::Binance, "ETHBTC".to_string(), FeedState::Connecting, None)).unwrap();
+    status_tx_ch.send(FeedStatus::new(Exchange::Binance, "ETHBTC".to_string(), FeedState::Synced, None)).unwrap();
+    assert_eq!(watch_stream.next().await.unwrap().unwrap().status, ServingStatus::Serving as i32);
+    assert_eq!(status(health.check(request(SERVER)).await.unwrap()), ServingStatus::Serving as i32);
+
+    // not serving again once no book is synced
+    status_tx_ch.send(FeedStatus::new(Exchange::Binance, "ETHBTC".to_string(), FeedState::Stale, None)).unwrap();
+    assert_eq!(watch_stream.next().await.unwrap().unwrap().status, ServingStatus::NotServing as i32);
+
+    let mut unknown_stream = health.watch(request("orderbook.Unknown")).await.unwrap().into_inner();
+    assert_eq!(unknown_stream.next().await.unwrap().unwrap().status, ServingStatus::ServiceUnknown as i32);
+    cancel_token.cancel();
+}
+
+#[tokio::test]
+async fn test_health_admin_serving_when_enabled() {
+
+    let (_status_tx_ch, status_rx_ch) =  broadcast::channel(10);
+    let mut tasks = JoinSet::new();
+    let cancel_token = CancellationToken::new();
+    let status_feed = StatusFeed::start(status_rx_ch, cancel_token.clone(), &mut tasks);
+    let health = HealthService::start(&status_feed, true, cancel_token.clone(), &mut tasks);
+    assert_eq!(health.check(request(ADMIN_SERVICE)).await.unwrap().into_inner().status, ServingStatus::Serving as i32);
+    cancel_token.cancel();
+}
@@ -0,0 +1,67 @@
+use std::{
+    collections::BTreeMap,
+    net::TcpListener,
+    time::Duration
+};
+use crate::*;
+use crate::auth::{Authenticator, AUTHORIZATION};
+use crate::book_feed::{BookFeed, BookFeedSettings};
+use tonic::metadata::MetadataValue;
+use super::snapshot;
+use pretty_assertions::assert_eq;
+use tokio::time::timeout;
+
+/// The line of `metric` in the scraped text, metrics are global so other tests may count too
+fn sample<'a>(body: &'a str, metric: &str) -> Option<&'a str> {
+    body.lines().find(|line| line.starts_with(metric))
+}
+
+#[tokio::test]
+async fn test_metrics_endpoint() {
+
+    let (snapshot_tx_ch, snapshot_rx_ch) =  broadcast::channel(10);
+    let mut tasks = JoinSet::new();
+    let cancel_token = CancellationToken::new();
+    let feed = BookFeed::start(BookFeedSettings::default(), snapshot_rx_ch, cancel_token.clone(), &mut tasks);
+    feed.add_book(&Exchange::Binance, "METRICSBTC");
+    let (_, mut summary_rx_ch) = feed.subscribe("METRICSBTC").unwrap();
+    snapshot_tx_ch.send(snapshot(Exchange::Binance, "METRICSBTC")).unwrap();
+    timeout(Duration::from_secs(1), summary_rx_ch.recv()).await.unwrap().unwrap();
+    let mut clients = BTreeMap::new();
+    clients.insert("metrics-test".to_string(), ClientGrant::default());
+    let mut tokens = BTreeMap::new();
+    tokens.insert("metrics-token".to_string(), "metrics-test".to_string());
+    let auth = Authenticator::new(&AuthConfig{clients: clients, tokens: tokens, ..Default::default()}).unwrap();
+    let mut request = Request::new(());
+    request.metadata_mut().insert(AUTHORIZATION, MetadataValue::from_str("Bearer metrics-token").unwrap());
+    let permit = auth.open_stream(&auth.grant(request.metadata()).unwrap()).unwrap();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}{}", listener.local_addr().unwrap(), metrics_server::METRICS_PATH);
+    let server = tokio::spawn(metrics_server::serve(listener, feed.clone(), cancel_token.clone()));
+
+    let response = reqwest::get(&url).await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    assert!(response.headers()["content-type"].to_str().unwrap().starts_with("text/plain"));
+    let body = response.text().await.unwrap();
+    assert_eq!(sample(&body, "orderbook_summaries_published_total{symbol=\"METRICSBTC\"}"),
+        Some("orderbook_summaries_published_total{symbol=\"METRICSBTC\"} 1"));
+    assert_eq!(sample(&body, "orderbook_grpc_subscribers{client=\"metrics-test\"}"),
+        Some("orderbook_grpc_subscribers{client=\"metrics-test\"} 1"));
+    assert!(sample(&body, "orderbook_feed_age_seconds{exchange=\"Binance\",symbol=\"METRICSBTC\"}").is_some());
+
+    // a closed stream is no longer counted and a removed book has no age
+    drop(permit);
+    feed.remove_book(&Exchange::Binance, "METRICSBTC");
+    let body = reqwest::get(&url).await.unwrap().text().await.unwrap();
+    assert_eq!(sample(&body, "orderbook_grpc_subscribers{client=\"metrics-test\"}"),
+        Some("orderbook_grpc_subscribers{client=\"metrics-test\"} 0"));
+    assert!(sample(&body, "orderbook_feed_age_seconds{exchange=\"Binance\",symbol=\"METRICSBTC\"}").is_none());
+
+    let response = reqwest::get(&url.replace(metrics_server::METRICS_PATH, "/other")).await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+
+    // the server stops with the others
+    cancel_token.cancel();
+    timeout(Duration::from_secs(1), server).await.unwrap().unwrap().unwrap();
+}
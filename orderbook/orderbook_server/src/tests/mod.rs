@@ -1 +1,48 @@
-pub mod book_tests;
\ No newline at end of file
+pub mod book_tests;
+pub mod feed_tests;
+pub mod status_tests;
+pub mod synthetic_tests;
+pub mod crossed_tests;
+pub mod quote_cost_tests;
+pub mod analytics_tests;
+pub mod delta_tests;
+pub mod health_tests;
+pub mod reflection_tests;
+pub mod tls_tests;
+pub mod auth_tests;
+pub mod admin_tests;
+pub mod discovery_tests;
+pub mod metrics_tests;
+pub mod grpc_web_tests;
+
+
+use std::{
+    str::FromStr,
+    collections::BTreeMap
+};
+use crate::*;
+use rust_decimal::Decimal;
+
+/// Snapshot of `exchange` for `symbol` holding the (price, volume) levels of each side
+pub fn leg(exchange: Exchange, symbol: &str, bids: &[(&str, &str)], asks: &[(&str, &str)]) -> SnapshotData {
+    let to_ladder = |levels: &[(&str, &str)]| levels.iter()
+        .map(|(price, volume)| (Decimal::from_str(price).unwrap(), Decimal::from_str(volume).unwrap()))
+        .collect::<BTreeMap<Price, Volume>>();
+    SnapshotData {
+        exchange: exchange,
+        symbol: symbol.to_string(),
+        timestamp: 1833980193,
+        bid_to_update: to_ladder(bids),
+        ask_to_update: to_ladder(asks)
+    }
+}
+
+/// Snapshot of `exchange` for ETHBTC
+pub fn book(exchange: Exchange, bids: &[(&str, &str)], asks: &[(&str, &str)]) -> SnapshotData {
+    leg(exchange, "ETHBTC", bids, asks)
+}
+
+/// Snapshot of `exchange` for `symbol` quoting 1 at 7.0 and 1 at 8.0
+pub fn snapshot(exchange: Exchange, symbol: &str) -> SnapshotData {
+    leg(exchange, symbol, &[("7.0", "1")], &[("8.0", "1")])
+}
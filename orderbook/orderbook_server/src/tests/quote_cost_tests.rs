@@ -0,0 +1,66 @@
+use std::str::FromStr;
+use crate::*;
+use crate::aggregated_order_book::AggregatedBook;
+use crate::quote_cost::{Fill, OrderSize, Side};
+use rust_decimal::Decimal;
+use super::book;
+use pretty_assertions::assert_eq;
+
+fn aggregated_book() -> AggregatedBook {
+    let mut agrregate_book = AggregatedBook::new();
+    agrregate_book.update_book(book(Exchange::Binance, &[("99", "1")], &[("101", "1"), ("102", "2")]));
+    agrregate_book.update_book(book(Exchange::Bitstamp, &[("98", "2")], &[("101", "0.5"), ("103", "1")]));
+    agrregate_book
+}
+
+#[test]
+fn test_quote_cost_buy_base() {
+
+    let quote_cost = aggregated_book()
+        .quote_cost(Side::Buy, OrderSize::Base(Decimal::from_str("2").unwrap()))
+        .unwrap();
+
+    // 1 at 101 and 0.5 at 101 then 0.5 at 102
+    assert_eq!(quote_cost.volume, Decimal::from_str("2").unwrap());
+    assert_eq!(quote_cost.notional, Decimal::from_str("202.5").unwrap());
+    assert_eq!(quote_cost.vwap, Decimal::from_str("101.25").unwrap());
+    assert_eq!(quote_cost.worst_price, Decimal::from_str("102").unwrap());
+    assert_eq!(quote_cost.mid, Decimal::from_str("100").unwrap());
+    assert_eq!(quote_cost.slippage_bps, Decimal::from_str("125").unwrap());
+    assert_eq!(quote_cost.fills, vec![
+        Fill{exchange: Exchange::Binance, volume: Decimal::from_str("1.5").unwrap(), notional: Decimal::from_str("152").unwrap()},
+        Fill{exchange: Exchange::Bitstamp, volume: Decimal::from_str("0.5").unwrap(), notional: Decimal::from_str("50.5").unwrap()},
+    ]);
+    assert!(quote_cost.complete);
+}
+
+#[test]
+fn test_quote_cost_sell_quote_past_the_book() {
+
+    let quote_cost = aggregated_book()
+        .quote_cost(Side::Sell, OrderSize::Quote(Decimal::from_str("500").unwrap()))
+        .unwrap();
+
+    // the whole bid side only makes 295
+    assert_eq!(quote_cost.volume, Decimal::from_str("3").unwrap());
+    assert_eq!(quote_cost.notional, Decimal::from_str("295").unwrap());
+    assert_eq!(quote_cost.worst_price, Decimal::from_str("98").unwrap());
+    assert!(quote_cost.slippage_bps > Decimal::ZERO);
+    assert_eq!(quote_cost.fills.len(), 2);
+    assert!(!quote_cost.complete);
+
+    let quote_cost = aggregated_book()
+        .quote_cost(Side::Sell, OrderSize::Quote(Decimal::from_str("49.5").unwrap()))
+        .unwrap();
+    assert_eq!(quote_cost.volume, Decimal::from_str("0.5").unwrap());
+    assert_eq!(quote_cost.vwap, Decimal::from_str("99").unwrap());
+    assert!(quote_cost.complete);
+}
+
+#[test]
+fn test_quote_cost_one_sided_book() {
+
+    let mut agrregate_book = AggregatedBook::new();
+    agrregate_book.update_book(book(Exchange::Binance, &[], &[("101", "1")]));
+    assert_eq!(agrregate_book.quote_cost(Side::Buy, OrderSize::Base(Decimal::ONE)), None);
+}
@@ -0,0 +1,50 @@
+use crate::reflection::{ReflectionService, FILE_DESCRIPTOR_SET};
+use crate::reflection::proto::{ServerReflectionRequest, server_reflection_request::MessageRequest, server_reflection_response::MessageResponse};
+use tokio_util::sync::CancellationToken;
+use prost::Message;
+use prost_types::FileDescriptorProto;
+use pretty_assertions::assert_eq;
+
+fn respond(service: &ReflectionService, message_request: MessageRequest) -> MessageResponse {
+    let request = ServerReflectionRequest{host: String::new(), message_request: Some(message_request)};
+    service.respond(request).message_response.unwrap()
+}
+
+/// Names of the files of a file descriptor response
+fn file_names(response: MessageResponse) -> Vec<String> {
+    match response {
+        MessageResponse::FileDescriptorResponse(response) => response.file_descriptor_proto.iter()
+            .map(|file| FileDescriptorProto::decode(file.as_slice()).unwrap().name().to_string())
+            .collect(),
+        response => panic!("unexpected response {:?}", response)
+    }
+}
+
+#[test]
+fn test_reflection_lists_services() {
+
+    let service = ReflectionService::new(FILE_DESCRIPTOR_SET, CancellationToken::new()).unwrap();
+    let services = match respond(&service, MessageRequest::ListServices(String::new())) {
+        MessageResponse::ListServicesResponse(response) => response.service.into_iter().map(|service| service.name).collect::<Vec<_>>(),
+        response => panic!("unexpected response {:?}", response)
+    };
+    assert_eq!(services, vec!["orderbook.OrderbookAggregator", "orderbook.Admin", "grpc.health.v1.Health", "grpc.reflection.v1alpha.ServerReflection"]);
+}
+
+#[test]
+fn test_reflection_files_by_symbol_and_name() {
+
+    let service = ReflectionService::new(FILE_DESCRIPTOR_SET, CancellationToken::new()).unwrap();
+    let orderbook_files = vec!["orderbook.proto", "google/protobuf/wrappers.proto"];
+
+    assert_eq!(file_names(respond(&service, MessageRequest::FileContainingSymbol("orderbook.OrderbookAggregator".to_string()))), orderbook_files);
+    assert_eq!(file_names(respond(&service, MessageRequest::FileContainingSymbol("orderbook.OrderbookAggregator.GetBook".to_string()))), orderbook_files);
+    assert_eq!(file_names(respond(&service, MessageRequest::FileContainingSymbol("orderbook.LevelChange.Action".to_string()))), orderbook_files);
+    assert_eq!(file_names(respond(&service, MessageRequest::FileContainingSymbol("grpc.health.v1.HealthCheckResponse".to_string()))), vec!["health.proto"]);
+    assert_eq!(file_names(respond(&service, MessageRequest::FileByFilename("orderbook.proto".to_string()))), orderbook_files);
+
+    match respond(&service, MessageRequest::FileContainingSymbol("orderbook.Unknown".to_string())) {
+        MessageResponse::ErrorResponse(error) => assert_eq!(error.error_code, tonic::Code::NotFound as i32),
+        response => panic!("unexpected response {:?}", response)
+    }
+}
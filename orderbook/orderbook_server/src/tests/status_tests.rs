@@ -0,0 +1,29 @@
+use crate::*;
+use crate::status_feed::StatusFeed;
+use crate::orderbook::feed_status_event::State;
+use pretty_assertions::assert_eq;
+
+#[tokio::test]
+async fn test_status_feed_keeps_latest_status_per_feed() {
+
+    let (status_tx_ch, status_rx_ch) =  broadcast::channel(10);
+    let mut tasks = JoinSet::new();
+    let status_feed = StatusFeed::start(status_rx_ch, CancellationToken::new(), &mut tasks);
+    let (_, mut update_rx_ch) = status_feed.subscribe();
+
+    status_tx_ch.send(FeedStatus::new(Exchange::Binance, "ETHBTC".to_string(), FeedState::Connecting, None)).unwrap();
+    status_tx_ch.send(FeedStatus::new(Exchange::Bitstamp, "ETHBTC".to_string(), FeedState::Synced, None)).unwrap();
+    status_tx_ch.send(FeedStatus::new(Exchange::Binance, "ETHBTC".to_string(), FeedState::Resyncing, Some("sequence gap".to_string()))).unwrap();
+
+    for _ in 0..3 {
+        update_rx_ch.recv().await.unwrap();
+    }
+    let (current_state, _) = status_feed.subscribe();
+
+    assert_eq!(current_state.len(), 2);
+    assert_eq!(current_state[0].exchange, "Binance");
+    assert_eq!(current_state[0].state, State::Resyncing as i32);
+    assert_eq!(current_state[0].reason, "sequence gap");
+    assert_eq!(current_state[1].exchange, "Bitstamp");
+    assert_eq!(current_state[1].state, State::Synced as i32);
+}
@@ -0,0 +1,83 @@
+use std::str::FromStr;
+use crate::*;
+use crate::aggregated_order_book::AggregatedBook;
+use crate::book_feed::{BookFeed, BookFeedSettings};
+use crate::synthetic_book::{SyntheticBook, SyntheticLevel};
+use rust_decimal::Decimal;
+use super::leg;
+use pretty_assertions::assert_eq;
+
+fn ethbtc() -> SyntheticBook {
+    SyntheticBook::new(&SyntheticConfig {
+        symbol: "ethbtc.syn".to_string(),
+        base_leg: "ethusd".to_string(),
+        quote_leg: "btcusd".to_string(),
+    })
+}
+
+#[test]
+fn test_synthetic_depth_limited_by_thinner_leg() {
+
+    let mut base = AggregatedBook::new();
+    base.update_book(leg(Exchange::Binance, "ETHUSD", &[], &[("2000", "1"), ("2010", "2")]));
+    let mut quote = AggregatedBook::new();
+    quote.update_book(leg(Exchange::Bitstamp, "BTCUSD", &[("40000", "0.025"), ("39900", "1")], &[]));
+
+    let binance = Exchange::Binance;
+    let bitstamp = Exchange::Bitstamp;
+    let price = |base: &str, quote: &str| Decimal::from_str(base).unwrap() / Decimal::from_str(quote).unwrap();
+    // 1000 USD of BTC at 40000 only buys half of the first ETH level,
+    // the other half goes against the next BTC level
+    let asks_expected = vec![
+        SyntheticLevel {
+            base_exchange: binance.clone(),
+            quote_exchange: bitstamp.clone(),
+            price: price("2000", "40000"),
+            volume: Decimal::from_str("0.5").unwrap(),
+            raw_price: price("2000", "40000")
+        },
+        SyntheticLevel {
+            base_exchange: binance.clone(),
+            quote_exchange: bitstamp.clone(),
+            price: price("2000", "39900"),
+            volume: Decimal::from_str("0.5").unwrap(),
+            raw_price: price("2000", "39900")
+        },
+        SyntheticLevel {
+            base_exchange: binance.clone(),
+            quote_exchange: bitstamp.clone(),
+            price: price("2010", "39900"),
+            volume: Decimal::from_str("2").unwrap(),
+            raw_price: price("2010", "39900")
+        },
+    ];
+
+    assert_eq!(asks_expected, ethbtc().get_top_asks(&base, &quote, 20));
+    assert_eq!(Vec::<SyntheticLevel>::new(), ethbtc().get_top_bids(&base, &quote, 20));
+}
+
+#[tokio::test]
+async fn test_synthetic_published_on_leg_updates() {
+
+    let (snapshot_tx_ch, snapshot_rx_ch) =  broadcast::channel(10);
+    let mut tasks = JoinSet::new();
+    let settings = BookFeedSettings {
+        symbols: vec!["ETHUSD".to_string(), "BTCUSD".to_string()],
+        synthetics: vec![ethbtc()],
+        ..Default::default()
+    };
+    let feed = BookFeed::start(settings, snapshot_rx_ch, CancellationToken::new(), &mut tasks);
+    let (last_summary, mut summary_rx_ch) = feed.subscribe("ETHBTC.SYN").unwrap();
+    assert_eq!(last_summary, None);
+
+    snapshot_tx_ch.send(leg(Exchange::Binance, "ETHUSD", &[("1990", "1")], &[("2000", "1")])).unwrap();
+    snapshot_tx_ch.send(leg(Exchange::Bitstamp, "BTCUSD", &[("40000", "1")], &[("40100", "1")])).unwrap();
+
+    let summary = summary_rx_ch.recv().await.unwrap().view(AggregationMode::ByVenue);
+    assert_eq!(summary.symbol, "ETHBTC.SYN");
+    assert!(summary.synthetic);
+    assert_eq!(summary.legs, vec!["ETHUSD".to_string(), "BTCUSD".to_string()]);
+    assert_eq!(summary.asks[0].exchange, "Binance/Bitstamp");
+    assert_eq!(summary.asks[0].price, 0.05);
+    assert_eq!(summary.asks[0].amount, 1.0);
+}
@@ -0,0 +1,55 @@
+use crate::*;
+use crate::health::{HealthService, ORDERBOOK_SERVICE};
+use crate::health::proto::{HealthCheckRequest, health_client::HealthClient, health_check_response::ServingStatus};
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Identity};
+use pretty_assertions::assert_eq;
+
+/// Test certificates of the common crate
+fn tls_file(name: &str) -> String {
+    format!("{}/../common/test_data/tls/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+async fn health_check(port: u16, identity: Option<&str>) -> Result<i32, Status> {
+    let mut tls = ClientTlsConfig::new()
+        .ca_certificate(Certificate::from_pem(std::fs::read(tls_file("ca.pem")).unwrap()))
+        .domain_name("localhost");
+    if let Some(name) = identity {
+        let cert = std::fs::read(tls_file(&format!("{}.pem", name))).unwrap();
+        let key = std::fs::read(tls_file(&format!("{}.key", name))).unwrap();
+        tls = tls.identity(Identity::from_pem(cert, key));
+    }
+    let channel = Channel::from_shared(format!("https://127.0.0.1:{}", port)).unwrap()
+        .tls_config(tls).unwrap()
+        .connect().await
+        .map_err(|err| Status::unavailable(err.to_string()))?;
+    let response = HealthClient::new(channel)
+        .check(Request::new(HealthCheckRequest{service: ORDERBOOK_SERVICE.to_string()})).await?;
+    Ok(response.into_inner().status)
+}
+
+#[tokio::test]
+async fn test_grpc_over_mutual_tls() {
+
+    let (_status_tx_ch, status_rx_ch) =  broadcast::channel(10);
+    let mut tasks = JoinSet::new();
+    let cancel_token = CancellationToken::new();
+    let status_feed = StatusFeed::start(status_rx_ch, cancel_token.clone(), &mut tasks);
+    let health_service = HealthService::start(&status_feed, false, cancel_token.clone(), &mut tasks);
+
+    let tls = Arc::new(ReloadingTls::server(&ServerTlsFiles{
+        cert_path: tls_file("server.pem"),
+        key_path: tls_file("server.key"),
+        client_ca_path: Some(tls_file("ca.pem")),
+    }).unwrap());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let shutdown_token = cancel_token.clone();
+    tokio::spawn(Server::builder()
+        .add_service(HealthServer::new(health_service))
+        .serve_with_incoming_shutdown(tls_incoming(listener, tls, cancel_token.clone()), async move { shutdown_token.cancelled().await }));
+
+    assert_eq!(health_check(port, Some("client")).await.unwrap(), ServingStatus::NotServing as i32);
+    assert!(health_check(port, None).await.is_err());
+    assert!(health_check(port, Some("other_client")).await.is_err());
+    cancel_token.cancel();
+}
@@ -0,0 +1,51 @@
+use std::{
+    sync::Arc,
+    time::Duration
+};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::mpsc
+};
+use tokio_util::sync::CancellationToken;
+use tokio_stream::wrappers::ReceiverStream;
+use common::{ReloadingTls, tokio_rustls::{rustls::ServerConfig, server::TlsStream, TlsAcceptor}};
+
+/// A client not done with the handshake by then is dropped
+pub const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Connections accepted on `listener` once their TLS handshake succeeded, each handshake
+/// uses the server config current when the client connected, so renewed certificates
+/// apply to the following connections without a restart
+pub fn tls_incoming(listener: TcpListener, tls: Arc<ReloadingTls<ServerConfig>>, cancel_token: CancellationToken)
+    -> ReceiverStream<Result<TlsStream<TcpStream>, std::io::Error>> {
+    let (tx, rx) = mpsc::channel(16);
+    tokio::spawn(async move {
+        let task_name = "--TLS Accept Task--";
+        loop {
+            let val = tokio::select! {
+                _ = cancel_token.cancelled() => break,
+                val = listener.accept() => val
+            };
+            let (stream, peer) = match val {
+                Ok(val) => val,
+                Err(err) => {
+                    log::warn!("Warning in {:?}:\n{:?}", task_name, err);
+                    continue;
+                }
+            };
+            let acceptor = TlsAcceptor::from(tls.get());
+            let tx = tx.clone();
+            // a slow handshake does not hold the other clients
+            tokio::spawn(async move {
+                match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                    Ok(Ok(stream)) => {
+                        let _ = tx.send(Ok(stream)).await;
+                    },
+                    Ok(Err(err)) => log::warn!("Warning in {:?}:\nTLS handshake with {} failed: {:?}", task_name, peer, err),
+                    Err(_) => log::warn!("Warning in {:?}:\nTLS handshake with {} timed out", task_name, peer)
+                }
+            });
+        }
+    });
+    ReceiverStream::new(rx)
+}
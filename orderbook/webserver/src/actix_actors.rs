@@ -3,6 +3,7 @@ use actix::prelude::*;
 use actix_web_actors::ws;
 use actix_web::Result;
 use actix_broker::{BrokerIssue, BrokerSubscribe, SystemBroker};
+use common::metrics;
 use crate::orderbook::Summary;
 use actix::Message as ActixMessage;
 // use serde::{Serialize, Deserialize};
@@ -47,6 +48,8 @@ pub struct WsSession  {
     /// Client must send ping at least once per 10 seconds (CLIENT_TIMEOUT),
     /// otherwise we drop connection.
     hb: Instant,
+    /// Subscription of this session alone, the shared feed when none
+    summary_stream: Option<tonic::Streaming<Summary>>,
 }
 
 impl Actor for WsSession  {
@@ -55,9 +58,17 @@ impl Actor for WsSession  {
     /// Method is called on actor start. We start the heartbeat process here.
     fn started(&mut self, ctx: &mut Self::Context) {
         self.hb(ctx);
-        self.subscribe_async::<SystemBroker, OutputData>(ctx);
-
+        match self.summary_stream.take() {
+            Some(summary_stream) => {
+                ctx.add_stream(summary_stream);
+            },
+            None => self.subscribe_async::<SystemBroker, OutputData>(ctx)
+        }
+        metrics::WEBSOCKET_SESSIONS.inc();
+    }
 
+    fn stopped(&mut self, _ctx: &mut Self::Context) {
+        metrics::WEBSOCKET_SESSIONS.dec();
     }
 }
 
@@ -70,6 +81,27 @@ impl Handler<OutputData> for WsSession {
         ctx.text(json);
     }
 }
+/// Handler for the summaries of the session's own subscription
+impl StreamHandler<Result<Summary, tonic::Status>> for WsSession {
+    fn handle(&mut self, item: Result<Summary, tonic::Status>, ctx: &mut Self::Context) {
+        match item {
+            Ok(summary) => {
+                let json = serde_json::to_string(&summary).unwrap_or("#r{}".to_owned());
+                ctx.text(json);
+            },
+            Err(status) => {
+                log::warn!("Warning in WsSession:\nsummary stream failed:\n{:?}", status);
+                ctx.close(None);
+                ctx.stop();
+            }
+        }
+    }
+
+    fn finished(&mut self, ctx: &mut Self::Context) {
+        ctx.stop();
+    }
+}
+
 /// Handler for `ws::Message`
 impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsSession  {
     fn handle(
@@ -108,7 +140,11 @@ impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for WsSession  {
 
 impl WsSession  {
     pub fn new() -> Self {
-        Self { hb: Instant::now() }
+        Self { hb: Instant::now(), summary_stream: None }
+    }
+
+    pub fn with_summary_stream(summary_stream: tonic::Streaming<Summary>) -> Self {
+        Self { hb: Instant::now(), summary_stream: Some(summary_stream) }
     }
 
     /// helper method that sends ping to client every second.
@@ -7,6 +7,8 @@
       unused_qualifications,
       warnings
   )]
+// fields are named in full, `field: field`, across the crates
+#![allow(clippy::redundant_field_names)]
 mod actix_actors;
 mod orderbook;
 
@@ -21,16 +23,30 @@ use anyhow::Result;
 use actix_web::{web, App, HttpRequest, Error, HttpResponse, HttpServer, Result as ActixResult, middleware::Logger};
 use actix_actors::{FeedActor, WsSession, OutputData};
 use log::warn;
-use std::sync::Once;
-use tonic::Request;
-use orderbook::{Summary, Empty};
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    future::Future,
+    sync::{Arc, Once, RwLock},
+    time::Duration
+};
+use tonic::{metadata::MetadataValue, transport::{Channel, Endpoint, Uri}, Code, Request};
+use serde::Deserialize;
+use orderbook::{Summary, Empty, BookSummaryRequest, AggregationMode, FeedStatusEvent, QuoteCostRequest, Side};
+use orderbook::quote_cost_request::Size;
 use orderbook::orderbook_aggregator_client::OrderbookAggregatorClient;
 
 const CONFIG_PATH: &str = "../config.json"; 
 const LOG_CONFIG_PATH: &str = "log_config.yaml";
 const PATH_SERVER: &str = "./www/dist/";
+/// First wait before calling the orderbook_server again, doubled after each failure
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Latest status of every exchange feed, keyed by exchange and symbol
+type FeedStatuses = RwLock<BTreeMap<(String, String), FeedStatusEvent>>;
 static INIT: Once = Once::new();
-pub fn setup_log() -> () { 
+pub fn setup_log() { 
     INIT.call_once(|| {    
         log4rs::init_file(LOG_CONFIG_PATH, Default::default()).unwrap();
     });
@@ -46,10 +62,48 @@ lazy_static! {
     };   
 }
 
-async fn ws_stream_rates(req: HttpRequest, stream: web::Payload) -> ActixResult<HttpResponse, Error> {
+/// Query of the rates websocket, a browser setting none of them gets the shared feed
+#[derive(Deserialize)]
+struct RatesQuery {
+    symbol: Option<String>,
+    depth: Option<u32>,
+    /// Comma separated, e.g. binance,bitstamp
+    exchanges: Option<String>,
+    /// Summaries per second at most
+    max_update_rate: Option<u32>,
+}
+
+async fn ws_stream_rates(req: HttpRequest, stream: web::Payload, query: web::Query<RatesQuery>,
+    client: web::Data<OrderbookAggregatorClient<Channel>>) -> ActixResult<HttpResponse, Error> {
     warn!("Websocket Called");
-    let resp = ws::start(WsSession::new(), &req, stream);
-    resp
+    let query = query.into_inner();
+    if query.symbol.is_none() && query.depth.is_none() && query.exchanges.is_none() && query.max_update_rate.is_none() {
+        return ws::start(WsSession::new(), &req, stream);
+    }
+    // the server validates the subscription, this session gets its own stream
+    let request = BookSummaryRequest{
+        symbol: query.symbol.unwrap_or_else(|| CONFIG.symbols().into_iter().next().unwrap_or_default()),
+        mode: AggregationMode::ByVenue as i32,
+        depth: query.depth.unwrap_or_default(),
+        exchanges: query.exchanges
+            .map(|exchanges| exchanges.split(',')
+                .map(|exchange| exchange.trim().to_string())
+                .filter(|exchange| !exchange.is_empty())
+                .collect())
+            .unwrap_or_default(),
+        max_update_rate: query.max_update_rate.unwrap_or_default(),
+    };
+    let mut client = client.get_ref().clone();
+    match client.book_summary(Request::new(request)).await {
+        Ok(response) => ws::start(WsSession::with_summary_stream(response.into_inner()), &req, stream),
+        Err(status) => match status.code() {
+            Code::InvalidArgument | Code::NotFound => Ok(HttpResponse::BadRequest().body(status.message().to_string())),
+            _ => {
+                log::error!("\n{:?}", status);
+                Ok(HttpResponse::InternalServerError().finish())
+            }
+        }
+    }
 }
 
 async fn data_stream(mut stream: tonic::Streaming<Summary>, feed_actor_addr: Addr<FeedActor>) -> Result<()> {
@@ -68,6 +122,154 @@ async fn data_stream(mut stream: tonic::Streaming<Summary>, feed_actor_addr: Add
     Ok(())
 }
 
+async fn status_stream(mut stream: tonic::Streaming<FeedStatusEvent>, statuses: web::Data<FeedStatuses>) -> Result<()> {
+    warn!("Status Stream Called");
+
+    while let Some(status) = stream.message().await? {
+        let mut statuses = statuses.write().unwrap_or_else(|err| err.into_inner());
+        statuses.insert((status.exchange.clone(), status.symbol.clone()), status);
+    }
+
+    Ok(())
+}
+
+/// Calls `call` until it succeeds, waiting twice as long after each failure up to `MAX_RETRY_DELAY`
+async fn with_backoff<T, E, F, Fut>(task_name: &str, mut call: F) -> T
+where
+    E: Debug,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>
+{
+    let mut delay = RETRY_DELAY;
+    loop {
+        match call().await {
+            Ok(value) => return value,
+            Err(err) => {
+                log::warn!("Warning in {:?}:\n{:?}\nRetrying in {:?}", task_name, err, delay);
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_RETRY_DELAY);
+            }
+        }
+    }
+}
+
+/// Feeds the page with the summaries of `symbol`, subscribing again whenever the stream ends
+async fn summary_task(client: OrderbookAggregatorClient<Channel>, symbol: String, feed_actor_addr: Addr<FeedActor>) {
+    let task_name = "--Summary Task--";
+    loop {
+        let stream = with_backoff(task_name, || {
+            let mut client = client.clone();
+            let request = BookSummaryRequest{symbol: symbol.clone(), mode: AggregationMode::ByVenue as i32, depth: 0, exchanges: Vec::new(), max_update_rate: 0};
+            async move { client.book_summary(Request::new(request)).await.map(|response| response.into_inner()) }
+        }).await;
+        if let Err(err) = data_stream(stream, feed_actor_addr.clone()).await {
+            log::warn!("Warning in {:?}:\n{:?}", task_name, err);
+        }
+    }
+}
+
+/// Keeps `statuses` up to date, subscribing again whenever the stream ends
+async fn feed_status_task(client: OrderbookAggregatorClient<Channel>, statuses: web::Data<FeedStatuses>) {
+    let task_name = "--Feed Status Task--";
+    loop {
+        let stream = with_backoff(task_name, || {
+            let mut client = client.clone();
+            async move { client.feed_status(Request::new(Empty{})).await.map(|response| response.into_inner()) }
+        }).await;
+        if let Err(err) = status_stream(stream, statuses.clone()).await {
+            log::warn!("Warning in {:?}:\n{:?}", task_name, err);
+        }
+    }
+}
+
+/// Health of the exchange feeds as JSON
+async fn feed_status(statuses: web::Data<FeedStatuses>) -> HttpResponse {
+    let statuses = statuses.read().unwrap_or_else(|err| err.into_inner());
+    HttpResponse::Ok().json(statuses.values().collect::<Vec<&FeedStatusEvent>>())
+}
+
+/// Instruments streamed by the orderbook_server as JSON, by canonical symbol then exchange
+async fn instruments(client: web::Data<OrderbookAggregatorClient<Channel>>) -> HttpResponse {
+    let mut client = client.get_ref().clone();
+    match client.list_instruments(Request::new(Empty{})).await {
+        Ok(response) => HttpResponse::Ok().json(response.into_inner().instruments),
+        Err(status) => {
+            log::error!("\n{:?}", status);
+            HttpResponse::InternalServerError().finish()
+        }
+    }
+}
+
+/// Prometheus metrics of the webserver
+async fn metrics_text() -> HttpResponse {
+    HttpResponse::Ok().content_type(metrics::CONTENT_TYPE).body(metrics::encode())
+}
+
+/// Query of the quote_cost endpoint, exactly one of the amounts is expected
+#[derive(Deserialize)]
+struct QuoteCostQuery {
+    symbol: String,
+    side: String,
+    base_amount: Option<f64>,
+    quote_amount: Option<f64>,
+}
+
+/// Cost of filling an order against the aggregated book as JSON
+async fn quote_cost(query: web::Query<QuoteCostQuery>, client: web::Data<OrderbookAggregatorClient<Channel>>) -> HttpResponse {
+    let query = query.into_inner();
+    let side = match query.side.to_lowercase().as_str() {
+        "buy" => Side::Buy,
+        "sell" => Side::Sell,
+        _ => return HttpResponse::BadRequest().body(format!("unknown side {}", query.side))
+    };
+    let size = match (query.base_amount, query.quote_amount) {
+        (Some(amount), None) => Size::BaseAmount(amount),
+        (None, Some(amount)) => Size::QuoteAmount(amount),
+        _ => return HttpResponse::BadRequest().body("one of base_amount or quote_amount is required")
+    };
+    let request = QuoteCostRequest{
+        symbol: query.symbol,
+        side: side as i32,
+        size: Some(size),
+    };
+    // the client is cheap to clone, it shares the channel
+    let mut client = client.get_ref().clone();
+    match client.quote_cost(Request::new(request)).await {
+        Ok(response) => HttpResponse::Ok().json(response.into_inner()),
+        Err(status) => match status.code() {
+            Code::InvalidArgument => HttpResponse::BadRequest().body(status.message().to_string()),
+            Code::NotFound => HttpResponse::NotFound().body(status.message().to_string()),
+            Code::FailedPrecondition => HttpResponse::Conflict().body(status.message().to_string()),
+            _ => {
+                log::error!("\n{:?}", status);
+                HttpResponse::InternalServerError().finish()
+            }
+        }
+    }
+}
+
+/// Channel to the orderbook_server, over TLS when the client side of the "tls" config is set.
+/// The TLS files are checked on each connection, a reconnection uses the renewed certificates.
+///
+/// Waits for the orderbook_server to be reachable, only a wrong config is an error
+async fn grpc_channel(grpc_server_add: &str, tls: &TlsConfig) -> Result<Channel> {
+    let task_name = "--gRPC Channel--";
+    let endpoint = Endpoint::from_shared(format!("http://{}", grpc_server_add))?;
+    let files = match &tls.client {
+        Some(files) => files,
+        None => return Ok(with_backoff(task_name, || endpoint.connect()).await)
+    };
+    let client_tls = Arc::new(ReloadingTls::client(files)?);
+    let addr = grpc_server_add.to_string();
+    let domain = files.domain.clone()
+        .unwrap_or_else(|| addr.rsplit_once(':').map(|(host, _)| host.to_string()).unwrap_or_default());
+    let connector = tower::service_fn(move |_: Uri| {
+        let (client_tls, addr, domain) = (client_tls.clone(), addr.clone(), domain.clone());
+        async move { client_tls.connect(&addr, &domain).await }
+    });
+    Ok(with_backoff(task_name, || endpoint.connect_with_connector(connector.clone())).await)
+}
+
 #[rustfmt::skip]
 #[actix_web::main(flavor = "multi_thread")]
 async fn main() ->  std::io::Result<()> {
@@ -78,29 +280,42 @@ async fn main() ->  std::io::Result<()> {
     warn!("Server INIT");
     let web_server_add = CONFIG.web_server.clone();
     
-    let grpc_server_add = format!("http://{}", CONFIG.grpc_server.clone());
-    let mut client = match OrderbookAggregatorClient::connect(grpc_server_add).await {
-        Ok(cli) => cli,
-        Err(err) => {
-            log::error!("\n{:?}", err);
-            panic!("\n{:?}", err);
-        }
-    };
-    let stream = match client.book_summary(Request::new(Empty{})).await {
-        Ok(cli) => cli.into_inner(),
+    let client = match grpc_channel(&CONFIG.grpc_server, &CONFIG.tls).await {
+        Ok(channel) => match CONFIG.auth.client_token.as_deref().map(|token| MetadataValue::from_str(&format!("Bearer {}", token))) {
+            None => OrderbookAggregatorClient::new(channel),
+            Some(Ok(authorization)) => OrderbookAggregatorClient::with_interceptor(channel, move |mut request: Request<()>| {
+                request.metadata_mut().insert("authorization", authorization.clone());
+                Ok(request)
+            }),
+            Some(Err(err)) => {
+                log::error!("\nInvalid client_token: {:?}", err);
+                panic!("\nInvalid client_token: {:?}", err);
+            }
+        },
         Err(err) => {
             log::error!("\n{:?}", err);
             panic!("\n{:?}", err);
         }
     };
+    // the page shows the first configured instrument
+    let symbol = CONFIG.symbols().into_iter().next().unwrap_or_default();
     let feed_actor_addr = FeedActor.start();
-    tokio::spawn(data_stream(stream, feed_actor_addr));
+    tokio::spawn(summary_task(client.clone(), symbol, feed_actor_addr));
+    let statuses: web::Data<FeedStatuses> = web::Data::new(RwLock::new(BTreeMap::new()));
+    tokio::spawn(feed_status_task(client.clone(), statuses.clone()));
+    let client = web::Data::new(client);
 
-    HttpServer::new(|| {
+    HttpServer::new(move || {
         let logger = Logger::default();
         App::new().wrap(logger)
+        .app_data(statuses.clone())
+        .app_data(client.clone())
         // .route("/index", web::get().to(index))
         .route("/rates", web::get().to(ws_stream_rates))
+        .route("/feed_status", web::get().to(feed_status))
+        .route("/quote_cost", web::get().to(quote_cost))
+        .route("/instruments", web::get().to(instruments))
+        .route("/metrics", web::get().to(metrics_text))
         .service(fs::Files::new("/", PATH_SERVER).index_file("index.html"))
     })
     .bind(web_server_add.clone())?
@@ -1,6 +1,15 @@
 tonic::include_proto!("orderbook");
 use serde::ser::{Serialize, Serializer, SerializeStruct};
 
+/// The exact decimal string, or the double of a server not sending it
+fn exact(decimal: &str, double: f64) -> String {
+    if decimal.is_empty() {
+        double.to_string()
+    } else {
+        decimal.to_string()
+    }
+}
+
 // #[derive(Clone, PartialEq, ::prost::Message)]
 // pub struct Summary {
 //     #[prost(double, tag = "1")]
@@ -24,24 +33,167 @@ impl Serialize for Summary {
     where
         S: Serializer,
     {
-        // 3 is the number of fields in the struct.
-        let mut state = serializer.serialize_struct("Summary", 3)?;
-        state.serialize_field("spread", &self.spread)?;
+        // 13 is the number of fields in the struct.
+        let mut state = serializer.serialize_struct("Summary", 13)?;
+        state.serialize_field("symbol", &self.symbol)?;
+        state.serialize_field("spread", &exact(&self.spread_decimal, self.spread))?;
         state.serialize_field("bids", &self.bids)?;
         state.serialize_field("asks", &self.asks)?;
+        state.serialize_field("consolidated_bids", &self.consolidated_bids)?;
+        state.serialize_field("consolidated_asks", &self.consolidated_asks)?;
+        state.serialize_field("synthetic", &self.synthetic)?;
+        state.serialize_field("legs", &self.legs)?;
+        state.serialize_field("stats", &self.stats)?;
+        state.serialize_field("venue_stats", &self.venue_stats)?;
+        state.serialize_field("sequence", &self.sequence)?;
+        state.serialize_field("publish_time", &self.publish_time)?;
+        state.serialize_field("exchange_updates", &self.exchange_updates)?;
+        state.end()
+    }
+}
+impl Serialize for ExchangeUpdate {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // 4 is the number of fields in the struct.
+        let mut state = serializer.serialize_struct("ExchangeUpdate", 4)?;
+        state.serialize_field("exchange", &self.exchange)?;
+        state.serialize_field("symbol", &self.symbol)?;
+        state.serialize_field("sequence", &self.sequence)?;
+        state.serialize_field("received_time", &self.received_time)?;
+        state.end()
+    }
+}
+impl Serialize for BookStats {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // 6 is the number of fields in the struct.
+        let mut state = serializer.serialize_struct("BookStats", 6)?;
+        state.serialize_field("exchange", &self.exchange)?;
+        state.serialize_field("mid", &self.mid)?;
+        state.serialize_field("microprice", &self.microprice)?;
+        state.serialize_field("imbalance", &self.imbalance)?;
+        state.serialize_field("depth", &self.depth)?;
+        state.serialize_field("weighted_spread_bps", &self.weighted_spread_bps)?;
+        state.end()
+    }
+}
+impl Serialize for DepthWithin {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // 3 is the number of fields in the struct.
+        let mut state = serializer.serialize_struct("DepthWithin", 3)?;
+        state.serialize_field("bps", &self.bps)?;
+        state.serialize_field("bid_amount", &self.bid_amount)?;
+        state.serialize_field("ask_amount", &self.ask_amount)?;
+        state.end()
+    }
+}
+impl Serialize for ConsolidatedLevel {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // 3 is the number of fields in the struct.
+        let mut state = serializer.serialize_struct("ConsolidatedLevel", 3)?;
+        state.serialize_field("price", &exact(&self.price_decimal, self.price))?;
+        state.serialize_field("amount", &exact(&self.amount_decimal, self.amount))?;
+        state.serialize_field("exchanges", &self.exchanges)?;
         state.end()
     }
 }
 impl Serialize for Level {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // 4 is the number of fields in the struct.
+        let mut state = serializer.serialize_struct("Level", 4)?;
+        state.serialize_field("exchange", &self.exchange)?;
+        state.serialize_field("price", &exact(&self.price_decimal, self.price))?;
+        state.serialize_field("amount", &exact(&self.amount_decimal, self.amount))?;
+        state.serialize_field("raw_price", &exact(&self.raw_price_decimal, self.raw_price))?;
+        state.end()
+    }
+}
+impl Serialize for FeedStatusEvent {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let state = match feed_status_event::State::from_i32(self.state) {
+            Some(state) => format!("{:?}", state),
+            None => "Unknown".to_string()
+        };
+        // 5 is the number of fields in the struct.
+        let mut state_struct = serializer.serialize_struct("FeedStatusEvent", 5)?;
+        state_struct.serialize_field("exchange", &self.exchange)?;
+        state_struct.serialize_field("symbol", &self.symbol)?;
+        state_struct.serialize_field("state", &state)?;
+        state_struct.serialize_field("reason", &self.reason)?;
+        state_struct.serialize_field("timestamp", &self.timestamp)?;
+        state_struct.end()
+    }
+}
+
+impl Serialize for QuoteCostResponse {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let side = match Side::from_i32(self.side) {
+            Some(side) => format!("{:?}", side),
+            None => "Unknown".to_string()
+        };
+        // 10 is the number of fields in the struct.
+        let mut state = serializer.serialize_struct("QuoteCostResponse", 10)?;
+        state.serialize_field("symbol", &self.symbol)?;
+        state.serialize_field("side", &side)?;
+        state.serialize_field("amount", &self.amount)?;
+        state.serialize_field("notional", &self.notional)?;
+        state.serialize_field("vwap", &self.vwap)?;
+        state.serialize_field("worst_price", &self.worst_price)?;
+        state.serialize_field("mid", &self.mid)?;
+        state.serialize_field("slippage_bps", &self.slippage_bps)?;
+        state.serialize_field("fills", &self.fills)?;
+        state.serialize_field("complete", &self.complete)?;
+        state.end()
+    }
+}
+impl Serialize for Fill {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
         // 3 is the number of fields in the struct.
-        let mut state = serializer.serialize_struct("Level", 3)?;
+        let mut state = serializer.serialize_struct("Fill", 3)?;
         state.serialize_field("exchange", &self.exchange)?;
-        state.serialize_field("price", &self.price)?;
         state.serialize_field("amount", &self.amount)?;
+        state.serialize_field("notional", &self.notional)?;
         state.end()
     }
-}
\ No newline at end of file
+}
+impl Serialize for Instrument {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let state = match feed_status_event::State::from_i32(self.state) {
+            Some(state) => format!("{:?}", state),
+            None => "Unknown".to_string()
+        };
+        // 5 is the number of fields in the struct.
+        let mut state_struct = serializer.serialize_struct("Instrument", 5)?;
+        state_struct.serialize_field("exchange", &self.exchange)?;
+        state_struct.serialize_field("symbol", &self.symbol)?;
+        state_struct.serialize_field("canonical_symbol", &self.canonical_symbol)?;
+        state_struct.serialize_field("state", &state)?;
+        state_struct.serialize_field("last_update_time", &self.last_update_time)?;
+        state_struct.end()
+    }
+}
@@ -3,28 +3,34 @@ import './App.css';
 import React, { useState, useEffect,  useRef} from "react";
 
 
-// import configData from "../config.json";
-let symbols = ["usgd", "ujpy", "uaud", "ukrw", "umnt", "uchf", 
-                "uhkd", "ucny", "uthb", "unok", "uusd", "ugbp", 
-                "ucad", "usdr", "ueur", "usek", "uinr", "udkk"]
-
-let prev_rates = symbols.reduce((acc, symbol) => {
-  acc[symbol] = 0.0;
-  return acc;
-}, {});
-
-
+// symbol, depth, exchanges and max_update_rate of the page query are passed through to the server, e.g. ?depth=10&exchanges=binance
+const query = new URLSearchParams(window.location.search);
+const depth = parseInt(query.get("depth")) || 20;
+
+// picking a symbol reloads the page with it in the query, the other parameters are kept
+function selectSymbol(symbol) {
+  query.set("symbol", symbol);
+  window.location.search = query.toString();
+}
 
 function App() {
-  const book = Array(41).fill(0);
-  const [previous_rates, setPrevious_rates] = useState(prev_rates);
+  const book = Array(2 * depth + 1).fill(0);
+  // canonical symbols streamed by the server, listed by the webserver from ListInstruments
+  const [symbols, setSymbols] = useState([]);
 
   const ws = useRef(null);
 
+  useEffect(() => {
+    fetch("/instruments")
+      .then(response => response.json())
+      .then(instruments => setSymbols([...new Set(instruments.map(instrument => instrument.canonical_symbol))]))
+      .catch(error => console.log("Instruments not loaded: " + error));
+  }, []);
+
   useEffect(() => {    
     if ("WebSocket" in window) {
       console.log("WebSocket is supported by your Browser!");
-      let url =  "@@@IP@@@";
+      let url =  "@@@IP@@@" + window.location.search;
       console.log("Websocket URL" + url );
       ws.current = new WebSocket(url);
       ws.current.onopen = function() {
@@ -40,7 +46,8 @@ function App() {
       // The browser doesn't support WebSocket
       console.log("WebSocket NOT supported by your Browser!");
    }
-  });
+  // connected once, not again when the symbols are loaded
+  }, []);
 
   useEffect(() => {  
     ws.current.onmessage = function (message) { 
@@ -62,52 +69,31 @@ function App() {
       if (!summary) return;
       let spread = summary.spread;
     
-      summary['asks'].forEach((level, index) => {
-        let row = document.getElementById(index);
+      // the asks end at the spread row, a thin book leaves the first rows empty,
+      // the levels beyond the rows of the page (a server default_depth above it) are not shown
+      let asks = summary['asks'].slice(-depth);
+      let offset = Math.max(depth - asks.length, 0);
+      asks.forEach((level, index) => {
+        let row = document.getElementById(index + offset);
         row.getElementsByTagName('td')[1].innerHTML = level.price;
         row.getElementsByTagName('td')[2].innerHTML = level.amount;
         row.getElementsByTagName('td')[3].innerHTML = level.exchange;
         row.className = 'asks';
       });
 
-      let row = document.getElementById(20);
+      let row = document.getElementById(depth);
       row.getElementsByTagName('td')[0].innerHTML = "Spread";
       row.getElementsByTagName('td')[1].innerHTML = spread;
       row.className = 'spread';
 
-      summary['bids'].reverse().forEach((level, index) => {
-        index = index + 21;
+      summary['bids'].reverse().slice(0, depth).forEach((level, index) => {
+        index = index + depth + 1;
         let row = document.getElementById(index);
         row.getElementsByTagName('td')[1].innerHTML = level.price;
         row.getElementsByTagName('td')[2].innerHTML = level.amount;
         row.getElementsByTagName('td')[3].innerHTML = level.exchange;
         row.className = 'bids';
       });
-      // console.log(myObj);
-      // symbols.forEach(function(symbol){
-      //       let previous = parseFloat(previous_rates[symbol]);
-      //       let current = parseFloat(myObj[symbol]);
-      //       let row = document.getElementById(symbol);
-      //       if (current !== 0.0) {
-      //          if (current > previous && previous !== 0.0){
-      //             row.getElementsByTagName('td')[1].innerHTML = current;
-      //             row.className = 'increase';
-
-      //          }
-      //          else if (current < previous && previous !== 0.0){
-      //             row.getElementsByTagName('td')[1].innerHTML = current;
-      //             row.className = 'decrease';
-      //          }
-      //          else {
-      //             row.getElementsByTagName('td')[1].innerHTML = current;
-      //             row.className = '';
-      //          }
-      //       }
-
-      //       previous_rates[symbol] = current;
-      //       setPrevious_rates(previous_rates);
-
-      //     });
     };  
   });
 
@@ -117,6 +103,9 @@ function App() {
   return (
       <div>
         <h1>Aggregated Orderbook</h1>
+        <select id="symbol" value={(query.get("symbol") || symbols[0] || "").toUpperCase()} onChange={event => selectSymbol(event.target.value)}>
+          { symbols.map(symbol => <option key={symbol} value={symbol}>{symbol}</option>) }
+        </select>
         <table  id="rates">
           <thead>
             <tr><th></th><th>Price</th><th>Volume</th><th>Exchange</th></tr>